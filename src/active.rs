@@ -0,0 +1,106 @@
+use flate2::read::ZlibDecoder;
+use std::io::Read;
+
+/// Things in a document that run or open something without being asked to, found by
+/// scanning the raw file, including compressed object streams.
+#[derive(Debug, Clone, Default)]
+pub struct ActiveContent {
+    pub javascript: bool,
+    /// An action run on opening, other than showing a destination.
+    pub open_action: bool,
+    /// Actions triggered by events such as opening a page or leaving a field.
+    pub auto_actions: bool,
+    /// Actions that launch other programs.
+    pub launch: bool,
+}
+
+impl ActiveContent {
+    pub fn is_empty(&self) -> bool {
+        !(self.javascript || self.open_action || self.auto_actions || self.launch)
+    }
+
+    /// A one-line warning, e.g. "This document contains JavaScript and an open action".
+    pub fn warning(&self) -> String {
+        let found: Vec<&str> = [
+            (self.javascript, "JavaScript"),
+            (self.open_action, "an open action"),
+            (self.auto_actions, "automatic actions"),
+            (self.launch, "actions that launch programs"),
+        ]
+        .into_iter()
+        .filter_map(|(present, name)| present.then_some(name))
+        .collect();
+        let list = match found.split_last() {
+            Some((last, [])) => last.to_string(),
+            Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
+            None => String::new(),
+        };
+        format!("This document contains {}; lukia does not run them", list)
+    }
+}
+
+pub fn scan(file: &[u8]) -> ActiveContent {
+    let mut content = ActiveContent::default();
+    scan_bytes(file, &mut content);
+    for stream in object_streams(file) {
+        scan_bytes(&stream, &mut content);
+    }
+    content
+}
+
+fn scan_bytes(bytes: &[u8], content: &mut ActiveContent) {
+    content.javascript |= has_name(bytes, b"/JS") || has_name(bytes, b"/JavaScript");
+    content.auto_actions |= has_name(bytes, b"/AA");
+    content.launch |= has_name(bytes, b"/Launch");
+    content.open_action |= names(bytes, b"/OpenAction").any(|end| {
+        // An array is a destination, which only selects the first page shown.
+        let value = bytes[end..].iter().find(|b| !b.is_ascii_whitespace());
+        value.is_some_and(|&b| b != b'[')
+    });
+}
+
+fn has_name(bytes: &[u8], name: &[u8]) -> bool {
+    names(bytes, name).next().is_some()
+}
+
+/// End offsets of each occurrence of the PDF name `name` (not a longer name starting
+/// with it).
+pub fn names<'a>(bytes: &'a [u8], name: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+    bytes
+        .windows(name.len())
+        .enumerate()
+        .filter(move |(_, window)| *window == name)
+        .map(move |(start, _)| start + name.len())
+        .filter(move |&end| bytes.get(end).is_none_or(|&b| !b.is_ascii_alphanumeric()))
+}
+
+/// The decompressed contents of every Flate-encoded object stream, where compressed
+/// files keep most of their dictionaries.
+pub fn object_streams(file: &[u8]) -> Vec<Vec<u8>> {
+    let mut streams = Vec::new();
+    for dictionary in names(file, b"/ObjStm") {
+        let Some(keyword) = find(&file[dictionary..], b"stream") else {
+            continue;
+        };
+        let mut start = dictionary + keyword + b"stream".len();
+        if file.get(start) == Some(&b'\r') {
+            start += 1;
+        }
+        if file.get(start) == Some(&b'\n') {
+            start += 1;
+        }
+        let end = find(&file[start..], b"endstream").map_or(file.len(), |end| start + end);
+
+        // Keep whatever decodes; a truncated stream still shows its first objects.
+        let mut decoded = Vec::new();
+        let _ = ZlibDecoder::new(&file[start..end]).read_to_end(&mut decoded);
+        streams.push(decoded);
+    }
+    streams
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,397 @@
+use iced::{Color, Rectangle};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::export::ExportFormat;
+use crate::theme::HexColor;
+
+/// Color names accepted wherever a markup color is expected.
+const NAMED_COLORS: &[(&str, [u8; 3])] = &[
+    ("yellow", [255, 235, 59]),
+    ("green", [139, 230, 111]),
+    ("blue", [120, 190, 255]),
+    ("pink", [255, 140, 200]),
+    ("orange", [255, 170, 70]),
+    ("red", [255, 99, 99]),
+    ("purple", [190, 150, 255]),
+];
+
+/// Text markup annotations that can be created from a visual selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkupKind {
+    Highlight,
+    Underline,
+    Strikeout,
+    Squiggly,
+}
+
+impl MarkupKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MarkupKind::Highlight => "highlight",
+            MarkupKind::Underline => "underline",
+            MarkupKind::Strikeout => "strikeout",
+            MarkupKind::Squiggly => "squiggly",
+        }
+    }
+}
+
+/// A markup color: one of `NAMED_COLORS` or `"#rrggbb"`.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct MarkupColor(pub Color);
+
+impl TryFrom<String> for MarkupColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some((_, [r, g, b])) = NAMED_COLORS.iter().find(|(name, _)| *name == value) {
+            return Ok(MarkupColor(Color::from_rgb8(*r, *g, *b)));
+        }
+        HexColor::try_from(value.clone())
+            .map(|color| MarkupColor(color.0))
+            .map_err(|_| {
+                format!(
+                    "invalid color '{}', expected \"#rrggbb\" or one of: {}",
+                    value,
+                    NAMED_COLORS
+                        .iter()
+                        .map(|(name, _)| *name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// The `[markup_colors]` table: the color used when a command names none.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkupColors {
+    pub highlight: MarkupColor,
+    pub underline: MarkupColor,
+    pub strikeout: MarkupColor,
+    pub squiggly: MarkupColor,
+}
+
+impl Default for MarkupColors {
+    fn default() -> Self {
+        Self {
+            highlight: MarkupColor(Color::from_rgb8(255, 235, 59)),
+            underline: MarkupColor(Color::from_rgb8(40, 110, 220)),
+            strikeout: MarkupColor(Color::from_rgb8(220, 40, 40)),
+            squiggly: MarkupColor(Color::from_rgb8(40, 160, 60)),
+        }
+    }
+}
+
+impl MarkupColors {
+    pub fn get(&self, kind: MarkupKind) -> Color {
+        match kind {
+            MarkupKind::Highlight => self.highlight.0,
+            MarkupKind::Underline => self.underline.0,
+            MarkupKind::Strikeout => self.strikeout.0,
+            MarkupKind::Squiggly => self.squiggly.0,
+        }
+    }
+}
+
+/// An existing annotation, as listed by `:annots`.
+#[derive(Debug, Clone)]
+pub struct DocumentAnnotation {
+    pub page_index: usize,
+    /// Relative to the page, origin top-left.
+    pub bounds: Rectangle,
+    /// The lines covered by a markup annotation, relative like `bounds`.
+    pub quads: Vec<Rectangle>,
+    /// The annotation subtype in lower case, e.g. `highlight` or `text`.
+    pub kind: String,
+    pub color: Option<Color>,
+    /// The text under a markup annotation.
+    pub text: String,
+    /// The annotation's comment.
+    pub note: String,
+}
+
+impl DocumentAnnotation {
+    /// The comment, or for markup without one the text it covers.
+    pub fn excerpt(&self) -> &str {
+        if self.note.is_empty() {
+            &self.text
+        } else {
+            &self.note
+        }
+    }
+}
+
+/// The exported form of an annotation; rectangles are `[x, y, width, height]`
+/// relative to the page with the origin top-left.
+#[derive(Serialize)]
+struct AnnotationRecord<'a> {
+    /// 1-based.
+    page: usize,
+    #[serde(rename = "type")]
+    kind: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<String>,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    text: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    note: &'a str,
+    rect: [f32; 4],
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    quads: Vec<[f32; 4]>,
+}
+
+fn rect_values(rect: &Rectangle) -> [f32; 4] {
+    [rect.x, rect.y, rect.width, rect.height]
+}
+
+fn hex(color: Color) -> String {
+    let [r, g, b, _] = color.into_rgba8();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Renders annotations as a JSON array, or as Markdown grouped by page with each
+/// highlight's text quoted and its note below.
+pub fn export(annotations: &[DocumentAnnotation], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => {
+            let records: Vec<AnnotationRecord> = annotations
+                .iter()
+                .map(|annotation| AnnotationRecord {
+                    page: annotation.page_index + 1,
+                    kind: &annotation.kind,
+                    color: annotation.color.map(hex),
+                    text: &annotation.text,
+                    note: &annotation.note,
+                    rect: rect_values(&annotation.bounds),
+                    quads: annotation.quads.iter().map(rect_values).collect(),
+                })
+                .collect();
+            serde_json::to_string_pretty(&records).unwrap_or_default()
+        }
+        ExportFormat::Markdown => {
+            let mut pages: BTreeMap<usize, Vec<&DocumentAnnotation>> = BTreeMap::new();
+            for annotation in annotations {
+                pages
+                    .entry(annotation.page_index)
+                    .or_default()
+                    .push(annotation);
+            }
+            let mut output = String::new();
+            for (page_index, annotations) in pages {
+                output.push_str(&format!("## Page {}\n\n", page_index + 1));
+                for annotation in annotations {
+                    let color = annotation
+                        .color
+                        .map(|color| format!(" `{}`", hex(color)))
+                        .unwrap_or_default();
+                    output.push_str(&format!("- **{}**{}", annotation.kind, color));
+                    if !annotation.text.is_empty() {
+                        output.push_str(&format!(": \"{}\"", annotation.text));
+                    }
+                    output.push('\n');
+                    if !annotation.note.is_empty() {
+                        output.push_str(&format!("  - {}\n", annotation.note));
+                    }
+                }
+                output.push('\n');
+            }
+            output
+        }
+    }
+}
+
+/// What an imported annotation becomes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportKind {
+    Markup(MarkupKind),
+    Note,
+}
+
+impl ImportKind {
+    /// Matches `DocumentAnnotation::kind` and the `type` of exported records.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "highlight" => Some(ImportKind::Markup(MarkupKind::Highlight)),
+            "underline" => Some(ImportKind::Markup(MarkupKind::Underline)),
+            "strikeout" => Some(ImportKind::Markup(MarkupKind::Strikeout)),
+            "squiggly" => Some(ImportKind::Markup(MarkupKind::Squiggly)),
+            "text" => Some(ImportKind::Note),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ImportKind::Markup(kind) => kind.name(),
+            ImportKind::Note => "text",
+        }
+    }
+}
+
+/// How the rectangles of an imported annotation are measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Coordinates {
+    /// Relative to the page with the origin top-left, as `:annots export` writes them.
+    Relative,
+    /// PDF points with the origin bottom-left, as in XFDF; `y` is the bottom edge.
+    Points,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImportedAnnotation {
+    pub page_index: usize,
+    pub kind: ImportKind,
+    pub coordinates: Coordinates,
+    pub rect: Rectangle,
+    pub quads: Vec<Rectangle>,
+    pub color: Option<Color>,
+    pub note: String,
+}
+
+/// The JSON written by `export`; `text` is ignored since it comes from the page.
+#[derive(Deserialize)]
+struct ImportRecord {
+    page: usize,
+    #[serde(rename = "type")]
+    kind: String,
+    color: Option<String>,
+    #[serde(default)]
+    note: String,
+    rect: [f32; 4],
+    #[serde(default)]
+    quads: Vec<[f32; 4]>,
+}
+
+/// Reads annotations exported as JSON, or XFDF for `.xfdf` and `.xml` files. Returns
+/// the supported annotations and the number of unsupported ones left out.
+pub fn import(path: &Path) -> Result<(Vec<ImportedAnnotation>, usize), String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let invalid = |e: String| format!("Invalid annotations file {}: {}", path.display(), e);
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("xfdf" | "xml") => import_xfdf(&contents).map_err(invalid),
+        _ => import_json(&contents).map_err(invalid),
+    }
+}
+
+fn import_json(contents: &str) -> Result<(Vec<ImportedAnnotation>, usize), String> {
+    let records: Vec<ImportRecord> = serde_json::from_str(contents).map_err(|e| e.to_string())?;
+    let total = records.len();
+    let imported: Vec<ImportedAnnotation> = records
+        .into_iter()
+        .filter_map(|record| {
+            Some(ImportedAnnotation {
+                page_index: record.page.checked_sub(1)?,
+                kind: ImportKind::from_name(&record.kind)?,
+                coordinates: Coordinates::Relative,
+                rect: rect_from_values(record.rect),
+                quads: record.quads.into_iter().map(rect_from_values).collect(),
+                color: record
+                    .color
+                    .and_then(|color| MarkupColor::try_from(color).ok())
+                    .map(|color| color.0),
+                note: record.note,
+            })
+        })
+        .collect();
+    let unsupported = total - imported.len();
+    Ok((imported, unsupported))
+}
+
+/// Reads the `<annots>` of an XFDF document. Pages are 0-based and rectangles are
+/// `x1,y1,x2,y2` in points; markup lines come from `coords`, eight numbers per quad.
+fn import_xfdf(contents: &str) -> Result<(Vec<ImportedAnnotation>, usize), String> {
+    let document = roxmltree::Document::parse(contents).map_err(|e| e.to_string())?;
+    let Some(annots) = document
+        .descendants()
+        .find(|node| node.has_tag_name("annots"))
+    else {
+        return Ok((Vec::new(), 0));
+    };
+
+    let mut imported = Vec::new();
+    let mut unsupported = 0;
+    for node in annots.children().filter(|node| node.is_element()) {
+        match xfdf_annotation(node) {
+            Some(annotation) => imported.push(annotation),
+            None => unsupported += 1,
+        }
+    }
+    Ok((imported, unsupported))
+}
+
+/// One XFDF annotation element, or `None` for unsupported types and malformed entries.
+fn xfdf_annotation(node: roxmltree::Node) -> Option<ImportedAnnotation> {
+    let kind = ImportKind::from_name(node.tag_name().name())?;
+    let page_index = node.attribute("page")?.trim().parse().ok()?;
+    let rect = match numbers(node.attribute("rect")?)[..] {
+        [x1, y1, x2, y2] => points_rect(x1, y1, x2, y2),
+        _ => return None,
+    };
+    let quads = node
+        .attribute("coords")
+        .map(|coords| {
+            numbers(coords)
+                .chunks_exact(8)
+                .map(|quad| {
+                    let xs = [quad[0], quad[2], quad[4], quad[6]];
+                    let ys = [quad[1], quad[3], quad[5], quad[7]];
+                    points_rect(
+                        xs.iter().copied().fold(f32::MAX, f32::min),
+                        ys.iter().copied().fold(f32::MAX, f32::min),
+                        xs.iter().copied().fold(f32::MIN, f32::max),
+                        ys.iter().copied().fold(f32::MIN, f32::max),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let note = node
+        .children()
+        .find(|child| child.has_tag_name("contents"))
+        .and_then(|child| child.text())
+        .unwrap_or_default()
+        .to_string();
+    Some(ImportedAnnotation {
+        page_index,
+        kind,
+        coordinates: Coordinates::Points,
+        rect,
+        quads,
+        color: node
+            .attribute("color")
+            .and_then(|color| MarkupColor::try_from(color.to_lowercase()).ok())
+            .map(|color| color.0),
+        note,
+    })
+}
+
+fn numbers(list: &str) -> Vec<f32> {
+    list.split(',')
+        .filter_map(|number| number.trim().parse().ok())
+        .collect()
+}
+
+fn rect_from_values([x, y, width, height]: [f32; 4]) -> Rectangle {
+    Rectangle {
+        x,
+        y,
+        width,
+        height,
+    }
+}
+
+/// A rectangle in points from two corners, stored with `y` as the bottom edge.
+fn points_rect(x1: f32, y1: f32, x2: f32, y2: f32) -> Rectangle {
+    Rectangle {
+        x: x1.min(x2),
+        y: y1.min(y2),
+        width: (x2 - x1).abs(),
+        height: (y2 - y1).abs(),
+    }
+}
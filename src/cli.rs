@@ -0,0 +1,626 @@
+use clap::Subcommand;
+use clap_complete::Shell;
+use image::RgbaImage;
+use pdfium_render::prelude::PdfiumError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::annotations;
+use crate::config::ViewerConfig;
+use crate::engine;
+use crate::export::ExportFormat;
+use crate::library::{self, LibraryIndex};
+use crate::outline;
+use crate::pages;
+use crate::pdf::{self, PdfRenderer, RenderSettings};
+
+pub const LIBRARY_EMPTY: &str = "The library index is empty; build it with `index <dir>` first";
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CliCommand {
+    /// Build or update the text index for every PDF under a directory
+    Index {
+        dir: PathBuf,
+        /// Keep running and re-scan the directory periodically
+        #[arg(long)]
+        watch: bool,
+        /// Seconds between re-scans in watch mode
+        #[arg(long, default_value_t = 300)]
+        interval: u64,
+    },
+    /// Search the library index and open the best-matching document
+    Find {
+        #[arg(required = true, num_args = 1..)]
+        query: Vec<String>,
+        /// Print the ranked matches instead of opening the best one
+        #[arg(long)]
+        list: bool,
+        /// Maximum number of matches to print with --list
+        #[arg(short = 'n', long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Print the lines of PDFs that contain a pattern, as `file:page: line`
+    Grep {
+        pattern: String,
+        /// PDFs, or directories to search for PDFs
+        #[arg(required = true, num_args = 1..)]
+        files: Vec<PathBuf>,
+        #[arg(short = 'i', long)]
+        ignore_case: bool,
+    },
+    /// Print a document's outline with page numbers
+    Toc {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+    },
+    /// Print a document's annotations: highlighted text, notes, pages and colors
+    Annots {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ExportFormat::Markdown)]
+        format: ExportFormat,
+    },
+    /// Render pages to PNG files without opening a window
+    Render {
+        file: PathBuf,
+        /// Pages to render, such as `1-5,8` or `10-`; all pages by default
+        #[arg(long)]
+        pages: Option<String>,
+        /// Image width in pixels
+        #[arg(long, default_value_t = 1600)]
+        width: u16,
+        /// Output file, with `%d` replaced by the page number [default: <file>-%d.png]
+        #[arg(short = 'o', long)]
+        output: Option<String>,
+    },
+    /// Write a small PNG of every page into a directory
+    Thumbs {
+        file: PathBuf,
+        /// Longest side of each image in pixels
+        #[arg(long, default_value_t = 200)]
+        size: u16,
+        /// Directory for the images, created if missing
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+    },
+    /// Copy a range of pages to a new PDF
+    Extract {
+        file: PathBuf,
+        /// Pages to copy, such as `10-20` or `1,3,5-`, in the order given
+        #[arg(long)]
+        pages: String,
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+    },
+    /// Rotate pages and save the result
+    Rotate {
+        file: PathBuf,
+        /// Pages to rotate, such as `3,7` or `2-`; all pages by default
+        #[arg(long)]
+        pages: Option<String>,
+        /// Clockwise angle in degrees, a multiple of 90; negative turns counterclockwise
+        #[arg(long, allow_hyphen_values = true)]
+        angle: i32,
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+    },
+    /// Remove pages and save the result
+    Delete {
+        file: PathBuf,
+        /// Pages to remove, such as `1,14-16`
+        #[arg(long)]
+        pages: String,
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+    },
+    /// Print page count, page sizes, metadata, encryption and outline depth
+    Info {
+        file: PathBuf,
+        /// Print JSON for other programs instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Combine PDFs into one, optionally taking only some pages of each
+    Merge {
+        /// Input files in order; `file.pdf:1-3,7` takes only those pages
+        #[arg(required = true, num_args = 2..)]
+        inputs: Vec<String>,
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+    },
+    /// Print a shell completion script for lukia and its subcommands
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+/// `info --json` output; sizes are `[width, height]` in points.
+#[derive(Serialize)]
+struct InfoReport<'a> {
+    pages: usize,
+    page_sizes: Vec<[f32; 2]>,
+    /// Non-empty fields, keyed by lowercase name.
+    metadata: BTreeMap<String, &'a str>,
+    encrypted: bool,
+    permissions: &'a pdf::Permissions,
+    /// Levels of nesting in the outline; 0 without one.
+    outline_depth: usize,
+}
+
+/// A document the viewer should be launched with once the subcommand finishes.
+pub struct OpenRequest {
+    pub file_name: String,
+    pub page_index: usize,
+}
+
+/// `app` describes the whole command line, for completions.
+pub fn run(
+    command: CliCommand,
+    config_path: Option<&Path>,
+    mut app: clap::Command,
+) -> Result<Option<OpenRequest>, Box<dyn Error>> {
+    match command {
+        CliCommand::Index {
+            dir,
+            watch,
+            interval,
+        } => run_index(&dir, watch, interval).map(|_| None),
+        CliCommand::Find { query, list, limit } => run_find(&query.join(" "), list, limit),
+        CliCommand::Grep {
+            pattern,
+            files,
+            ignore_case,
+        } => run_grep(&pattern, &files, ignore_case).map(|_| None),
+        CliCommand::Toc { file, format } => run_toc(&file, format).map(|_| None),
+        CliCommand::Annots { file, format } => run_annots(&file, format).map(|_| None),
+        CliCommand::Render {
+            file,
+            pages,
+            width,
+            output,
+        } => run_render(&file, pages.as_deref(), width, output, config_path).map(|_| None),
+        CliCommand::Thumbs { file, size, output } => {
+            run_thumbs(&file, size, &output, config_path).map(|_| None)
+        }
+        CliCommand::Extract {
+            file,
+            pages,
+            output,
+        } => run_extract(&file, &pages, &output).map(|_| None),
+        CliCommand::Rotate {
+            file,
+            pages,
+            angle,
+            output,
+        } => run_rotate(&file, pages.as_deref(), angle, &output).map(|_| None),
+        CliCommand::Delete {
+            file,
+            pages,
+            output,
+        } => run_delete(&file, &pages, &output).map(|_| None),
+        CliCommand::Merge { inputs, output } => run_merge(&inputs, &output).map(|_| None),
+        CliCommand::Info { file, json } => run_info(&file, json).map(|_| None),
+        CliCommand::Completions { shell } => {
+            let name = app.get_name().to_string();
+            clap_complete::generate(shell, &mut app, name, &mut io::stdout());
+            Ok(None)
+        }
+    }
+}
+
+fn run_index(dir: &Path, watch: bool, interval: u64) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    let mut index = LibraryIndex::load()?;
+
+    loop {
+        let stats = index.update_root(&pdfium, dir)?;
+        println!(
+            "{} indexed, {} unchanged, {} removed, {} failed ({} documents in library)",
+            stats.indexed,
+            stats.unchanged,
+            stats.removed,
+            stats.failed,
+            index.documents.len()
+        );
+
+        if !watch {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+fn run_find(query: &str, list: bool, limit: usize) -> Result<Option<OpenRequest>, Box<dyn Error>> {
+    let index = LibraryIndex::load()?;
+    if index.documents.is_empty() {
+        return Err(LIBRARY_EMPTY.into());
+    }
+
+    let hits = index.search(query, if list { limit } else { 1 });
+    if hits.is_empty() {
+        return Err(format!("No matches for: {}", query).into());
+    }
+
+    if list {
+        for hit in &hits {
+            println!(
+                "{}:{}: {}",
+                hit.path.display(),
+                hit.page_index + 1,
+                hit.snippet
+            );
+        }
+        return Ok(None);
+    }
+
+    let best = &hits[0];
+    Ok(Some(OpenRequest {
+        file_name: best.path.to_string_lossy().into_owned(),
+        page_index: best.page_index,
+    }))
+}
+
+/// Searches the text the library index stores, extracting it for files that are
+/// not indexed or have changed since.
+fn run_grep(pattern: &str, files: &[PathBuf], ignore_case: bool) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    // Without a usable index every file is read directly.
+    let index = LibraryIndex::load().unwrap_or_default();
+    let needle = if ignore_case {
+        pattern.to_lowercase()
+    } else {
+        pattern.to_string()
+    };
+
+    let mut paths = Vec::new();
+    for file in files {
+        if file.is_dir() {
+            library::collect_pdfs(file, &mut paths);
+        } else {
+            paths.push(file.clone());
+        }
+    }
+
+    let mut matches = 0;
+    for path in &paths {
+        let extracted;
+        let pages = match index.cached_pages(path) {
+            Some(pages) => pages,
+            None => match library::extract_pages(&pdfium, path) {
+                Ok(pages) => {
+                    extracted = pages;
+                    &extracted[..]
+                }
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", path.display(), e);
+                    continue;
+                }
+            },
+        };
+        for (page_index, text) in pages.iter().enumerate() {
+            for line in text.lines() {
+                let found = if ignore_case {
+                    line.to_lowercase().contains(&needle)
+                } else {
+                    line.contains(&needle)
+                };
+                if found {
+                    let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+                    println!("{}:{}: {}", path.display(), page_index + 1, line);
+                    matches += 1;
+                }
+            }
+        }
+    }
+    if matches == 0 {
+        return Err(format!("No matches for: {}", pattern).into());
+    }
+    Ok(())
+}
+
+fn run_toc(file: &Path, format: ExportFormat) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let items = outline::read_outline(&document);
+    if items.is_empty() {
+        return Err(format!("{} has no outline", file.display()).into());
+    }
+    print!("{}", outline::export(&items, format));
+    if format == ExportFormat::Json {
+        println!();
+    }
+    Ok(())
+}
+
+fn run_annots(file: &Path, format: ExportFormat) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let items = pdf::document_annotations(&document);
+    if items.is_empty() {
+        return Err(format!("{} has no annotations", file.display()).into());
+    }
+    print!("{}", annotations::export(&items, format));
+    if format == ExportFormat::Json {
+        println!();
+    }
+    Ok(())
+}
+
+fn run_render(
+    file: &Path,
+    pages: Option<&str>,
+    width: u16,
+    output: Option<String>,
+    config_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    // The page background and size limit follow the config, as in the viewer.
+    let config = ViewerConfig::load(config_path)?.for_document(file)?;
+    let pdfium = engine::init_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let page_indices = pages::page_indices(pages, document.pages().len() as usize)?;
+    let output = output.unwrap_or_else(|| {
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+        format!("{}-%d.png", stem)
+    });
+    if page_indices.len() > 1 && !output.contains("%d") {
+        return Err("The output name needs a %d to render several pages".into());
+    }
+
+    let renderer = PdfRenderer::new(document, RenderSettings::from_config(&config));
+    for page_index in page_indices {
+        let (image, clamped) = renderer.render_image(page_index, width)?;
+        let path = output.replace("%d", &(page_index + 1).to_string());
+        image
+            .save(&path)
+            .map_err(|e| format!("Could not write {}: {}", path, e))?;
+        if clamped {
+            eprintln!(
+                "Page {} was rendered {} pixels wide to stay within max_render_pixels",
+                page_index + 1,
+                image.width()
+            );
+        }
+        println!("{}", path);
+    }
+    Ok(())
+}
+
+fn run_thumbs(
+    file: &Path,
+    size: u16,
+    output: &Path,
+    config_path: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let config = ViewerConfig::load(config_path)?.for_document(file)?;
+    let pdfium = engine::init_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let page_count = document.pages().len() as usize;
+    fs::create_dir_all(output)
+        .map_err(|e| format!("Could not create {}: {}", output.display(), e))?;
+
+    // pdfium renders one page at a time, so pages are rendered here from the one
+    // document and PNG-encoded by workers, which is where most of the time goes.
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let (image_tx, image_rx) = mpsc::sync_channel::<(PathBuf, RgbaImage)>(workers * 2);
+    let image_rx = Arc::new(Mutex::new(image_rx));
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let image_rx = image_rx.clone();
+            thread::spawn(move || -> Result<usize, String> {
+                let mut written = 0;
+                loop {
+                    let received = image_rx.lock().unwrap().recv();
+                    let Ok((path, image)) = received else {
+                        return Ok(written);
+                    };
+                    image
+                        .save(&path)
+                        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+                    written += 1;
+                }
+            })
+        })
+        .collect();
+    drop(image_rx);
+
+    let renderer = PdfRenderer::new(document, RenderSettings::from_config(&config));
+    let digits = page_count.to_string().len();
+    let mut rendered = Ok(());
+    for page_index in 0..page_count {
+        let image = match renderer.render_thumbnail(page_index, size) {
+            Ok(image) => image,
+            Err(e) => {
+                rendered = Err(e);
+                break;
+            }
+        };
+        let path = output.join(format!("page-{:0digits$}.png", page_index + 1));
+        // Fails only once every worker has stopped on an error.
+        if image_tx.send((path, image)).is_err() {
+            break;
+        }
+    }
+    drop(image_tx);
+
+    let mut written = 0;
+    for handle in handles {
+        written += handle
+            .join()
+            .map_err(|_| "A thumbnail worker panicked".to_string())??;
+    }
+    rendered?;
+    println!("Wrote {} thumbnails to {}", written, output.display());
+    Ok(())
+}
+
+fn run_extract(file: &Path, pages: &str, output: &Path) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    let source = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let page_indices = pages::page_indices(Some(pages), source.pages().len() as usize)?;
+
+    let failed = |e: PdfiumError| format!("Could not copy pages: {}", e);
+    let mut document = pdfium.create_new_pdf().map_err(failed)?;
+    pages::copy_pages(&mut document, &source, &page_indices).map_err(failed)?;
+    pdf::write_document(&document, output)?;
+    println!("Wrote {} pages to {}", page_indices.len(), output.display());
+    Ok(())
+}
+
+fn run_rotate(
+    file: &Path,
+    pages: Option<&str>,
+    angle: i32,
+    output: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if angle % 90 != 0 {
+        return Err(format!("The angle must be a multiple of 90, not {}", angle).into());
+    }
+    let pdfium = engine::init_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let page_indices = pages::page_indices(pages, document.pages().len() as usize)?;
+    pages::rotate_pages(&document, &page_indices, angle / 90)
+        .map_err(|e| format!("Could not rotate pages: {}", e))?;
+    pdf::write_document(&document, output)?;
+    println!(
+        "Rotated {} pages; wrote {}",
+        page_indices.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn run_delete(file: &Path, pages: &str, output: &Path) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let page_count = document.pages().len() as usize;
+    let page_indices = pages::page_indices(Some(pages), page_count)?;
+    if (0..page_count).all(|page_index| page_indices.contains(&page_index)) {
+        return Err("Cannot delete every page of the document".into());
+    }
+    pages::delete_pages(&document, &page_indices)
+        .map_err(|e| format!("Could not delete pages: {}", e))?;
+    pdf::write_document(&document, output)?;
+    println!(
+        "Wrote {} pages to {}",
+        document.pages().len(),
+        output.display()
+    );
+    Ok(())
+}
+
+fn run_info(file: &Path, json: bool) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    let document = pdfium
+        .load_pdf_from_file(file, None)
+        .map_err(|e| format!("Could not open {}: {}", file.display(), e))?;
+    let info = pdf::read_info(&document);
+    let page_sizes: Vec<[f32; 2]> = document
+        .pages()
+        .page_sizes()
+        .map_err(|e| format!("Could not read page sizes: {}", e))?
+        .iter()
+        .map(|rect| [rect.width().value, rect.height().value])
+        .collect();
+    let outline_depth = outline::read_outline(&document)
+        .iter()
+        .map(|item| item.depth + 1)
+        .max()
+        .unwrap_or(0);
+
+    if json {
+        let report = InfoReport {
+            pages: page_sizes.len(),
+            page_sizes,
+            metadata: info
+                .metadata
+                .iter()
+                .map(|(label, value)| (label.to_lowercase(), value.as_str()))
+                .collect(),
+            encrypted: info.encrypted,
+            permissions: &info.permissions,
+            outline_depth,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    for (label, value) in &info.metadata {
+        println!("{}: {}", label, value);
+    }
+    println!("Pages: {}", page_sizes.len());
+    // Each distinct size once, with the number of pages that have it.
+    let mut sizes: Vec<([f32; 2], usize)> = Vec::new();
+    for size in &page_sizes {
+        match sizes.iter_mut().find(|(known, _)| known == size) {
+            Some((_, count)) => *count += 1,
+            None => sizes.push((*size, 1)),
+        }
+    }
+    for ([width, height], count) in sizes {
+        println!("Page size: {} x {} pt ({} pages)", width, height, count);
+    }
+    println!("Encrypted: {}", if info.encrypted { "yes" } else { "no" });
+    println!("Outline depth: {}", outline_depth);
+    Ok(())
+}
+
+fn run_merge(inputs: &[String], output: &Path) -> Result<(), Box<dyn Error>> {
+    let pdfium = engine::init_pdfium()?;
+    let failed = |e: PdfiumError| format!("Could not copy pages: {}", e);
+    let mut document = pdfium.create_new_pdf().map_err(failed)?;
+    for input in inputs {
+        let (file, pages) = split_page_list(input);
+        let source = pdfium
+            .load_pdf_from_file(file, None)
+            .map_err(|e| format!("Could not open {}: {}", file, e))?;
+        let page_indices = pages::page_indices(pages, source.pages().len() as usize)
+            .map_err(|e| format!("{}: {}", file, e))?;
+        pages::copy_pages(&mut document, &source, &page_indices).map_err(failed)?;
+    }
+    pdf::write_document(&document, output)?;
+    println!(
+        "Wrote {} pages to {}",
+        document.pages().len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Splits `file.pdf:1-3,7` into the file and its page list. A suffix that is not a
+/// page list, such as the rest of `C:\file.pdf`, stays part of the file name.
+fn split_page_list(input: &str) -> (&str, Option<&str>) {
+    match input.rsplit_once(':') {
+        Some((file, pages))
+            if !file.is_empty()
+                && !pages.is_empty()
+                && pages
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || matches!(c, ',' | '-' | ' ')) =>
+        {
+            (file, Some(pages))
+        }
+        _ => (input, None),
+    }
+}
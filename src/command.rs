@@ -0,0 +1,312 @@
+use crate::annotations::MarkupKind;
+
+pub enum Command {
+    GoToPage(usize),
+    Marks,
+    Jumps,
+    Messages,
+    Toc,
+    /// `:toc export [file]`
+    TocExport(Option<String>),
+    Links,
+    Heading(String),
+    LibFind(String),
+    /// `:highlight [color]` and the other markup commands, over the visual selection.
+    Markup {
+        kind: MarkupKind,
+        color: Option<String>,
+    },
+    /// `:note <text>` adds a note; `:note` alone lists the notes on the page.
+    Note(Option<String>),
+    /// `:annots`
+    Annotations,
+    /// `:annots export [file]`
+    AnnotationsExport(Option<String>),
+    /// `:annots import <file>`
+    AnnotationsImport(String),
+    /// `:info`
+    Info,
+    /// `:buffers` / `:ls`
+    Buffers,
+    /// `:recent`
+    Recent,
+    /// `:open [file]` / `:e [file]`; without a file, a picker.
+    Open(Option<String>),
+    /// `:bnext` / `:bn`
+    NextBuffer,
+    /// `:bprevious` / `:bp` / `:bN`
+    PreviousBuffer,
+    /// `:buffer N` / `:b N`, counting from 1.
+    Buffer(usize),
+    /// `:vsplit [file]` / `:vs [file]`
+    VSplit(Option<String>),
+    /// `:newwindow <file>`
+    NewWindow(String),
+    /// `:mksession [file]` / `:mks [file]`
+    MakeSession(Option<String>),
+    /// `:only` / `:on`
+    Only,
+    /// `:close` / `:clo`
+    Close,
+    /// `:signatures`
+    Signatures,
+    /// `:attachments`
+    Attachments,
+    /// `:attachments save <n> [file]`, `n` counting from 1.
+    AttachmentSave(usize, Option<String>),
+    /// `:draw` toggles freehand drawing.
+    Draw,
+    /// `:form` toggles filling in form fields.
+    Form,
+    /// `:export png [pages] [--dpi N] [file]` writes pages as images; the current page
+    /// next to the document by default.
+    ExportImages {
+        pages: Option<String>,
+        dpi: Option<f32>,
+        path: Option<String>,
+    },
+    /// `:export [--flatten] <file>` writes a copy of the document.
+    Export {
+        path: String,
+        flatten: bool,
+    },
+    /// `:w --encrypt [file]` asks for passwords, then writes an encrypted file.
+    WriteEncrypted(Option<String>),
+    /// `:w [file]`
+    Write(Option<String>),
+    Set {
+        option: String,
+        value: SetValue,
+    },
+    /// Anything not built in; may be provided by a plugin.
+    External {
+        name: String,
+        argument: String,
+    },
+}
+
+/// The forms of `:set`: `name`, `noname`, `name!`, `name?` and `name=value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SetValue {
+    On,
+    Off,
+    Toggle,
+    Query,
+    Value(String),
+}
+
+impl SetValue {
+    /// Applies the value to a boolean option, returning the status text for `name?`.
+    /// `name=on` and `name=off` work as well as `name` and `noname`.
+    pub fn apply_flag(&self, option: &str, flag: &mut bool) -> Result<Option<String>, String> {
+        match self {
+            SetValue::On => *flag = true,
+            SetValue::Off => *flag = false,
+            SetValue::Value(value) if matches!(value.as_str(), "on" | "true" | "yes") => {
+                *flag = true
+            }
+            SetValue::Value(value) if matches!(value.as_str(), "off" | "false" | "no") => {
+                *flag = false
+            }
+            SetValue::Toggle => *flag = !*flag,
+            SetValue::Query => {
+                return Ok(Some(format!("{}{}", if *flag { "" } else { "no" }, option)));
+            }
+            SetValue::Value(_) => return Err(format!("Invalid argument: {}", option)),
+        }
+        Ok(None)
+    }
+}
+
+fn parse_set(argument: &str) -> Result<Command, String> {
+    let (option, value) = if let Some((option, value)) = argument.split_once('=') {
+        (option, SetValue::Value(value.trim().to_string()))
+    } else if let Some(option) = argument.strip_suffix('!') {
+        (option, SetValue::Toggle)
+    } else if let Some(option) = argument.strip_suffix('?') {
+        (option, SetValue::Query)
+    } else if let Some(option) = argument.strip_prefix("no") {
+        (option, SetValue::Off)
+    } else {
+        (argument, SetValue::On)
+    };
+    Ok(Command::Set {
+        option: option.trim().to_string(),
+        value,
+    })
+}
+
+fn markup(kind: MarkupKind, color: &str) -> Command {
+    Command::Markup {
+        kind,
+        color: (!color.is_empty()).then(|| color.to_string()),
+    }
+}
+
+impl Command {
+    /// Whether it writes files or starts programs, which user scripts may not do.
+    pub fn writes_or_runs(&self) -> bool {
+        matches!(
+            self,
+            Command::Write(_)
+                | Command::WriteEncrypted(_)
+                | Command::Export { .. }
+                | Command::ExportImages { .. }
+                | Command::MakeSession(_)
+                | Command::AttachmentSave(..)
+                | Command::TocExport(_)
+                | Command::AnnotationsExport(_)
+                | Command::External { .. }
+        )
+    }
+}
+
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim();
+
+    if let Ok(page_num) = input.parse::<usize>() {
+        return Ok(Command::GoToPage(page_num));
+    }
+
+    let (name, argument) = match input.split_once(' ') {
+        Some((name, argument)) => (name, argument.trim()),
+        None => (input, ""),
+    };
+
+    match name {
+        "marks" => Ok(Command::Marks),
+        "buffers" | "ls" => Ok(Command::Buffers),
+        "recent" => Ok(Command::Recent),
+        "open" | "o" | "edit" | "e" if argument.is_empty() => Ok(Command::Open(None)),
+        "open" | "o" | "edit" | "e" => Ok(Command::Open(Some(argument.to_string()))),
+        "bnext" | "bn" => Ok(Command::NextBuffer),
+        "bprevious" | "bp" | "bN" => Ok(Command::PreviousBuffer),
+        "buffer" | "b" => match argument.parse() {
+            Ok(number) => Ok(Command::Buffer(number)),
+            Err(_) => Err("Usage: buffer <n>".to_string()),
+        },
+        "vsplit" | "vs" if argument.is_empty() => Ok(Command::VSplit(None)),
+        "vsplit" | "vs" => Ok(Command::VSplit(Some(argument.to_string()))),
+        "mksession" | "mks" if argument.is_empty() => Ok(Command::MakeSession(None)),
+        "mksession" | "mks" => Ok(Command::MakeSession(Some(argument.to_string()))),
+        "newwindow" if argument.is_empty() => Err("Usage: newwindow <file>".to_string()),
+        "newwindow" => Ok(Command::NewWindow(argument.to_string())),
+        "only" | "on" => Ok(Command::Only),
+        "close" | "clo" => Ok(Command::Close),
+        "jumps" | "ju" => Ok(Command::Jumps),
+        "messages" | "mes" => Ok(Command::Messages),
+        "toc" if argument.is_empty() => Ok(Command::Toc),
+        "toc" => {
+            let (subcommand, path) = argument.split_once(' ').unwrap_or((argument, ""));
+            match subcommand {
+                "export" if path.trim().is_empty() => Ok(Command::TocExport(None)),
+                "export" => Ok(Command::TocExport(Some(path.trim().to_string()))),
+                _ => Err(format!("Invalid argument: {}", argument)),
+            }
+        }
+        "links" => Ok(Command::Links),
+        "heading" | "he" if argument.is_empty() => {
+            Err("Argument required: heading <query>".to_string())
+        }
+        "heading" | "he" => Ok(Command::Heading(argument.to_string())),
+        "libfind" if argument.is_empty() => Err("Argument required: libfind <query>".to_string()),
+        "libfind" => Ok(Command::LibFind(argument.to_string())),
+        "highlight" | "hi" => Ok(markup(MarkupKind::Highlight, argument)),
+        "underline" | "ul" => Ok(markup(MarkupKind::Underline, argument)),
+        "strikeout" | "strike" => Ok(markup(MarkupKind::Strikeout, argument)),
+        "squiggly" | "squig" => Ok(markup(MarkupKind::Squiggly, argument)),
+        "annots" | "annotations" if argument.is_empty() => Ok(Command::Annotations),
+        "annots" | "annotations" => {
+            let (subcommand, path) = argument.split_once(' ').unwrap_or((argument, ""));
+            match subcommand {
+                "export" if path.trim().is_empty() => Ok(Command::AnnotationsExport(None)),
+                "export" => Ok(Command::AnnotationsExport(Some(path.trim().to_string()))),
+                "import" if path.trim().is_empty() => {
+                    Err("Argument required: annots import <file>".to_string())
+                }
+                "import" => Ok(Command::AnnotationsImport(path.trim().to_string())),
+                _ => Err(format!("Invalid argument: {}", argument)),
+            }
+        }
+        "info" => Ok(Command::Info),
+        "signatures" | "sigs" => Ok(Command::Signatures),
+        "attachments" | "attach" if argument.is_empty() => Ok(Command::Attachments),
+        "attachments" | "attach" => {
+            let mut words = argument.splitn(3, ' ');
+            let index = match (words.next(), words.next()) {
+                (Some("save"), Some(n)) => n.parse::<usize>().ok().filter(|&n| n > 0),
+                _ => None,
+            };
+            let path = words.next().map(str::trim).filter(|path| !path.is_empty());
+            match index {
+                Some(n) => Ok(Command::AttachmentSave(n - 1, path.map(str::to_string))),
+                None => Err("Usage: attachments save <n> [file]".to_string()),
+            }
+        }
+        "draw" => Ok(Command::Draw),
+        "form" => Ok(Command::Form),
+        "note" if argument.is_empty() => Ok(Command::Note(None)),
+        "note" => Ok(Command::Note(Some(argument.to_string()))),
+        "export" if argument == "png" || argument.starts_with("png ") => {
+            parse_export_images(argument["png".len()..].trim())
+        }
+        "export" => {
+            let flag = argument
+                .strip_prefix("--flatten")
+                .filter(|rest| rest.is_empty() || rest.starts_with(' '));
+            let (flatten, path) = match flag {
+                Some(path) => (true, path.trim()),
+                None => (false, argument),
+            };
+            if path.is_empty() {
+                Err("Argument required: export [--flatten] <file>".to_string())
+            } else {
+                Ok(Command::Export {
+                    path: path.to_string(),
+                    flatten,
+                })
+            }
+        }
+        "write" | "w" if argument == "--encrypt" || argument.starts_with("--encrypt ") => {
+            let path = argument["--encrypt".len()..].trim();
+            Ok(Command::WriteEncrypted(
+                (!path.is_empty()).then(|| path.to_string()),
+            ))
+        }
+        "write" | "w" if argument.is_empty() => Ok(Command::Write(None)),
+        "write" | "w" => Ok(Command::Write(Some(argument.to_string()))),
+        "set" | "se" if argument.is_empty() => Err("Argument required: set <option>".to_string()),
+        "set" | "se" => parse_set(argument),
+        _ => Ok(Command::External {
+            name: name.to_string(),
+            argument: argument.to_string(),
+        }),
+    }
+}
+
+fn parse_export_images(argument: &str) -> Result<Command, String> {
+    const USAGE: &str = "Usage: export png [pages] [--dpi N] [file]";
+    let (mut pages, mut dpi, mut path) = (None, None, None);
+    let mut words = argument.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "--dpi" {
+            let value = words.next().and_then(|value| value.parse::<f32>().ok());
+            match value {
+                Some(value) if (10.0..=1200.0).contains(&value) => dpi = Some(value),
+                _ => return Err("--dpi takes a number between 10 and 1200".to_string()),
+            }
+        } else if pages.is_none()
+            && path.is_none()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_digit() || c == ',' || c == '-')
+        {
+            pages = Some(word.to_string());
+        } else if path.is_none() {
+            path = Some(word.to_string());
+        } else {
+            return Err(USAGE.to_string());
+        }
+    }
+    Ok(Command::ExportImages { pages, dpi, path })
+}
@@ -0,0 +1,537 @@
+use serde::Deserialize;
+use std::error::Error;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::annotations::{MarkupColor, MarkupColors};
+use crate::keymap::Keymap;
+use crate::paths;
+use crate::plugin::PluginConfig;
+use crate::statusline;
+use crate::theme::{HexColor, Theme, TintColor};
+
+const CONFIG_FILE: &str = "config.toml";
+/// Suffix of per-document sidecar files, e.g. `slides.pdf.rufium.toml`.
+const SIDECAR_SUFFIX: &str = ".rufium.toml";
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+pub const BRIGHTNESS_RANGE: RangeInclusive<f32> = -1.0..=1.0;
+pub const CONTRAST_RANGE: RangeInclusive<f32> = 0.1..=5.0;
+pub const GAMMA_RANGE: RangeInclusive<f32> = 0.1..=5.0;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewerConfig {
+    pub window_width: f32,
+    pub window_height: f32,
+    pub fullscreen: bool,
+    /// Number of rendered pages kept in memory.
+    pub cache_size: usize,
+    /// Upper bound on the pixel count of a single rendered page bitmap.
+    pub max_render_pixels: u64,
+    /// Pages turned by `next_page` / `previous_page`.
+    pub scroll_amount: usize,
+    /// Fit pages to the window width rather than showing them whole; pages taller than
+    /// the window then scroll.
+    pub fit_width: bool,
+    /// How far `next_page` / `previous_page` scroll within a page taller than the window,
+    /// as a fraction of the window height.
+    pub scroll_step: f32,
+    /// Animate scrolling within a page rather than jumping.
+    pub smooth_scroll: bool,
+    /// How long a smooth scroll takes, in milliseconds.
+    pub smooth_scroll_duration: u64,
+    /// The paper color: fill behind transparent page content, which is blended onto it.
+    pub page_background: HexColor,
+    /// Smooth the edges of text when rendering.
+    pub antialias_text: bool,
+    /// Smooth the edges of lines and shapes when rendering.
+    pub antialias_paths: bool,
+    /// Smooth images when they are scaled.
+    pub antialias_images: bool,
+    /// Render text for the subpixels of LCD screens; needs `antialias_text`.
+    pub lcd_text: bool,
+    /// Render as for printing, which some documents draw at a higher quality.
+    pub render_for_printing: bool,
+    /// Space between the page and the window edges, in pixels.
+    pub page_padding: u16,
+    /// Space between consecutive pages in continuous mode, in pixels.
+    pub page_gap: u16,
+    /// Command used to open URI links, e.g. `["firefox", "--new-tab"]`; the URI is
+    /// appended. Empty means xdg-open, open or start depending on the platform.
+    pub link_opener: Vec<String>,
+    /// Ask before opening a URI link.
+    pub confirm_external_links: bool,
+    /// Refuse to yank text from documents that do not allow copying.
+    pub respect_copy_restrictions: bool,
+    /// Show a large page number while flipping quickly through pages.
+    pub osd: bool,
+    /// Show a thin bar along the right edge marking the position in the document.
+    pub scrollbar: bool,
+    /// Animate page turns, for presentations.
+    pub transitions: bool,
+    /// How pages turn when `transitions` is on: `fade` or `slide`.
+    pub transition_style: String,
+    /// How long a page turn takes, in milliseconds.
+    pub transition_duration: u64,
+    /// Invert the colors of rendered pages.
+    pub invert: bool,
+    /// Recolor rendered pages from `recolor_background` to `recolor_foreground`,
+    /// keeping hues and images; takes precedence over `invert`.
+    pub recolor: bool,
+    /// What white becomes when recoloring.
+    pub recolor_background: HexColor,
+    /// What black becomes when recoloring.
+    pub recolor_foreground: HexColor,
+    /// Tint rendered pages so white becomes this color; used unless inverting or
+    /// recoloring.
+    pub tint: Option<TintColor>,
+    /// The tints `cycle_tint` steps through, before going back to none.
+    pub tints: Vec<TintColor>,
+    /// Render pages in shades of gray, e.g. for e-ink displays.
+    pub grayscale: bool,
+    /// Added to every color channel of rendered pages, from -1 (black) to 1 (white).
+    pub brightness: f32,
+    /// How far colors are pushed from mid-gray; 1 leaves them as they are.
+    pub contrast: f32,
+    /// Above 1 lightens the midtones of rendered pages, below 1 darkens them.
+    pub gamma: f32,
+    /// Suit e-ink displays: no anti-aliasing, more contrast, no page-number flashes,
+    /// few redraws and a larger status bar.
+    pub eink: bool,
+    /// Show the open documents above the page when there is more than one.
+    pub tabline: bool,
+    /// Show the status bar below the page; without it, it appears only for input.
+    pub statusbar: bool,
+    /// The status bar's text in normal mode, with `{field}` placeholders from
+    /// `statusline::FIELDS`.
+    pub statusline: String,
+    /// Distraction-free reading: only the page, with `zen_padding` around it on a
+    /// neutral background; input modes still show the status bar.
+    pub zen: bool,
+    /// Space around the page in zen mode, in pixels, unless `page_padding` is larger.
+    pub zen_padding: u16,
+    /// Darken the background in zen mode instead of showing it gray.
+    pub zen_dim: bool,
+    /// Show a banner when a document contains JavaScript or automatic actions.
+    pub warn_active_content: bool,
+    /// Serve zathura's D-Bus interface for editor plugins.
+    pub dbus: bool,
+    /// Reload the shown document when it changes on disk.
+    pub auto_reload: bool,
+    /// Shell commands run when a document is shown, the page changes and the window
+    /// closes; see `external::run_hook` for the substitutions.
+    pub on_open: Option<String>,
+    pub on_page_change: Option<String>,
+    pub on_close: Option<String>,
+    /// Rhai script run at startup, relative to the config directory; needs the
+    /// `scripting` feature.
+    pub script: Option<PathBuf>,
+    /// Default colors of annotations created from a selection.
+    pub markup_colors: MarkupColors,
+    /// Resolution of `:export png`.
+    pub export_dpi: f32,
+    /// Pen color and width (in points) of `:draw`.
+    pub ink_color: MarkupColor,
+    pub ink_width: f32,
+    /// Built-in theme name or a file in `themes/` next to the config file.
+    #[serde(rename = "theme")]
+    pub theme_name: String,
+    /// Preset colors for selections, search results and link hints: `default`,
+    /// `deuteranopia` or `protanopia`.
+    pub highlight_palette: String,
+    /// Per-color overrides applied on top of the theme.
+    pub colors: toml::Table,
+    #[serde(skip)]
+    pub theme: Theme,
+    pub keybindings: Keymap,
+    pub plugins: Vec<PluginConfig>,
+    /// Overrides for documents whose path matches `match`.
+    pub documents: Vec<DocumentRule>,
+    /// The parsed file without `documents`, which per-document overrides are merged into.
+    #[serde(skip)]
+    source: toml::Table,
+}
+
+/// A `[[documents]]` entry: a glob plus any top-level config keys.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DocumentRule {
+    #[serde(rename = "match")]
+    pub pattern: String,
+    #[serde(flatten)]
+    pub overrides: toml::Table,
+}
+
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        Self {
+            window_width: 800.0,
+            window_height: 600.0,
+            fullscreen: false,
+            cache_size: 5,
+            max_render_pixels: 40_000_000,
+            scroll_amount: 1,
+            fit_width: false,
+            scroll_step: 0.1,
+            smooth_scroll: true,
+            smooth_scroll_duration: 120,
+            page_background: HexColor(iced::Color::WHITE),
+            antialias_text: true,
+            antialias_paths: true,
+            antialias_images: true,
+            lcd_text: false,
+            render_for_printing: false,
+            page_padding: 0,
+            page_gap: 8,
+            link_opener: Vec::new(),
+            confirm_external_links: true,
+            respect_copy_restrictions: false,
+            osd: true,
+            scrollbar: true,
+            transitions: false,
+            transition_style: "fade".to_string(),
+            transition_duration: 200,
+            invert: false,
+            recolor: false,
+            recolor_background: HexColor(iced::Color::from_rgb8(0x1e, 0x1e, 0x1e)),
+            recolor_foreground: HexColor(iced::Color::from_rgb8(0xdc, 0xdc, 0xdc)),
+            tint: None,
+            tints: TintColor::presets(),
+            grayscale: false,
+            eink: false,
+            brightness: 0.0,
+            contrast: 1.0,
+            gamma: 1.0,
+            tabline: true,
+            statusbar: true,
+            statusline: "{position}  {percent}".to_string(),
+            zen: false,
+            zen_padding: 48,
+            zen_dim: false,
+            warn_active_content: true,
+            dbus: true,
+            auto_reload: true,
+            script: None,
+            on_open: None,
+            on_page_change: None,
+            on_close: None,
+            markup_colors: MarkupColors::default(),
+            export_dpi: 150.0,
+            ink_color: MarkupColor(iced::Color::from_rgb8(220, 40, 40)),
+            ink_width: 2.0,
+            theme_name: "dark".to_string(),
+            highlight_palette: "default".to_string(),
+            colors: toml::Table::new(),
+            theme: Theme::default(),
+            keybindings: Keymap::default(),
+            plugins: Vec::new(),
+            documents: Vec::new(),
+            source: toml::Table::new(),
+        }
+    }
+}
+
+impl ViewerConfig {
+    /// Space between the page and the window edges, wider in zen mode.
+    pub fn padding(&self) -> u16 {
+        if self.zen {
+            self.page_padding.max(self.zen_padding)
+        } else {
+            self.page_padding
+        }
+    }
+
+    pub fn default_path() -> Option<PathBuf> {
+        paths::config_dir().map(|dir| dir.join(CONFIG_FILE))
+    }
+
+    /// `script`, resolved against the config directory.
+    pub fn script_path(&self) -> Option<PathBuf> {
+        let script = self.script.as_ref()?;
+        Some(match paths::config_dir() {
+            Some(dir) => dir.join(script),
+            None => script.clone(),
+        })
+    }
+
+    /// The page colors in effect: `normal`, `invert`, `recolor` or `tint=<name>`.
+    pub fn color_choice(&self) -> String {
+        if self.recolor {
+            "recolor".to_string()
+        } else if self.invert {
+            "invert".to_string()
+        } else if let Some(tint) = &self.tint {
+            format!("tint={}", tint.name())
+        } else {
+            "normal".to_string()
+        }
+    }
+
+    /// Restores a `color_choice`, ignoring one that no longer parses.
+    pub fn apply_color_choice(&mut self, choice: &str) {
+        let tint = match choice {
+            "normal" | "invert" | "recolor" => None,
+            _ => match choice
+                .strip_prefix("tint=")
+                .map(|name| TintColor::try_from(name.to_string()))
+            {
+                Some(Ok(tint)) => Some(tint),
+                _ => return,
+            },
+        };
+        self.invert = choice == "invert";
+        self.recolor = choice == "recolor";
+        self.tint = tint;
+    }
+
+    /// Loads `path` if given, otherwise the default config file when it exists.
+    pub fn load(path: Option<&Path>) -> Result<Self, Box<dyn Error>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match Self::default_path() {
+                Some(path) if path.exists() => path,
+                _ => return Ok(Self::default()),
+            },
+        };
+
+        let contents = fs::read_to_string(&path)
+            .map_err(|e| format!("Could not read config {}: {}", path.display(), e))?;
+        let parse_error =
+            |e: toml::de::Error| format!("Error in config {}:\n{}", path.display(), e);
+        let source: toml::Table = toml::from_str(&contents).map_err(parse_error)?;
+        let config: Self = toml::from_str(&contents).map_err(parse_error)?;
+        let config = config
+            .resolve(source)
+            .map_err(|e| format!("Error in config {}: {}", path.display(), e))?;
+
+        for rule in &config.documents {
+            check_overrides(&rule.overrides)
+                .and_then(|_| Self::from_table(merged(config.source.clone(), &rule.overrides)))
+                .map_err(|e| {
+                    format!(
+                        "Error in config {}: documents '{}': {}",
+                        path.display(),
+                        rule.pattern,
+                        e
+                    )
+                })?;
+        }
+        Ok(config)
+    }
+
+    fn from_table(table: toml::Table) -> Result<Self, String> {
+        let config: Self = table
+            .clone()
+            .try_into()
+            .map_err(|e: toml::de::Error| e.to_string())?;
+        config.resolve(table)
+    }
+
+    /// Validates the values, loads the theme and keeps `source` for per-document merging.
+    fn resolve(mut self, mut source: toml::Table) -> Result<Self, String> {
+        self.validate()?;
+        self.theme = Theme::resolve(&self.theme_name, &self.highlight_palette, &self.colors)?;
+        source.remove("documents");
+        self.source = source;
+        Ok(self)
+    }
+
+    /// The configuration for `file`: matching `[[documents]]` entries are applied in
+    /// order, followed by a `<file>.rufium.toml` sidecar next to the document.
+    pub fn for_document(&self, file: &Path) -> Result<Self, String> {
+        let path = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+        let mut table = self.source.clone();
+        let mut changed = false;
+
+        for rule in &self.documents {
+            if path_matches(&rule.pattern, &path) {
+                table = merged(table, &rule.overrides);
+                changed = true;
+            }
+        }
+
+        let mut sidecar = path.clone().into_os_string();
+        sidecar.push(SIDECAR_SUFFIX);
+        let sidecar = PathBuf::from(sidecar);
+        if let Ok(contents) = fs::read_to_string(&sidecar) {
+            let overrides: toml::Table = toml::from_str(&contents)
+                .map_err(|e| format!("Error in {}:\n{}", sidecar.display(), e))?;
+            check_overrides(&overrides)
+                .map_err(|e| format!("Error in {}: {}", sidecar.display(), e))?;
+            table = merged(table, &overrides);
+            changed = true;
+        }
+
+        if !changed {
+            return Ok(self.clone());
+        }
+        let mut config = Self::from_table(table)
+            .map_err(|e| format!("Error in overrides for {}: {}", path.display(), e))?;
+        config.documents = self.documents.clone();
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.window_width < 100.0 || self.window_height < 100.0 {
+            return Err("window_width and window_height must be at least 100".to_string());
+        }
+        if self.cache_size < 3 {
+            return Err("cache_size must be at least 3 (current page and its neighbours)".into());
+        }
+        if self.max_render_pixels < 1_000_000 {
+            return Err("max_render_pixels must be at least 1000000".into());
+        }
+        if self.page_padding > 200 || self.page_gap > 200 || self.zen_padding > 200 {
+            return Err("page_padding, page_gap and zen_padding must be at most 200".into());
+        }
+        if !(10.0..=1200.0).contains(&self.export_dpi) {
+            return Err("export_dpi must be between 10 and 1200".into());
+        }
+        if !(0.25..=50.0).contains(&self.ink_width) {
+            return Err("ink_width must be between 0.25 and 50".into());
+        }
+        for (name, value, range) in [
+            ("brightness", self.brightness, BRIGHTNESS_RANGE),
+            ("contrast", self.contrast, CONTRAST_RANGE),
+            ("gamma", self.gamma, GAMMA_RANGE),
+        ] {
+            if !range.contains(&value) {
+                return Err(format!(
+                    "{} must be between {} and {}",
+                    name,
+                    range.start(),
+                    range.end()
+                ));
+            }
+        }
+        if self.scroll_amount == 0 {
+            return Err("scroll_amount must be at least 1".into());
+        }
+        if !(0.01..=1.0).contains(&self.scroll_step) {
+            return Err("scroll_step must be between 0.01 and 1".into());
+        }
+        if self.smooth_scroll_duration > 1000 {
+            return Err("smooth_scroll_duration must be at most 1000".into());
+        }
+        if !matches!(self.transition_style.as_str(), "fade" | "slide") {
+            return Err(format!(
+                "unknown transition_style '{}', expected \"fade\" or \"slide\"",
+                self.transition_style
+            ));
+        }
+        if self.transition_duration > 1000 {
+            return Err("transition_duration must be at most 1000".into());
+        }
+        statusline::validate(&self.statusline)?;
+        Ok(())
+    }
+}
+
+fn check_overrides(overrides: &toml::Table) -> Result<(), String> {
+    match ["documents", "plugins"]
+        .iter()
+        .find(|key| overrides.contains_key(**key))
+    {
+        Some(key) => Err(format!("{} can only be set in the main config", key)),
+        None => Ok(()),
+    }
+}
+
+/// Merges `overrides` into `base`, recursing into tables such as `[colors]`.
+fn merged(mut base: toml::Table, overrides: &toml::Table) -> toml::Table {
+    for (key, value) in overrides {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(value)) => {
+                *existing = merged(std::mem::take(existing), value);
+            }
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    base
+}
+
+/// Matches `pattern` against `path`. Patterns without a `/` only see the file name;
+/// `*` and `?` stop at `/` while `**` spans directories, and a leading `~/` is the home
+/// directory.
+fn path_matches(pattern: &str, path: &Path) -> bool {
+    if !pattern.contains('/') {
+        return path
+            .file_name()
+            .is_some_and(|name| glob_match(pattern.as_bytes(), name.as_encoded_bytes()));
+    }
+
+    let pattern = match pattern.strip_prefix("~/") {
+        Some(rest) => match std::env::var_os("HOME") {
+            Some(home) => format!("{}/{}", home.to_string_lossy(), rest),
+            None => return false,
+        },
+        None => pattern.to_string(),
+    };
+    glob_match(pattern.as_bytes(), path.as_os_str().as_encoded_bytes())
+}
+
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => {
+            let (crosses_dirs, rest) = match rest.split_first() {
+                Some((b'*', rest)) => (true, rest.strip_prefix(b"/").unwrap_or(rest)),
+                _ => (false, rest),
+            };
+            (0..=text.len())
+                .take_while(|&i| crosses_dirs || i == 0 || text[i - 1] != b'/')
+                .any(|i| glob_match(rest, &text[i..]))
+        }
+        Some((b'?', rest)) => {
+            matches!(text.first(), Some(&c) if c != b'/') && glob_match(rest, &text[1..])
+        }
+        Some((&c, rest)) => text.first() == Some(&c) && glob_match(rest, &text[1..]),
+    }
+}
+
+/// Polls the config file's modification time so edits can be applied without a restart.
+pub struct ConfigWatcher {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl ConfigWatcher {
+    pub fn new(path: Option<&Path>) -> Self {
+        let path = path
+            .map(Path::to_path_buf)
+            .or_else(ViewerConfig::default_path);
+        let modified = path.as_deref().and_then(modified_time);
+        Self {
+            path,
+            modified,
+            last_check: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// True when the file was created or modified since the last call.
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < WATCH_INTERVAL {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        let modified = self.path.as_deref().and_then(modified_time);
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
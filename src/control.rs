@@ -0,0 +1,155 @@
+//! The control socket: a Unix socket on which scripts, window managers and editors send
+//! one command per line, such as `goto 42`, `reload`, `search foo` or `quit`. Each line is
+//! answered with `ok` or `error: <message>`, except `status`, which is answered with the
+//! focused window's file, page, zoom and search as one line of JSON.
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// The socket `--reuse-instance` looks for and listens on: in `$XDG_RUNTIME_DIR`, or
+/// else in the temporary directory under the user's name.
+pub fn default_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("rufium.sock"),
+        None => {
+            let user = env::var("USER").unwrap_or_default();
+            env::temp_dir().join(format!("rufium-{}.sock", user))
+        }
+    }
+}
+
+/// Sends `lines` to the instance listening on `path` and returns its answers, failing
+/// at the first error. `Ok(None)` means no instance is listening.
+#[cfg(unix)]
+pub fn send(path: &Path, lines: &[String]) -> Result<Option<Vec<String>>, String> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let Ok(mut stream) = UnixStream::connect(path) else {
+        return Ok(None);
+    };
+    let mut answers = Vec::new();
+    let mut replies = BufReader::new(stream.try_clone().map_err(|e| e.to_string())?).lines();
+    for line in lines {
+        writeln!(stream, "{}", line).map_err(|e| e.to_string())?;
+        match replies.next() {
+            Some(Ok(reply)) => match reply.strip_prefix("error: ") {
+                Some(e) => return Err(e.to_string()),
+                None => answers.push(reply),
+            },
+            Some(Err(e)) => return Err(e.to_string()),
+            None => return Err("The running instance closed the connection".to_string()),
+        }
+    }
+    Ok(Some(answers))
+}
+
+#[cfg(not(unix))]
+pub fn send(_path: &Path, _lines: &[String]) -> Result<Option<Vec<String>>, String> {
+    Ok(None)
+}
+
+/// A line received on the socket, waiting for its answer.
+pub struct ControlRequest {
+    pub line: String,
+    reply: mpsc::Sender<String>,
+}
+
+impl ControlRequest {
+    pub fn reply(&self, result: Result<(), String>) {
+        let _ = self.reply.send(match result {
+            Ok(()) => "ok".to_string(),
+            Err(e) => format!("error: {}", e),
+        });
+    }
+
+    /// Answers with `line` instead of `ok`.
+    pub fn reply_with(&self, line: String) {
+        let _ = self.reply.send(line);
+    }
+}
+
+pub struct ControlServer {
+    path: PathBuf,
+    requests: mpsc::Receiver<ControlRequest>,
+}
+
+impl ControlServer {
+    /// Listens on `path`, replacing a socket left behind by an instance that is no
+    /// longer running.
+    #[cfg(unix)]
+    pub fn start(path: &Path) -> Result<Self, String> {
+        use std::os::unix::net::{UnixListener, UnixStream};
+        use std::thread;
+
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(format!("{} is in use by another instance", path.display()));
+            }
+            let _ = std::fs::remove_file(path);
+        }
+        let listener = UnixListener::bind(path)
+            .map_err(|e| format!("Could not listen on {}: {}", path.display(), e))?;
+
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let tx = tx.clone();
+                thread::spawn(move || serve(stream, tx));
+            }
+        });
+        Ok(Self {
+            path: path.to_path_buf(),
+            requests: rx,
+        })
+    }
+
+    #[cfg(not(unix))]
+    pub fn start(_path: &Path) -> Result<Self, String> {
+        Err("The control socket needs a Unix system".to_string())
+    }
+
+    pub fn poll(&self) -> Vec<ControlRequest> {
+        self.requests.try_iter().collect()
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Answers the lines of one connection in order until it is closed.
+#[cfg(unix)]
+fn serve(stream: std::os::unix::net::UnixStream, requests: mpsc::Sender<ControlRequest>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let Ok(mut writer) = stream.try_clone() else {
+        return;
+    };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (reply, answer) = mpsc::channel();
+        let request = ControlRequest {
+            line: line.to_string(),
+            reply,
+        };
+        if requests.send(request).is_err() {
+            break;
+        }
+        let Ok(answer) = answer.recv() else {
+            break;
+        };
+        if writeln!(writer, "{}", answer).is_err() {
+            break;
+        }
+    }
+}
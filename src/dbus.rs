@@ -0,0 +1,239 @@
+//! The `org.pwmt.zathura` D-Bus interface, so editor plugins written for zathura (vimtex
+//! and others) can drive lukia. Like zathura, each process registers
+//! `org.pwmt.zathura.PID-<pid>` on the session bus and serves `/org/pwmt/zathura`. Page
+//! numbers are 0-based and rectangles are in points from the page's top-left corner.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{mpsc, Arc, Mutex};
+use zbus::blocking::connection;
+use zbus::zvariant::Value;
+
+use crate::synctex::{self, Forward};
+
+const OBJECT_PATH: &str = "/org/pwmt/zathura";
+const INTERFACE: &str = "org.pwmt.zathura";
+
+/// `(x1, y1, x2, y2)` in points from the page's top-left corner.
+pub type Rect = (f64, f64, f64, f64);
+
+/// A call to act on, for the focused window.
+pub enum DbusRequest {
+    Open {
+        file: String,
+        page: Option<usize>,
+    },
+    Close,
+    GotoPage(usize),
+    Highlight {
+        page: usize,
+        rects: Vec<Rect>,
+    },
+    /// A SyncTeX forward search that found `rects` on `page`.
+    Synctex {
+        page: usize,
+        rects: Vec<Rect>,
+    },
+    Execute(String),
+}
+
+/// The document the `filename`, `pagenumber` and `numberofpages` properties describe.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentSnapshot {
+    pub file_name: String,
+    pub page: u32,
+    pub pages: u32,
+}
+
+struct Zathura {
+    requests: mpsc::Sender<DbusRequest>,
+    snapshot: Arc<Mutex<DocumentSnapshot>>,
+}
+
+impl Zathura {
+    fn snapshot(&self) -> DocumentSnapshot {
+        self.snapshot.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    fn send(&self, request: DbusRequest) -> bool {
+        self.requests.send(request).is_ok()
+    }
+}
+
+#[zbus::interface(name = "org.pwmt.zathura")]
+impl Zathura {
+    /// Opens `filename` at `page`, or where it was last left when `page` is negative.
+    /// Encrypted documents ask for their password in the window instead.
+    fn open_document(&self, filename: String, _password: String, page: i32) -> bool {
+        Path::new(&filename).is_file()
+            && self.send(DbusRequest::Open {
+                file: filename,
+                page: usize::try_from(page).ok(),
+            })
+    }
+
+    fn close_document(&self) -> bool {
+        self.send(DbusRequest::Close)
+    }
+
+    fn goto_page(&self, page: u32) -> bool {
+        page < self.snapshot().pages && self.send(DbusRequest::GotoPage(page as usize))
+    }
+
+    /// Only the primary rectangles are shown.
+    fn highlight_rects(
+        &self,
+        page: u32,
+        rectangles: Vec<Rect>,
+        _secondary_rectangles: Vec<(u32, f64, f64, f64, f64)>,
+    ) -> bool {
+        page < self.snapshot().pages
+            && self.send(DbusRequest::Highlight {
+                page: page as usize,
+                rects: rectangles,
+            })
+    }
+
+    fn goto_page_and_highlight_rects(
+        &self,
+        page: u32,
+        rectangles: Vec<Rect>,
+        secondary_rectangles: Vec<(u32, f64, f64, f64, f64)>,
+    ) -> bool {
+        self.goto_page(page) && self.highlight_rects(page, rectangles, secondary_rectangles)
+    }
+
+    /// Goes to the lines `line` of `input` produced, found in the document's SyncTeX
+    /// file, and highlights them briefly.
+    fn synctex_view(&self, input: String, line: u32, _column: u32) -> bool {
+        let forward = Forward {
+            line: line as usize,
+            input: PathBuf::from(input),
+        };
+        match synctex::find(Path::new(&self.snapshot().file_name), &forward) {
+            Ok((page, rects)) => self.send(DbusRequest::Synctex { page, rects }),
+            Err(_) => false,
+        }
+    }
+
+    /// Runs a `:` command.
+    fn execute_command(&self, input: String) -> bool {
+        self.send(DbusRequest::Execute(input))
+    }
+
+    #[zbus(property, name = "filename")]
+    fn filename(&self) -> String {
+        self.snapshot().file_name
+    }
+
+    #[zbus(property, name = "pagenumber")]
+    fn pagenumber(&self) -> u32 {
+        self.snapshot().page
+    }
+
+    #[zbus(property, name = "numberofpages")]
+    fn numberofpages(&self) -> u32 {
+        self.snapshot().pages
+    }
+}
+
+pub struct DbusServer {
+    connection: zbus::blocking::Connection,
+    requests: mpsc::Receiver<DbusRequest>,
+    snapshot: Arc<Mutex<DocumentSnapshot>>,
+}
+
+impl DbusServer {
+    pub fn start() -> Result<Self, String> {
+        let (tx, rx) = mpsc::channel();
+        let snapshot = Arc::new(Mutex::new(DocumentSnapshot::default()));
+        let interface = Zathura {
+            requests: tx,
+            snapshot: snapshot.clone(),
+        };
+        let connection = connection::Builder::session()
+            .and_then(|builder| builder.name(format!("{}.PID-{}", INTERFACE, process::id())))
+            .and_then(|builder| builder.serve_at(OBJECT_PATH, interface))
+            .and_then(|builder| builder.build())
+            .map_err(|e| format!("Could not register on D-Bus: {}", e))?;
+        Ok(Self {
+            connection,
+            requests: rx,
+            snapshot,
+        })
+    }
+
+    pub fn poll(&self) -> Vec<DbusRequest> {
+        self.requests.try_iter().collect()
+    }
+
+    /// Updates the properties, announcing the ones that changed.
+    pub fn update(&self, snapshot: DocumentSnapshot) {
+        let Ok(mut current) = self.snapshot.lock() else {
+            return;
+        };
+        if *current == snapshot {
+            return;
+        }
+        let mut changed: HashMap<&str, Value> = HashMap::new();
+        if current.file_name != snapshot.file_name {
+            changed.insert("filename", Value::from(snapshot.file_name.clone()));
+        }
+        if current.page != snapshot.page {
+            changed.insert("pagenumber", Value::from(snapshot.page));
+        }
+        if current.pages != snapshot.pages {
+            changed.insert("numberofpages", Value::from(snapshot.pages));
+        }
+        *current = snapshot;
+        drop(current);
+
+        let _ = self.connection.emit_signal(
+            None::<&str>,
+            OBJECT_PATH,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+            &(INTERFACE, changed, Vec::<&str>::new()),
+        );
+    }
+}
+
+/// Hands `forward` to a running instance showing `pdf`, as zathura does for
+/// `--synctex-forward`, so editors can call it on every search. Returns whether one
+/// took it.
+pub fn forward_to_running(pdf: &Path, forward: &Forward) -> bool {
+    let Ok(wanted) = fs::canonicalize(pdf) else {
+        return false;
+    };
+    let Ok(connection) = zbus::blocking::Connection::session() else {
+        return false;
+    };
+    let Ok(bus) = zbus::blocking::fdo::DBusProxy::new(&connection) else {
+        return false;
+    };
+    let Ok(names) = bus.list_names() else {
+        return false;
+    };
+    let input = std::path::absolute(&forward.input).unwrap_or_else(|_| forward.input.clone());
+    let prefix = format!("{}.PID-", INTERFACE);
+    names
+        .iter()
+        .filter(|name| name.starts_with(&prefix))
+        .any(|name| {
+            let Ok(proxy) =
+                zbus::blocking::Proxy::new(&connection, name.as_str(), OBJECT_PATH, INTERFACE)
+            else {
+                return false;
+            };
+            let shown = proxy.get_property::<String>("filename");
+            shown.is_ok_and(|shown| fs::canonicalize(shown).is_ok_and(|shown| shown == wanted))
+                && proxy
+                    .call::<_, _, bool>(
+                        "SynctexView",
+                        &(input.to_string_lossy().as_ref(), forward.line as u32, 0u32),
+                    )
+                    .unwrap_or(false)
+        })
+}
@@ -9,7 +9,13 @@ pub fn init_pdfium() -> Result<Pdfium, Box<dyn Error>> {
     };
 
     let bindings = Pdfium::bind_to_library(format!("./{}", lib_name))
-        .or_else(|_| Pdfium::bind_to_library(format!("/usr/lib/{}", lib_name)))?;
+        .or_else(|_| Pdfium::bind_to_library(format!("/usr/lib/{}", lib_name)))
+        .map_err(|e| {
+            format!(
+                "pdfium not found at ./{} or /usr/lib/{}: {}",
+                lib_name, lib_name, e
+            )
+        })?;
 
     Ok(Pdfium::new(bindings))
 }
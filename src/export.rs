@@ -0,0 +1,20 @@
+use clap::ValueEnum;
+use std::path::Path;
+
+/// Output format of `:toc export`, `:annots export` and the matching subcommands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    #[value(name = "md")]
+    Markdown,
+}
+
+impl ExportFormat {
+    /// `json` for `.json` files, Markdown otherwise.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => ExportFormat::Json,
+            _ => ExportFormat::Markdown,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Plugins compiled in by crates that embed lukia, as opposed to the external plugins of
+//! `plugin`, which are separate programs. A crate depending on lukia implements
+//! [`Plugin`], passes it to [`register`] and then calls [`crate::run`], which reads the
+//! command line like the `lukia` binary does. Registered plugins are shared by every
+//! window; each call gets a [`Context`] for the window it concerns.
+
+use iced::Element;
+use std::sync::{Mutex, MutexGuard};
+
+use crate::keymap::Action;
+use crate::plugin::PluginEvent;
+use crate::script::{ScriptAction, ScriptView};
+
+static PLUGINS: Mutex<Vec<Box<dyn Plugin>>> = Mutex::new(Vec::new());
+
+/// Extends lukia with commands, key binding behavior and overlays. Every method has a
+/// default that does nothing.
+pub trait Plugin: Send {
+    /// Runs `:name args` if `name` is one of this plugin's commands, returning whether
+    /// it was. Built-in commands come first.
+    fn handle_command(&mut self, _name: &str, _args: &str, _cx: &mut Context) -> bool {
+        false
+    }
+
+    /// Called before lukia performs the action of a key binding; returning true
+    /// replaces lukia's own behavior.
+    fn handle_action(&mut self, _action: Action, _cx: &mut Context) -> bool {
+        false
+    }
+
+    fn on_event(&mut self, _event: &PluginEvent, _cx: &mut Context) {}
+
+    /// Drawn over the page, e.g. a panel of notes for it. Overlays do not take input.
+    fn draw_overlay(&self, _cx: &Context) -> Option<Element<'static, ()>> {
+        None
+    }
+}
+
+/// The shown document of a window, and what a plugin asks the window to do. Requests
+/// are carried out once the plugin returns.
+pub struct Context {
+    view: ScriptView,
+    actions: Vec<ScriptAction>,
+}
+
+impl Context {
+    pub fn file(&self) -> &str {
+        &self.view.file
+    }
+
+    /// 0-based.
+    pub fn page(&self) -> usize {
+        self.view.page
+    }
+
+    pub fn pages(&self) -> usize {
+        self.view.pages
+    }
+
+    /// Goes to page index `page`, recording the jump.
+    pub fn goto_page(&mut self, page: usize) {
+        self.actions.push(ScriptAction::GotoPage(page));
+    }
+
+    /// Opens a document as a buffer, where it was last left.
+    pub fn open(&mut self, file: impl Into<String>) {
+        self.actions.push(ScriptAction::Open(file.into()));
+    }
+
+    /// Runs a `:` command line, without the colon.
+    pub fn command(&mut self, line: impl Into<String>) {
+        self.actions.push(ScriptAction::Command(line.into()));
+    }
+
+    /// Shows `text` in the status bar.
+    pub fn message(&mut self, text: impl Into<String>) {
+        self.actions.push(ScriptAction::Message(text.into()));
+    }
+}
+
+/// Adds `plugin` to every window opened from now on.
+pub fn register(plugin: impl Plugin + 'static) {
+    plugins().push(Box::new(plugin));
+}
+
+/// Offers the plugins to `f` in registration order until one handles it, returning
+/// whether one did and what they asked for.
+pub(crate) fn dispatch(
+    view: ScriptView,
+    mut f: impl FnMut(&mut dyn Plugin, &mut Context) -> bool,
+) -> (bool, Vec<ScriptAction>) {
+    let mut cx = Context {
+        view,
+        actions: Vec::new(),
+    };
+    let handled = plugins()
+        .iter_mut()
+        .any(|plugin| f(plugin.as_mut(), &mut cx));
+    (handled, cx.actions)
+}
+
+pub(crate) fn overlays(view: ScriptView) -> Vec<Element<'static, ()>> {
+    let cx = Context {
+        view,
+        actions: Vec::new(),
+    };
+    plugins()
+        .iter()
+        .filter_map(|plugin| plugin.draw_overlay(&cx))
+        .collect()
+}
+
+/// The registered plugins; one that panicked does not take the others down with it.
+fn plugins() -> MutexGuard<'static, Vec<Box<dyn Plugin>>> {
+    PLUGINS.lock().unwrap_or_else(|e| e.into_inner())
+}
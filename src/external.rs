@@ -0,0 +1,127 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// Opens `uri` with `opener` (a command and its arguments, the URI appended), or with
+/// the platform's default handler when `opener` is empty.
+pub fn open_uri(uri: &str, opener: &[String]) -> Result<(), String> {
+    let mut command = match opener.split_first() {
+        Some((program, args)) => {
+            let mut command = Command::new(program);
+            command.args(args);
+            command
+        }
+        None if cfg!(target_os = "windows") => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        }
+        None if cfg!(target_os = "macos") => Command::new("open"),
+        None => Command::new("xdg-open"),
+    };
+
+    let mut child = command
+        .arg(uri)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Could not open {}: {}", uri, e))?;
+    // Reap the opener so it does not linger as a zombie.
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Runs an `on_*` hook from the config with the shell, after replacing `%f` (file),
+/// `%p` (page number), `%P` (page count), `%l` (page label) and `%%`. The file and
+/// label are inserted quoted, as they come from outside and may hold anything.
+pub fn run_hook(
+    command: &str,
+    file: &str,
+    page: usize,
+    pages: usize,
+    label: &str,
+) -> Result<(), String> {
+    let mut expanded = String::new();
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f') => expanded.push_str(&shell_quote(file)),
+            Some('p') => expanded.push_str(&page.to_string()),
+            Some('P') => expanded.push_str(&pages.to_string()),
+            Some('l') => expanded.push_str(&shell_quote(label)),
+            Some('%') => expanded.push('%'),
+            Some(other) => {
+                expanded.push('%');
+                expanded.push(other);
+            }
+            None => expanded.push('%'),
+        }
+    }
+
+    let mut shell = if cfg!(target_os = "windows") {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell
+        .arg(&expanded)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Could not run {}: {}", command, e))?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+fn shell_quote(value: &str) -> String {
+    if cfg!(target_os = "windows") {
+        format!("\"{}\"", value.replace('"', ""))
+    } else {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+}
+
+/// Encrypts `input` into `output` with qpdf (AES-256). Arguments are passed on standard
+/// input so the passwords do not show up in the process list.
+pub fn encrypt_pdf(input: &Path, output: &Path, user: &str, owner: &str) -> Result<(), String> {
+    let mut child = Command::new("qpdf")
+        .arg("@-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("encryption needs qpdf: {}", e))?;
+
+    let arguments = [
+        "--encrypt".to_string(),
+        format!("--user-password={}", user),
+        format!("--owner-password={}", owner),
+        "--bits=256".to_string(),
+        "--".to_string(),
+        input.to_string_lossy().into_owned(),
+        output.to_string_lossy().into_owned(),
+    ];
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(arguments.join("\n").as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+
+    let result = child.wait_with_output().map_err(|e| e.to_string())?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&result.stderr).trim().to_string())
+    }
+}
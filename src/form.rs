@@ -0,0 +1,36 @@
+use crate::pdf::PageField;
+
+/// Form mode: the focused field among the current page's fields and the text typed
+/// into it so far.
+#[derive(Default)]
+pub struct Form {
+    /// `None` until a field is chosen, and again after changing page.
+    pub focused: Option<usize>,
+    pub value: String,
+}
+
+impl Form {
+    pub fn next(&mut self, fields: &[PageField]) {
+        if !fields.is_empty() {
+            let index = self.focused.map_or(0, |i| (i + 1) % fields.len());
+            self.focus(fields, index);
+        }
+    }
+
+    pub fn previous(&mut self, fields: &[PageField]) {
+        if !fields.is_empty() {
+            let index = self.focused.unwrap_or(0).checked_sub(1);
+            self.focus(fields, index.unwrap_or(fields.len() - 1));
+        }
+    }
+
+    pub fn unfocus(&mut self) {
+        self.focused = None;
+        self.value.clear();
+    }
+
+    fn focus(&mut self, fields: &[PageField], index: usize) {
+        self.focused = Some(index);
+        self.value = fields[index].value.clone();
+    }
+}
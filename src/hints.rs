@@ -0,0 +1,73 @@
+use crate::pdf::{LinkTarget, PageLink};
+
+/// Hint characters, easiest to reach first (the same set Vimium uses).
+const HINT_CHARS: &[char] = &[
+    's', 'a', 'd', 'f', 'j', 'k', 'l', 'e', 'w', 'c', 'm', 'p', 'g', 'h',
+];
+
+pub enum HintInput {
+    /// More characters are needed to pick a link.
+    Pending,
+    Follow(LinkTarget),
+    NoMatch,
+}
+
+/// Link-hint mode: every link on the page gets a label and typing one follows it.
+pub struct LinkHints {
+    pub hints: Vec<(String, PageLink)>,
+    pub typed: String,
+}
+
+impl LinkHints {
+    pub fn new(links: &[PageLink]) -> Self {
+        Self {
+            hints: labels(links.len())
+                .into_iter()
+                .zip(links.iter().cloned())
+                .collect(),
+            typed: String::new(),
+        }
+    }
+
+    pub fn push(&mut self, c: char) -> HintInput {
+        self.typed.push(c.to_ascii_lowercase());
+        if let Some((_, link)) = self.hints.iter().find(|(label, _)| *label == self.typed) {
+            return HintInput::Follow(link.target.clone());
+        }
+        if self.visible().next().is_some() {
+            HintInput::Pending
+        } else {
+            HintInput::NoMatch
+        }
+    }
+
+    pub fn pop(&mut self) {
+        self.typed.pop();
+    }
+
+    /// Hints whose label starts with what has been typed so far.
+    pub fn visible(&self) -> impl Iterator<Item = &(String, PageLink)> {
+        self.hints
+            .iter()
+            .filter(|(label, _)| label.starts_with(&self.typed))
+    }
+}
+
+/// `count` labels of equal length, so no label is a prefix of another.
+fn labels(count: usize) -> Vec<String> {
+    let mut length = 1;
+    while HINT_CHARS.len().pow(length) < count {
+        length += 1;
+    }
+
+    (0..count)
+        .map(|mut n| {
+            let mut label = String::new();
+            for _ in 0..length {
+                label.insert(0, HINT_CHARS[n % HINT_CHARS.len()]);
+                n /= HINT_CHARS.len();
+            }
+            label
+        })
+        .collect()
+}
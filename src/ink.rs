@@ -0,0 +1,51 @@
+use iced::Point;
+
+/// Moves shorter than this (relative to the page size) do not add a point.
+const MIN_STEP: f32 = 0.002;
+
+#[derive(Debug, Clone, Copy)]
+pub enum InkInput {
+    Press,
+    /// The pointer position relative to the page, origin top-left.
+    Move(Point),
+    Release,
+}
+
+/// Freehand drawing mode; points are relative to the page like link bounds.
+#[derive(Default)]
+pub struct Ink {
+    /// The stroke being drawn while the button is held.
+    pub current: Option<Vec<Point>>,
+    /// Strokes sent to the render thread, shown until the page is re-rendered with them.
+    pub finished: Vec<Vec<Point>>,
+    last_position: Option<Point>,
+}
+
+impl Ink {
+    pub fn press(&mut self) {
+        self.current = Some(self.last_position.into_iter().collect());
+    }
+
+    pub fn move_to(&mut self, position: Point) {
+        let position = Point::new(position.x.clamp(0.0, 1.0), position.y.clamp(0.0, 1.0));
+        self.last_position = Some(position);
+        if let Some(stroke) = &mut self.current
+            && stroke
+                .last()
+                .is_none_or(|last| last.distance(position) >= MIN_STEP)
+        {
+            stroke.push(position);
+        }
+    }
+
+    /// Ends the current stroke, returning it unless it is empty.
+    pub fn release(&mut self) -> Option<Vec<Point>> {
+        let stroke = self.current.take().filter(|stroke| !stroke.is_empty())?;
+        self.finished.push(stroke.clone());
+        Some(stroke)
+    }
+
+    pub fn strokes(&self) -> impl Iterator<Item = &Vec<Point>> {
+        self.finished.iter().chain(self.current.iter())
+    }
+}
@@ -0,0 +1,353 @@
+use iced::keyboard::key::Named;
+use iced::keyboard::{Key, Modifiers};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    NextPage,
+    PreviousPage,
+    HalfPageDown,
+    HalfPageUp,
+    FirstPage,
+    LastPage,
+    CommandLine,
+    SetMark,
+    GoToMark,
+    JumpBack,
+    JumpForward,
+    ToggleToc,
+    LinkHints,
+    GoBack,
+    Visual,
+    ShowComment,
+    Draw,
+    Form,
+    Invert,
+    CycleTint,
+    BrightnessUp,
+    BrightnessDown,
+    ContrastUp,
+    ContrastDown,
+    GammaUp,
+    GammaDown,
+    ToggleStatusBar,
+    ToggleZen,
+    NextObject,
+    PreviousObject,
+    GPrefix,
+    WindowPrefix,
+    Help,
+    Quit,
+}
+
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("next_page", Action::NextPage),
+    ("previous_page", Action::PreviousPage),
+    ("half_page_down", Action::HalfPageDown),
+    ("half_page_up", Action::HalfPageUp),
+    ("first_page", Action::FirstPage),
+    ("last_page", Action::LastPage),
+    ("command_line", Action::CommandLine),
+    ("set_mark", Action::SetMark),
+    ("go_to_mark", Action::GoToMark),
+    ("jump_back", Action::JumpBack),
+    ("jump_forward", Action::JumpForward),
+    ("toggle_toc", Action::ToggleToc),
+    ("link_hints", Action::LinkHints),
+    ("go_back", Action::GoBack),
+    ("visual", Action::Visual),
+    ("show_comment", Action::ShowComment),
+    ("draw", Action::Draw),
+    ("form", Action::Form),
+    ("invert", Action::Invert),
+    ("cycle_tint", Action::CycleTint),
+    ("brightness_up", Action::BrightnessUp),
+    ("brightness_down", Action::BrightnessDown),
+    ("contrast_up", Action::ContrastUp),
+    ("contrast_down", Action::ContrastDown),
+    ("gamma_up", Action::GammaUp),
+    ("gamma_down", Action::GammaDown),
+    ("toggle_statusbar", Action::ToggleStatusBar),
+    ("toggle_zen", Action::ToggleZen),
+    ("next_object", Action::NextObject),
+    ("previous_object", Action::PreviousObject),
+    ("g_prefix", Action::GPrefix),
+    ("window_prefix", Action::WindowPrefix),
+    ("help", Action::Help),
+    ("quit", Action::Quit),
+];
+
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("j", Action::NextPage),
+    ("<Down>", Action::NextPage),
+    ("<PageDown>", Action::NextPage),
+    ("k", Action::PreviousPage),
+    ("<Up>", Action::PreviousPage),
+    ("<PageUp>", Action::PreviousPage),
+    ("<C-d>", Action::HalfPageDown),
+    ("<C-u>", Action::HalfPageUp),
+    ("<Home>", Action::FirstPage),
+    ("G", Action::LastPage),
+    ("<End>", Action::LastPage),
+    (":", Action::CommandLine),
+    ("m", Action::SetMark),
+    ("'", Action::GoToMark),
+    ("`", Action::GoToMark),
+    ("<C-o>", Action::JumpBack),
+    ("<C-i>", Action::JumpForward),
+    ("<Tab>", Action::ToggleToc),
+    ("f", Action::LinkHints),
+    ("<BS>", Action::GoBack),
+    ("v", Action::Visual),
+    ("i", Action::Invert),
+    ("t", Action::CycleTint),
+    (")", Action::BrightnessUp),
+    ("(", Action::BrightnessDown),
+    ("}", Action::ContrastUp),
+    ("{", Action::ContrastDown),
+    ("<C-n>", Action::ToggleStatusBar),
+    ("Z", Action::ToggleZen),
+    ("K", Action::ShowComment),
+    ("]", Action::NextObject),
+    ("[", Action::PreviousObject),
+    ("g", Action::GPrefix),
+    ("<C-w>", Action::WindowPrefix),
+    ("?", Action::Help),
+    ("q", Action::Quit),
+];
+
+const NAMED_KEYS: &[(&str, Named)] = &[
+    ("Down", Named::ArrowDown),
+    ("Up", Named::ArrowUp),
+    ("Left", Named::ArrowLeft),
+    ("Right", Named::ArrowRight),
+    ("PageDown", Named::PageDown),
+    ("PageUp", Named::PageUp),
+    ("Home", Named::Home),
+    ("End", Named::End),
+    ("Space", Named::Space),
+    ("Tab", Named::Tab),
+    ("CR", Named::Enter),
+    ("BS", Named::Backspace),
+    ("Esc", Named::Escape),
+    ("Del", Named::Delete),
+];
+
+/// Headings of the `?` cheatsheet, in the order it shows them.
+const CATEGORIES: &[&str] = &["Navigation", "Modes and panels", "Display", "Other"];
+
+/// A cheatsheet heading with the actions under it and the keys bound to each.
+pub type CheatsheetSection = (&'static str, Vec<(Action, Vec<String>)>);
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTION_NAMES
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, action)| *action)
+    }
+
+    /// The name used for it in `[keybindings]`.
+    pub fn name(self) -> &'static str {
+        ACTION_NAMES
+            .iter()
+            .find(|(_, action)| *action == self)
+            .map(|(name, _)| *name)
+            .expect("every action is named")
+    }
+
+    /// The heading it is listed under in the cheatsheet, one of `CATEGORIES`.
+    fn category(self) -> &'static str {
+        match self {
+            Action::NextPage
+            | Action::PreviousPage
+            | Action::HalfPageDown
+            | Action::HalfPageUp
+            | Action::FirstPage
+            | Action::LastPage
+            | Action::SetMark
+            | Action::GoToMark
+            | Action::JumpBack
+            | Action::JumpForward
+            | Action::GoBack
+            | Action::NextObject
+            | Action::PreviousObject => "Navigation",
+            Action::CommandLine
+            | Action::ToggleToc
+            | Action::LinkHints
+            | Action::Visual
+            | Action::ShowComment
+            | Action::Draw
+            | Action::Form
+            | Action::Help => "Modes and panels",
+            Action::Invert
+            | Action::CycleTint
+            | Action::BrightnessUp
+            | Action::BrightnessDown
+            | Action::ContrastUp
+            | Action::ContrastDown
+            | Action::GammaUp
+            | Action::GammaDown
+            | Action::ToggleStatusBar
+            | Action::ToggleZen => "Display",
+            Action::GPrefix | Action::WindowPrefix | Action::Quit => "Other",
+        }
+    }
+}
+
+/// A single key press in Vim notation: `j`, `G`, `<Down>`, `<C-d>`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    key: String,
+    ctrl: bool,
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "<C-{}>", self.key)
+        } else if self.key.chars().count() > 1 {
+            write!(f, "<{}>", self.key)
+        } else {
+            f.write_str(&self.key)
+        }
+    }
+}
+
+impl KeyChord {
+    pub fn from_event(key: &Key, modifiers: Modifiers) -> Option<Self> {
+        let ctrl = modifiers.control();
+        let key = match key.as_ref() {
+            Key::Character(c) if ctrl => c.to_lowercase(),
+            Key::Character(c) => c.to_string(),
+            Key::Named(named) => NAMED_KEYS
+                .iter()
+                .find(|(_, n)| *n == named)
+                .map(|(name, _)| name.to_string())?,
+            _ => return None,
+        };
+        Some(Self { key, ctrl })
+    }
+
+    pub fn parse(notation: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid key '{}'", notation);
+
+        let Some(inner) = notation
+            .strip_prefix('<')
+            .and_then(|rest| rest.strip_suffix('>'))
+        else {
+            return if notation.chars().count() == 1 {
+                Ok(Self {
+                    key: notation.to_string(),
+                    ctrl: false,
+                })
+            } else {
+                Err(invalid())
+            };
+        };
+
+        let (ctrl, name) = match inner.get(..2) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("C-") => (true, &inner[2..]),
+            _ => (false, inner),
+        };
+
+        if let Some((canonical, _)) = NAMED_KEYS
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+        {
+            return Ok(Self {
+                key: canonical.to_string(),
+                ctrl,
+            });
+        }
+        if ctrl && name.chars().count() == 1 {
+            return Ok(Self {
+                key: name.to_lowercase(),
+                ctrl,
+            });
+        }
+        Err(invalid())
+    }
+}
+
+/// Default bindings overlaid with the `[keybindings]` table; binding a key to `"none"` unmaps it.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "BTreeMap<String, String>")]
+pub struct Keymap {
+    bindings: HashMap<KeyChord, Action>,
+}
+
+impl Keymap {
+    pub fn action_for(&self, key: &Key, modifiers: Modifiers) -> Option<Action> {
+        let chord = KeyChord::from_event(key, modifiers)?;
+        self.bindings.get(&chord).copied()
+    }
+
+    /// The bound actions with their keys, grouped under the `CATEGORIES` headings and
+    /// otherwise in `ACTION_NAMES` order; unbound actions are left out.
+    pub fn cheatsheet(&self) -> Vec<CheatsheetSection> {
+        CATEGORIES
+            .iter()
+            .map(|&category| {
+                let actions = ACTION_NAMES
+                    .iter()
+                    .filter(|(_, action)| action.category() == category)
+                    .filter_map(|&(_, action)| {
+                        let mut keys: Vec<String> = self
+                            .bindings
+                            .iter()
+                            .filter(|(_, bound)| **bound == action)
+                            .map(|(chord, _)| chord.to_string())
+                            .collect();
+                        // Plain characters first, then `<...>` keys.
+                        keys.sort_by_key(|key| (key.chars().count() > 1, key.clone()));
+                        (!keys.is_empty()).then_some((action, keys))
+                    })
+                    .collect::<Vec<_>>();
+                (category, actions)
+            })
+            .filter(|(_, actions)| !actions.is_empty())
+            .collect()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = DEFAULT_BINDINGS
+            .iter()
+            .map(|(notation, action)| {
+                let chord = KeyChord::parse(notation).expect("valid default binding");
+                (chord, *action)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl TryFrom<BTreeMap<String, String>> for Keymap {
+    type Error = String;
+
+    fn try_from(overrides: BTreeMap<String, String>) -> Result<Self, Self::Error> {
+        let mut keymap = Self::default();
+        for (notation, action_name) in overrides {
+            let chord = KeyChord::parse(&notation)?;
+            if action_name == "none" {
+                keymap.bindings.remove(&chord);
+                continue;
+            }
+            let action = Action::from_name(&action_name).ok_or_else(|| {
+                let known: Vec<&str> = ACTION_NAMES.iter().map(|(n, _)| *n).collect();
+                format!(
+                    "unknown action '{}' for key '{}' (expected one of: none, {})",
+                    action_name,
+                    notation,
+                    known.join(", ")
+                )
+            })?;
+            keymap.bindings.insert(chord, action);
+        }
+        Ok(keymap)
+    }
+}
@@ -0,0 +1,4968 @@
+use clap::{ArgGroup, CommandFactory, Parser};
+use iced::keyboard::key::Named;
+use iced::keyboard::{self, Key, Modifiers};
+use iced::widget::{column, container, image, mouse_area, row, sensor, space, stack};
+use iced::{
+    clipboard, event, mouse, time, touch, window, Color, Element, Event, Length, Point, Rectangle,
+    Size, Subscription, Task,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+mod active;
+mod annotations;
+mod cli;
+mod command;
+mod config;
+mod control;
+mod dbus;
+mod engine;
+mod export;
+mod extension;
+mod external;
+mod form;
+mod hints;
+mod ink;
+mod keymap;
+mod library;
+mod marks;
+mod messages;
+mod outline;
+mod overlay;
+mod pages;
+mod paths;
+mod pdf;
+mod plugin;
+mod script;
+mod selection;
+mod session;
+mod signatures;
+mod state;
+mod statusline;
+mod synctex;
+mod theme;
+mod ui;
+mod watcher;
+
+use active::ActiveContent;
+use annotations::{DocumentAnnotation, ImportKind, MarkupColor, MarkupKind};
+use command::{Command, SetValue};
+use config::{ConfigWatcher, ViewerConfig};
+use control::ControlServer;
+use dbus::{DbusRequest, DbusServer, DocumentSnapshot};
+use export::ExportFormat;
+use form::Form;
+use hints::{HintInput, LinkHints};
+use ink::{Ink, InkInput};
+use library::{LibraryHit, LibraryIndex};
+use marks::{BackStack, JumpList, Marks};
+use messages::{Level, MessageLog, Toasts};
+use outline::{OutlineItem, TocPanel};
+use overlay::{Overlay, OverlayEntry, OverlayTarget};
+use pdf::{
+    Attachment, ColorMode, DocumentInfo, FieldKind, FieldValue, LinkTarget, PageField, PageLayout,
+    PageLink, PageNote, PdfRenderer, PendingField, PendingFieldKind, RenderCommand, RenderEvent,
+    RenderSettings,
+};
+use plugin::{PluginHost, PluginRequest};
+use script::{ScriptAction, ScriptHost, ScriptView};
+use selection::Selection;
+use session::{Session, SessionBuffer, SessionSplit};
+use signatures::Signature;
+use state::{DocumentState, StateStore, WindowGeometry};
+use synctex::Forward;
+use theme::TintColor;
+use watcher::FileWatcher;
+
+pub use extension::{register, Context, Plugin};
+pub use keymap::Action;
+pub use plugin::PluginEvent;
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    version,
+    subcommand_negates_reqs = true,
+    group(ArgGroup::new("input").required(true).multiple(true).args(["file_name", "files", "session", "last", "query"]))
+)]
+struct Args {
+    #[arg(short = 'f', long)]
+    file_name: Option<String>,
+
+    /// Documents to open as buffers; the first one (or `-f`) is shown
+    files: Vec<String>,
+
+    /// Page to open at (1-based) instead of where the document was last closed
+    #[arg(short = 'p', long)]
+    page: Option<usize>,
+
+    /// Named destination to open at; also accepted as `file.pdf#name`
+    #[arg(short = 'd', long, conflicts_with = "page")]
+    dest: Option<String>,
+
+    /// Reopen the most recently viewed document
+    #[arg(long, conflicts_with_all = ["file_name", "files", "page", "dest", "session"])]
+    last: bool,
+
+    /// Session file written by `:mksession` to restore
+    #[arg(short = 'S', long, conflicts_with_all = ["file_name", "files", "page", "dest"])]
+    session: Option<PathBuf>,
+
+    /// Unix socket on which to accept commands such as "goto 42" from other programs
+    #[arg(long, value_name = "SOCKET")]
+    listen: Option<PathBuf>,
+
+    /// Source location `line:column:file.tex` to show, found through the document's
+    /// SyncTeX file; a running instance showing the document is used if there is one
+    #[arg(long, value_name = "LINE:COLUMN:FILE", value_parser = Forward::parse, conflicts_with_all = ["page", "dest", "session", "last"])]
+    synctex_forward: Option<Forward>,
+
+    /// Open the documents in the running instance, if there is one, instead of a new
+    /// window; otherwise start one that later invocations can reuse. Uses the `--listen`
+    /// socket if given
+    #[arg(long, conflicts_with_all = ["session", "last"])]
+    reuse_instance: bool,
+
+    /// Print the file, page, zoom and search of the instance listening on SOCKET (by
+    /// default the `--reuse-instance` one) as JSON, and exit
+    #[arg(long, value_name = "SOCKET", num_args = 0..=1, exclusive = true)]
+    query: Option<Option<PathBuf>>,
+
+    /// Config file to use instead of ~/.config/rufium/config.toml
+    #[arg(short = 'c', long, global = true)]
+    config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<cli::CliCommand>,
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    Tick,
+    EventOccurred(Event),
+    WindowEvent(window::Id, window::Event),
+    Ink(InkInput),
+    /// A tab of the tabline was clicked.
+    ShowBuffer(usize),
+    /// The area the page is shown in appeared or changed size.
+    PageAreaResized(Size),
+    /// A frame is about to be drawn while a scroll is animating.
+    Frame(Instant),
+    /// The mouse moved over the page area, to this point in it.
+    CursorMoved(Point),
+    WheelScrolled(mouse::ScrollDelta),
+    /// The left button went down or up over the page area, grabbing or letting go of it.
+    PagePressed,
+    PageReleased,
+    /// A message from or for the window's `App`.
+    ForWindow(window::Id, Box<Message>),
+}
+
+#[derive(Debug, Clone)]
+enum PendingKey {
+    SetMark,
+    GoToMark,
+    /// Waiting for `y` to open this URI.
+    ConfirmOpen(String),
+    /// Waiting for `y` to quit without writing new annotations.
+    ConfirmQuit,
+    /// `]` was pressed; the next key names what to move to, e.g. `a` for an annotation.
+    Next,
+    /// `[`, the backwards counterpart of `Next`.
+    Previous,
+    /// `g`; `t` and `T` switch to the next and previous buffer.
+    G,
+    /// `Ctrl-w`, followed by a split view command.
+    Window,
+}
+
+/// The page grabbed with the mouse: where the pointer and the page's offsets were.
+struct Drag {
+    start: Point,
+    pan: f32,
+    scroll: f32,
+}
+
+/// A finger that may be swiping: where and when it touched the screen.
+struct Swipe {
+    finger: touch::Finger,
+    start: Point,
+    at: Instant,
+}
+
+/// A page turn being animated: the page turned away from, drawn over the new one.
+struct Transition {
+    previous: image::Handle,
+    fit: ui::PageFit,
+    forward: bool,
+    started: Instant,
+    /// How far along it is, from 0 to 1.
+    progress: f32,
+}
+
+/// A smooth scroll of the current page towards `App::scroll`.
+struct ScrollAnimation {
+    from: f32,
+    started: Instant,
+    /// Where the page is shown scrolled to in the current frame.
+    shown: f32,
+}
+
+/// Collects the passwords for `:w --encrypt`.
+struct PasswordPrompt {
+    path: PathBuf,
+    /// Set once the user password has been entered; `input` is then the owner password.
+    user_password: Option<String>,
+    input: String,
+}
+
+const RESIZE_DEBOUNCE: Duration = Duration::from_millis(150);
+/// How often windows poll their render threads, sockets and files.
+const TICK: Duration = Duration::from_millis(50);
+/// The polling interval in e-ink mode once input has stopped, as every tick redraws.
+const EINK_IDLE_TICK: Duration = Duration::from_secs(1);
+/// How long after input e-ink mode keeps polling at `TICK`, for pages to arrive.
+const EINK_ACTIVE: Duration = Duration::from_secs(2);
+/// Page changes closer together than this count as rapid flipping.
+const OSD_REPEAT_WINDOW: Duration = Duration::from_millis(400);
+const OSD_DURATION: Duration = Duration::from_millis(800);
+/// The zoom the mouse wheel can reach, relative to the fitted page.
+const ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.25..=4.0;
+/// How much one notch of Ctrl+wheel zooms.
+const ZOOM_STEP: f32 = 1.1;
+/// A single finger moving this far sideways, this quickly, turns the page.
+const SWIPE_DISTANCE: f32 = 80.0;
+const SWIPE_TIME: Duration = Duration::from_millis(500);
+/// How long the lines found by a SyncTeX forward search stay highlighted.
+const SYNCTEX_HIGHLIGHT: Duration = Duration::from_secs(2);
+/// Where `:note` places a note without a selection, relative to the page size.
+const NOTE_MARGIN: f32 = 0.02;
+/// The background around the page in zen mode, and with `zen_dim`.
+const ZEN_BACKGROUND: Color = Color::from_rgb(0.35, 0.35, 0.35);
+const ZEN_DIMMED_BACKGROUND: Color = Color::from_rgb(0.05, 0.05, 0.05);
+
+/// How far below the document's directory `:open` looks for PDFs, and how many
+/// directory entries it reads at most.
+const PICKER_DEPTH: usize = 3;
+const PICKER_ENTRIES: usize = 10_000;
+
+/// Line length of the comment overlay, in characters.
+const COMMENT_WIDTH: usize = 72;
+
+struct LoadedDocument {
+    total_pages: u16,
+    start_page: usize,
+    render_tx: mpsc::Sender<RenderCommand>,
+    render_rx: Arc<Mutex<mpsc::Receiver<RenderEvent>>>,
+    search_index: Arc<Mutex<Vec<String>>>,
+    outline: Vec<OutlineItem>,
+    page_labels: Vec<String>,
+    attachments: Vec<Attachment>,
+    info: DocumentInfo,
+    watcher: Result<FileWatcher, String>,
+}
+
+/// What a background `:libfind` found: its hits, or `None` when the library is empty.
+type LibrarySearch = Result<Option<Vec<LibraryHit>>, String>;
+
+/// A document that is loaded but not shown, kept with everything `App` holds per
+/// document (render thread and page cache included) for switching back to it.
+struct Buffer {
+    file_name: String,
+    /// What its render thread was last given, and the width its cache was rendered at.
+    render_settings: RenderSettings,
+    rendered_at: u16,
+    current_image: Option<image::Handle>,
+    current_page_index: usize,
+    /// Pixels scrolled down and across the current page.
+    scroll: f32,
+    pan: f32,
+    total_pages: u16,
+    render_tx: mpsc::Sender<RenderCommand>,
+    render_rx: Arc<Mutex<mpsc::Receiver<RenderEvent>>>,
+    search_index: Arc<Mutex<Vec<String>>>,
+    banner: Option<String>,
+    page_cache: HashMap<(usize, ColorMode), image::Handle>,
+    page_layouts: HashMap<usize, PageLayout>,
+    annotations: Vec<DocumentAnnotation>,
+    annotation_cursor: Option<usize>,
+    pending_fields: Vec<PendingField>,
+    pending_field_cursor: Option<usize>,
+    modified: bool,
+    marks: Marks,
+    jump_list: JumpList,
+    back_stack: BackStack,
+    state_key: Option<String>,
+    document_state: DocumentState,
+    toc: TocPanel,
+    toc_open: bool,
+    page_labels: Vec<String>,
+    signatures: Vec<Signature>,
+    unsigned_fields: Vec<String>,
+    attachments: Vec<Attachment>,
+    info: DocumentInfo,
+    watcher: Option<FileWatcher>,
+}
+
+/// The second viewport of a split view; the focused one is always `App`'s own.
+struct Split {
+    pane: Buffer,
+    /// Whether this viewport is the left one.
+    left: bool,
+}
+
+struct App {
+    file_name: String,
+    current_image: Option<image::Handle>,
+    current_page_index: usize,
+    /// Pixels scrolled down the current page, when it is taller than the page area.
+    scroll: f32,
+    scroll_animation: Option<ScrollAnimation>,
+    transition: Option<Transition>,
+    /// Pixels scrolled across the current page, when it is wider than the page area.
+    pan: f32,
+    /// Scale of the pages relative to fitting them, set with Ctrl+wheel.
+    zoom: f32,
+    /// Where the mouse last was over the page area.
+    cursor: Point,
+    drag: Option<Drag>,
+    /// Fingers on the screen, in window coordinates; for two, how far apart they last
+    /// were and the point between them in the page area.
+    touches: HashMap<touch::Finger, Point>,
+    pinch: Option<(f32, Point)>,
+    swipe: Option<Swipe>,
+    modifiers: Modifiers,
+    /// Wheel scrolling past the current page's edge, turning it once a notch's worth.
+    wheel_overscroll: f32,
+    total_pages: u16,
+    render_tx: mpsc::Sender<RenderCommand>,
+    render_rx: Arc<Mutex<mpsc::Receiver<RenderEvent>>>,
+    search_index: Arc<Mutex<Vec<String>>>,
+    window_size: Size,
+    window_position: Option<Point>,
+    window_id: Option<window::Id>,
+    window_fitted: bool,
+    /// The area the page is shown in, as last laid out.
+    page_area_size: Size,
+    /// Size pages are rendered at; lags `window_size` until a resize settles.
+    render_size: (u16, u16),
+    resize_pending_since: Option<Instant>,
+    resize_render_in_flight: bool,
+    command_input: Option<String>,
+    pending_key: Option<PendingKey>,
+    password_prompt: Option<PasswordPrompt>,
+    /// Warning shown above the page until the next key press.
+    banner: Option<String>,
+    status_message: Option<String>,
+    messages: MessageLog,
+    last_page_change: Option<Instant>,
+    osd_until: Option<Instant>,
+    /// While the status bar is hidden, the page number is shown in its place until then.
+    page_toast_until: Option<Instant>,
+    toasts: Toasts,
+    /// Rectangles in points on a page, shown until the next key press; set over D-Bus
+    /// or by a SyncTeX forward search.
+    highlights: Option<(usize, Vec<dbus::Rect>)>,
+    /// When set, `highlights` are only shown until then.
+    highlights_until: Option<Instant>,
+    page_cache: HashMap<(usize, ColorMode), image::Handle>,
+    /// Layout of every page rendered so far.
+    page_layouts: HashMap<usize, PageLayout>,
+    hints: Option<LinkHints>,
+    selection: Option<Selection>,
+    /// Set while in drawing mode.
+    ink: Option<Ink>,
+    /// Set while in form mode.
+    form: Option<Form>,
+    /// Every annotation in reading order, as last reported by the render thread.
+    annotations: Vec<DocumentAnnotation>,
+    /// The annotation `]a` / `[a` last moved to.
+    annotation_cursor: Option<usize>,
+    pending_fields: Vec<PendingField>,
+    /// The field `]s` / `]f` and their reverses last moved to.
+    pending_field_cursor: Option<usize>,
+    /// Annotations were added since the document was last written.
+    modified: bool,
+    /// The query of the last text search, for `status`.
+    last_search: Option<String>,
+    marks: Marks,
+    jump_list: JumpList,
+    back_stack: BackStack,
+    /// Key of the open document in the state store; `None` if it could not be hashed.
+    state_key: Option<String>,
+    document_state: DocumentState,
+    overlay: Option<Overlay>,
+    /// The query of a `:libfind` still searching in the background, and where its hits
+    /// arrive.
+    library_search: Option<(String, mpsc::Receiver<LibrarySearch>)>,
+    toc: TocPanel,
+    toc_open: bool,
+    /// Logical page labels by page index; empty when the document defines none.
+    page_labels: Vec<String>,
+    signatures: Vec<Signature>,
+    /// Partial names of the signature fields not signed yet.
+    unsigned_fields: Vec<String>,
+    attachments: Vec<Attachment>,
+    info: DocumentInfo,
+    /// `None` when the document could not be watched.
+    watcher: Option<FileWatcher>,
+    /// Files open in this window, in the order opened; the one shown is
+    /// `current_buffer`.
+    buffers: Vec<String>,
+    current_buffer: usize,
+    /// Documents of the other buffers that have been shown, by file name.
+    parked: HashMap<String, Buffer>,
+    split: Option<Split>,
+    /// The config file as loaded; `config` adds the overrides for the open document.
+    base_config: ViewerConfig,
+    config: ViewerConfig,
+    fullscreen: bool,
+    config_watcher: ConfigWatcher,
+    plugins: PluginHost,
+    scripts: ScriptHost,
+    /// What compiled-in plugins asked for, carried out on the next tick.
+    plugin_actions: Vec<ScriptAction>,
+    /// Files to open in new windows, taken by `Viewer` after each update.
+    new_windows: Vec<String>,
+    /// The window is to be closed; its state has been saved.
+    closing: bool,
+}
+
+/// Every open window, each an `App` with its own documents and render threads. All
+/// share the process, and with it the loaded pdfium library and the library index.
+struct Viewer {
+    windows: BTreeMap<window::Id, App>,
+    config_path: Option<PathBuf>,
+    /// The window commands from the control socket go to.
+    focused: Option<window::Id>,
+    control: Option<ControlServer>,
+    dbus: Option<DbusServer>,
+    /// When a key, mouse or window event last arrived; e-ink mode polls slowly after.
+    last_input: Instant,
+    /// The window saying why the document given at startup could not be opened;
+    /// closing it exits.
+    startup_error: Option<(window::Id, String)>,
+}
+
+/// Runs the viewer on the command line's arguments, with the plugins given to
+/// [`register`] compiled in.
+pub fn run() -> iced::Result {
+    let args = Args::parse();
+
+    if let Some(command) = args.command {
+        return match cli::run(command, args.config.as_deref(), Args::command()) {
+            Ok(Some(open)) => run_viewer(
+                open.file_name,
+                Vec::new(),
+                Some(open.page_index),
+                args.config,
+                None,
+                args.listen,
+                None,
+            ),
+            Ok(None) => Ok(()),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+    }
+
+    if let Some(socket) = args.query {
+        let socket = socket.unwrap_or_else(control::default_path);
+        match control::send(&socket, &["status".to_string()]) {
+            Ok(Some(answers)) => {
+                for answer in answers {
+                    println!("{}", answer);
+                }
+                return Ok(());
+            }
+            Ok(None) => eprintln!("No instance is listening on {}", socket.display()),
+            Err(e) => eprintln!("{}", e),
+        }
+        process::exit(1);
+    }
+
+    if let Some(path) = args.session {
+        let session = match Session::load(&path) {
+            Ok(session) => session,
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+        let mut files = session
+            .buffers
+            .iter()
+            .map(|buffer| buffer.file.to_string_lossy().into_owned());
+        let file_name = files.next().unwrap_or_default();
+        let other_files = files.collect();
+        let initial_page = session.buffers[0].page.map(|page| page.saturating_sub(1));
+        return run_viewer(
+            file_name,
+            other_files,
+            initial_page,
+            args.config,
+            Some(session),
+            args.listen,
+            None,
+        );
+    }
+
+    if args.last {
+        let last = StateStore::load().map(|store| {
+            store
+                .recent()
+                .into_iter()
+                .map(|state| state.path.clone())
+                .find(|path| path.exists())
+        });
+        return match last {
+            Ok(Some(path)) => run_viewer(
+                path.to_string_lossy().into_owned(),
+                Vec::new(),
+                None,
+                args.config,
+                None,
+                args.listen,
+                None,
+            ),
+            Ok(None) => {
+                eprintln!("No recently viewed document");
+                process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        };
+    }
+
+    let mut files = args.file_name.into_iter().chain(args.files);
+    let (file_name, fragment) = split_fragment(files.next().unwrap_or_default());
+    let other_files: Vec<String> = files.collect();
+
+    if let Some(file) = std::iter::once(&file_name)
+        .chain(&other_files)
+        .find(|file| !library::is_pdf(Path::new(file)))
+    {
+        return show_startup_error(format!("{} isn't a PDF.", file));
+    }
+
+    // `#page=N` and `#nameddest=name` follow Adobe's open parameters; any other
+    // fragment is a destination name.
+    let mut page = args.page;
+    let mut dest = args.dest;
+    if let Some(fragment) = fragment {
+        if let Some(n) = fragment.strip_prefix("page=") {
+            page = n.parse().ok();
+        } else {
+            dest = Some(
+                fragment
+                    .strip_prefix("nameddest=")
+                    .unwrap_or(&fragment)
+                    .to_string(),
+            );
+        }
+    }
+
+    let initial_page = match dest {
+        Some(name) => {
+            let resolved = engine::init_pdfium()
+                .map_err(|e| format!("Fatal Error loading PDFium: {}", e))
+                .and_then(|pdfium| {
+                    let document = pdfium
+                        .load_pdf_from_file(&file_name, None)
+                        .map_err(|e| format!("Could not open file: {}", e))?;
+                    pdf::resolve_named_destination(&document, &name)
+                });
+            match resolved {
+                Ok(page_index) => Some(page_index),
+                Err(e) => return show_startup_error(e),
+            }
+        }
+        None => page.map(|page| page.saturating_sub(1)),
+    };
+    if let Some(forward) = &args.synctex_forward
+        && dbus::forward_to_running(Path::new(&file_name), forward)
+    {
+        return Ok(());
+    }
+    let listen = if args.reuse_instance {
+        let socket = args.listen.unwrap_or_else(control::default_path);
+        let lines = reuse_lines(
+            &file_name,
+            &other_files,
+            initial_page,
+            args.synctex_forward.as_ref(),
+        );
+        match control::send(&socket, &lines) {
+            Ok(Some(_)) => return Ok(()),
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        Some(socket)
+    } else {
+        args.listen
+    };
+    run_viewer(
+        file_name,
+        other_files,
+        initial_page,
+        args.config,
+        None,
+        listen,
+        args.synctex_forward,
+    )
+}
+
+/// The control socket lines that show the documents in a running instance, the first
+/// one last so that it ends up shown. Paths are made absolute, as the instance may
+/// run elsewhere.
+fn reuse_lines(
+    file_name: &str,
+    other_files: &[String],
+    initial_page: Option<usize>,
+    synctex: Option<&Forward>,
+) -> Vec<String> {
+    let absolute = |file: &Path| std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+    let mut lines: Vec<String> = other_files
+        .iter()
+        .chain(std::iter::once(&file_name.to_string()))
+        .map(|file| format!("open {}", absolute(Path::new(file)).display()))
+        .collect();
+    if let Some(page_index) = initial_page {
+        lines.push(format!("goto {}", page_index + 1));
+    }
+    if let Some(forward) = synctex {
+        lines.push(format!(
+            "synctex-forward {}:0:{}",
+            forward.line,
+            absolute(&forward.input).display()
+        ));
+    }
+    lines
+}
+
+/// Splits `file.pdf#fragment`, unless a file with the full name exists.
+fn split_fragment(file_name: String) -> (String, Option<String>) {
+    if Path::new(&file_name).exists() {
+        return (file_name, None);
+    }
+    match file_name.rsplit_once('#') {
+        Some((file, fragment)) if !fragment.is_empty() => {
+            (file.to_string(), Some(fragment.to_string()))
+        }
+        _ => (file_name, None),
+    }
+}
+
+/// The config at `path`, or the defaults and the reason when it cannot be used, to be
+/// shown in the window rather than on a terminal that may not be there.
+fn load_config(path: Option<&Path>) -> (ViewerConfig, Option<String>) {
+    match ViewerConfig::load(path) {
+        Ok(config) => (config, None),
+        Err(e) => (
+            ViewerConfig::default(),
+            Some(format!(
+                "Using the default config: {}",
+                single_line(&e.to_string())
+            )),
+        ),
+    }
+}
+
+/// Shows `file_name`, with `other_files` open as further buffers.
+fn run_viewer(
+    file_name: String,
+    other_files: Vec<String>,
+    initial_page: Option<usize>,
+    config_path: Option<PathBuf>,
+    session: Option<Session>,
+    listen: Option<PathBuf>,
+    synctex: Option<Forward>,
+) -> iced::Result {
+    let (base_config, mut config_error) = load_config(config_path.as_deref());
+    let config = match base_config.for_document(Path::new(&file_name)) {
+        Ok(config) => config,
+        Err(e) => {
+            config_error = Some(single_line(&e.to_string()));
+            base_config.clone()
+        }
+    };
+    // A saved geometry replaces both the configured size and fitting the first page.
+    let geometry = match session.as_ref().and_then(|session| session.window) {
+        Some(geometry) => Some(geometry),
+        None => StateStore::load().ok().and_then(|store| store.window),
+    }
+    .filter(|_| !config.fullscreen);
+    let restored_size = geometry.map(|geometry| Size::new(geometry.width, geometry.height));
+    let window_settings = match geometry {
+        Some(geometry) => window::Settings {
+            size: Size::new(geometry.width, geometry.height),
+            position: match (geometry.x, geometry.y) {
+                (Some(x), Some(y)) => window::Position::Specific(Point::new(x, y)),
+                _ => window::Position::Default,
+            },
+            ..window_settings(&config)
+        },
+        None => window_settings(&config),
+    };
+    iced::daemon(
+        move || {
+            let (id, open) = window::open(window_settings.clone());
+            let (mut app, task) = match App::new(
+                file_name.clone(),
+                other_files.clone(),
+                initial_page,
+                base_config.clone(),
+                config.clone(),
+                ConfigWatcher::new(config_path.as_deref()),
+                restored_size,
+            ) {
+                Ok(app) => app,
+                Err(e) => {
+                    // Also on stderr, for when it was started from a terminal.
+                    eprintln!("{}", e);
+                    return (Viewer::failed(id, e), open.discard());
+                }
+            };
+            if let Some(e) = &config_error {
+                app.notify(Level::Error, e.clone());
+            }
+            if let Some(session) = &session {
+                app.restore_session(session);
+            }
+            if let Some(forward) = &synctex
+                && let Err(e) = app.synctex_forward(forward)
+            {
+                app.notify(Level::Warning, e);
+            }
+            let control = listen
+                .as_deref()
+                .and_then(|path| match ControlServer::start(path) {
+                    Ok(control) => Some(control),
+                    Err(e) => {
+                        app.notify(Level::Error, e);
+                        None
+                    }
+                });
+            let dbus = match config.dbus.then(DbusServer::start) {
+                Some(Ok(dbus)) => Some(dbus),
+                Some(Err(e)) => {
+                    // No session bus is common outside a desktop session.
+                    app.messages.push(Level::Warning, e);
+                    None
+                }
+                None => None,
+            };
+            let mut viewer = Viewer {
+                windows: BTreeMap::new(),
+                config_path: config_path.clone(),
+                focused: Some(id),
+                control,
+                dbus,
+                last_input: Instant::now(),
+                startup_error: None,
+            };
+            let task = viewer.add_window(id, app, task);
+            (viewer, Task::batch([open.discard(), task]))
+        },
+        Viewer::update,
+        Viewer::view,
+    )
+    .title(Viewer::title)
+    .subscription(Viewer::subscription)
+    .run()
+}
+
+/// Shows `message` alone in a window, and on stderr, for a problem found before any
+/// document could be opened.
+fn show_startup_error(message: String) -> iced::Result {
+    eprintln!("{}", message);
+    iced::daemon(
+        move || {
+            let (id, open) = window::open(window_settings(&ViewerConfig::default()));
+            (Viewer::failed(id, message.clone()), open.discard())
+        },
+        Viewer::update,
+        Viewer::view,
+    )
+    .title(Viewer::title)
+    .subscription(Viewer::subscription)
+    .run()
+}
+
+/// A new window sized by `config`.
+fn window_settings(config: &ViewerConfig) -> window::Settings {
+    window::Settings {
+        size: Size::new(config.window_width, config.window_height),
+        fullscreen: config.fullscreen,
+        exit_on_close_request: false,
+        ..window::Settings::default()
+    }
+}
+
+impl Viewer {
+    /// No document windows, only window `id` showing why none could be opened.
+    fn failed(id: window::Id, message: String) -> Self {
+        Self {
+            windows: BTreeMap::new(),
+            config_path: None,
+            focused: None,
+            control: None,
+            dbus: None,
+            last_input: Instant::now(),
+            startup_error: Some((id, message)),
+        }
+    }
+
+    fn add_window(&mut self, id: window::Id, mut app: App, task: Task<Message>) -> Task<Message> {
+        app.window_id = Some(id);
+        self.windows.insert(id, app);
+        task.map(move |message| Message::ForWindow(id, Box::new(message)))
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        if !matches!(message, Message::Tick | Message::Frame(_)) {
+            self.last_input = Instant::now();
+        }
+        match message {
+            Message::Tick => {
+                let mut tasks = vec![self.handle_control_requests(), self.handle_dbus_requests()];
+                let ids: Vec<window::Id> = self.windows.keys().copied().collect();
+                for id in ids {
+                    tasks.push(self.update_window(id, Message::Tick));
+                }
+                Task::batch(tasks)
+            }
+            Message::WindowEvent(id, event) => {
+                if let window::Event::Focused = event {
+                    self.focused = Some(id);
+                }
+                if let window::Event::CloseRequested = event
+                    && self
+                        .startup_error
+                        .as_ref()
+                        .is_some_and(|(error_window, _)| *error_window == id)
+                {
+                    return iced::exit();
+                }
+                self.update_window(id, Message::WindowEvent(id, event))
+            }
+            Message::ForWindow(id, message) => self.update_window(id, *message),
+            Message::Frame(now) => {
+                for app in self.windows.values_mut() {
+                    app.animate(now);
+                }
+                Task::none()
+            }
+            // Only ever sent to a window.
+            Message::EventOccurred(_)
+            | Message::Ink(_)
+            | Message::ShowBuffer(_)
+            | Message::PageAreaResized(_)
+            | Message::CursorMoved(_)
+            | Message::WheelScrolled(_)
+            | Message::PagePressed
+            | Message::PageReleased => Task::none(),
+        }
+    }
+
+    fn update_window(&mut self, id: window::Id, message: Message) -> Task<Message> {
+        let Some(app) = self.windows.get_mut(&id) else {
+            return Task::none();
+        };
+        let task = app
+            .update(message)
+            .map(move |message| Message::ForWindow(id, Box::new(message)));
+        Task::batch([task, self.settle_window(id)])
+    }
+
+    /// Closes window `id` or opens new ones, as its `App` asked.
+    fn settle_window(&mut self, id: window::Id) -> Task<Message> {
+        let Some(app) = self.windows.get_mut(&id) else {
+            return Task::none();
+        };
+        let mut tasks = Vec::new();
+        let new_windows = mem::take(&mut app.new_windows);
+        if app.closing {
+            self.windows.remove(&id);
+            tasks.push(window::close(id));
+            if self.windows.is_empty() {
+                tasks.push(iced::exit());
+            }
+        }
+        for file_name in new_windows {
+            tasks.push(self.open_window(id, file_name));
+        }
+        Task::batch(tasks)
+    }
+
+    /// Answers the lines received on the control socket. `quit` closes every window;
+    /// anything else goes to the focused one.
+    fn handle_control_requests(&mut self) -> Task<Message> {
+        let Some(control) = &self.control else {
+            return Task::none();
+        };
+        let mut tasks = Vec::new();
+        for request in control.poll() {
+            let id = self.focused_window();
+            let result = match (request.line.as_str(), id) {
+                ("quit", _) if self.windows.values().any(App::has_unwritten_buffers) => {
+                    Err("Unwritten annotations; use quit! to discard them".to_string())
+                }
+                ("quit" | "quit!", _) => {
+                    for app in self.windows.values_mut() {
+                        app.save_on_exit();
+                    }
+                    request.reply(Ok(()));
+                    return iced::exit();
+                }
+                ("status", Some(id)) => match self.windows.get(&id) {
+                    Some(app) => {
+                        request.reply_with(app.status());
+                        continue;
+                    }
+                    None => Err("No window is open".to_string()),
+                },
+                (line, Some(id)) => {
+                    let result = self
+                        .windows
+                        .get_mut(&id)
+                        .map_or(Ok(()), |app| app.control(line));
+                    if line.starts_with("open ") && result.is_ok() {
+                        tasks.push(window::gain_focus(id));
+                    }
+                    tasks.push(self.settle_window(id));
+                    result
+                }
+                (_, None) => Err("No window is open".to_string()),
+            };
+            request.reply(result);
+        }
+        Task::batch(tasks)
+    }
+
+    /// Acts on the D-Bus calls made since the last tick, in the focused window, and
+    /// updates the properties from it.
+    fn handle_dbus_requests(&mut self) -> Task<Message> {
+        let Some(dbus) = &self.dbus else {
+            return Task::none();
+        };
+        let requests = dbus.poll();
+        let Some(id) = self.focused_window() else {
+            return Task::none();
+        };
+        let mut tasks = Vec::new();
+        if let Some(app) = self.windows.get_mut(&id) {
+            for request in requests {
+                match request {
+                    DbusRequest::Open { file, page } => app.open_document(file, page),
+                    DbusRequest::Close => app.quit(),
+                    DbusRequest::GotoPage(page) => app.jump_to_page(page),
+                    DbusRequest::Highlight { page, rects } => {
+                        app.highlights = Some((page, rects));
+                        app.highlights_until = None;
+                    }
+                    DbusRequest::Synctex { page, rects } => app.show_synctex(page, rects),
+                    DbusRequest::Execute(input) => app.run_command(&input),
+                }
+            }
+            let snapshot = DocumentSnapshot {
+                file_name: std::path::absolute(&app.file_name)
+                    .map_or(app.file_name.clone(), |path| path.display().to_string()),
+                page: app.current_page_index as u32,
+                pages: app.total_pages as u32,
+            };
+            if let Some(dbus) = &self.dbus {
+                dbus.update(snapshot);
+            }
+            tasks.push(self.settle_window(id));
+        }
+        Task::batch(tasks)
+    }
+
+    /// The window commands from other programs go to: the last focused one.
+    fn focused_window(&self) -> Option<window::Id> {
+        self.focused
+            .filter(|id| self.windows.contains_key(id))
+            .or_else(|| self.windows.keys().next().copied())
+    }
+
+    /// Opens `file_name` in a new window, reporting failures in window `from`.
+    fn open_window(&mut self, from: window::Id, file_name: String) -> Task<Message> {
+        let Some(base_config) = self.windows.get(&from).map(|app| app.base_config.clone()) else {
+            return Task::none();
+        };
+        let opened = base_config
+            .for_document(Path::new(&file_name))
+            .map_err(|e| single_line(&e))
+            .and_then(|config| {
+                let settings = window_settings(&config);
+                let app = App::new(
+                    file_name,
+                    Vec::new(),
+                    None,
+                    base_config,
+                    config,
+                    ConfigWatcher::new(self.config_path.as_deref()),
+                    None,
+                )?;
+                Ok((settings, app))
+            });
+        match opened {
+            Ok((settings, (app, task))) => {
+                let (id, open) = window::open(settings);
+                let task = self.add_window(id, app, task);
+                Task::batch([open.discard(), task])
+            }
+            Err(e) => {
+                if let Some(app) = self.windows.get_mut(&from) {
+                    app.notify(Level::Error, e);
+                }
+                Task::none()
+            }
+        }
+    }
+
+    fn view(&self, id: window::Id) -> Element<'_, Message> {
+        match self.windows.get(&id) {
+            Some(app) => app
+                .view()
+                .map(move |message| Message::ForWindow(id, Box::new(message))),
+            None => match &self.startup_error {
+                Some((error_window, message)) if *error_window == id => {
+                    ui::startup_error(message.clone(), &theme::Theme::default())
+                }
+                _ => space().into(),
+            },
+        }
+    }
+
+    fn title(&self, id: window::Id) -> String {
+        match self.windows.get(&id) {
+            Some(app) => app.title(),
+            None => "lukia".to_string(),
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let input_sub = event::listen_with(|event, status, id| match (event, status) {
+            (Event::Keyboard(event), event::Status::Ignored) => Some(Message::ForWindow(
+                id,
+                Box::new(Message::EventOccurred(Event::Keyboard(event))),
+            )),
+            // Fingers also press the page area, which captures them.
+            (Event::Touch(event), _) => Some(Message::ForWindow(
+                id,
+                Box::new(Message::EventOccurred(Event::Touch(event))),
+            )),
+            _ => None,
+        });
+
+        let idle = self.last_input.elapsed() > EINK_ACTIVE
+            && self.windows.values().any(|app| app.config.eink)
+            && !self.windows.values().any(App::is_settling);
+        let interval = if idle { EINK_IDLE_TICK } else { TICK };
+        let ticker = time::every(interval).map(|_| Message::Tick);
+
+        let window_sub = window::events().map(|(id, event)| Message::WindowEvent(id, event));
+
+        let frames = if self
+            .windows
+            .values()
+            .any(|app| app.scroll_animation.is_some() || app.transition.is_some())
+        {
+            window::frames().map(Message::Frame)
+        } else {
+            Subscription::none()
+        };
+
+        Subscription::batch(vec![input_sub, ticker, window_sub, frames])
+    }
+}
+
+impl App {
+    fn new(
+        file_name: String,
+        other_files: Vec<String>,
+        initial_page: Option<usize>,
+        base_config: ViewerConfig,
+        mut config: ViewerConfig,
+        config_watcher: ConfigWatcher,
+        restored_size: Option<Size>,
+    ) -> Result<(Self, Task<Message>), String> {
+        let mut messages = MessageLog::default();
+        let (state_key, document_state) = match load_document_state(&file_name) {
+            Ok((key, state)) => (Some(key), state),
+            Err(e) => {
+                messages.push(
+                    Level::Warning,
+                    format!("Could not load document state: {}", e),
+                );
+                (None, DocumentState::default())
+            }
+        };
+        let initial_page = initial_page.unwrap_or(document_state.last_page);
+        if let Some(choice) = &document_state.color_mode {
+            config.apply_color_choice(choice);
+        }
+        let buffers = std::iter::once(file_name.clone())
+            .chain(other_files)
+            .collect();
+
+        let window_size =
+            restored_size.unwrap_or(Size::new(config.window_width, config.window_height));
+        let initial_size = (window_size.width as u16, window_size.height as u16);
+        let settings = RenderSettings::from_config(&config);
+        let color_mode = ColorMode::from_config(&config);
+        let loaded = load_document(&file_name, initial_page, initial_size, settings, color_mode)?;
+        let watcher = loaded
+            .watcher
+            .map_err(|e| messages.push(Level::Warning, e))
+            .ok();
+
+        let (plugins, mut plugin_errors) = PluginHost::start(&base_config.plugins);
+        let (scripts, script_error) = ScriptHost::start(base_config.script_path().as_deref());
+        plugin_errors.extend(script_error);
+        for e in plugin_errors {
+            messages.push(Level::Error, e);
+        }
+        plugins.broadcast(PluginEvent::DocumentOpened {
+            file: &file_name,
+            total_pages: loaded.total_pages as usize,
+        });
+
+        // Problems found while opening, shown once the window is up.
+        let mut toasts = Toasts::default();
+        for entry in messages.iter() {
+            toasts.push(entry.level, entry.text.clone());
+        }
+
+        let mut app = Self {
+            file_name,
+            current_image: None,
+            current_page_index: loaded.start_page,
+            scroll: 0.0,
+            scroll_animation: None,
+            transition: None,
+            pan: 0.0,
+            zoom: 1.0,
+            cursor: Point::ORIGIN,
+            drag: None,
+            touches: HashMap::new(),
+            pinch: None,
+            swipe: None,
+            modifiers: Modifiers::default(),
+            wheel_overscroll: 0.0,
+            total_pages: loaded.total_pages,
+            render_tx: loaded.render_tx,
+            render_rx: loaded.render_rx,
+            search_index: loaded.search_index,
+            toc: TocPanel::new(loaded.outline),
+            toc_open: false,
+            page_labels: loaded.page_labels,
+            signatures: Vec::new(),
+            unsigned_fields: Vec::new(),
+            attachments: loaded.attachments,
+            info: loaded.info,
+            watcher,
+            buffers,
+            current_buffer: 0,
+            parked: HashMap::new(),
+            split: None,
+            window_size,
+            window_position: None,
+            window_id: None,
+            window_fitted: restored_size.is_some(),
+            page_area_size: window_size,
+            render_size: initial_size,
+            resize_pending_since: None,
+            resize_render_in_flight: false,
+            command_input: None,
+            pending_key: None,
+            password_prompt: None,
+            banner: None,
+            status_message: None,
+            messages,
+            last_page_change: None,
+            osd_until: None,
+            page_toast_until: None,
+            toasts,
+            highlights: None,
+            highlights_until: None,
+            page_cache: HashMap::new(),
+            page_layouts: HashMap::new(),
+            hints: None,
+            selection: None,
+            ink: None,
+            form: None,
+            annotations: Vec::new(),
+            annotation_cursor: None,
+            pending_fields: Vec::new(),
+            pending_field_cursor: None,
+            modified: false,
+            marks: Marks::default(),
+            jump_list: JumpList::default(),
+            back_stack: BackStack::default(),
+            state_key,
+            document_state,
+            overlay: None,
+            library_search: None,
+            base_config,
+            fullscreen: config.fullscreen,
+            config,
+            config_watcher,
+            plugins,
+            scripts,
+            plugin_actions: Vec::new(),
+            last_search: None,
+            new_windows: Vec::new(),
+            closing: false,
+        };
+        app.restore_marks();
+        app.scripts.document_opened(app.script_view());
+        app.run_hook(app.config.on_open.clone());
+        app.dispatch_plugin_event(PluginEvent::DocumentOpened {
+            file: &app.file_name.clone(),
+            total_pages: app.total_pages as usize,
+        });
+        Ok((app, Task::none()))
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::Tick => {
+                self.handle_plugin_requests();
+                self.handle_script_actions();
+                self.poll_library_search();
+                if self.config_watcher.poll() {
+                    self.reload_config();
+                }
+                self.poll_file_watcher();
+
+                let mut task = Task::none();
+                let render_rx = self.render_rx.clone();
+                if let Ok(rx) = render_rx.lock() {
+                    while let Ok(event) = rx.try_recv() {
+                        let result = match event {
+                            RenderEvent::Page(result) => result,
+                            RenderEvent::Saved(path) => {
+                                self.document_saved(&path);
+                                continue;
+                            }
+                            RenderEvent::Exported(path) => {
+                                self.notify(Level::Info, format!("Wrote {}", path.display()));
+                                continue;
+                            }
+                            RenderEvent::ImagesExported(paths) => {
+                                let message = match &paths[..] {
+                                    [path] => format!("Wrote {}", path.display()),
+                                    [first, .., last] => format!(
+                                        "Wrote {} images, {} to {}",
+                                        paths.len(),
+                                        first.display(),
+                                        last.display()
+                                    ),
+                                    [] => continue,
+                                };
+                                self.notify(Level::Info, message);
+                                continue;
+                            }
+                            RenderEvent::Imported { added, skipped } => {
+                                self.notify(
+                                    Level::Info,
+                                    format!(
+                                        "Imported {} annotations, {} already present",
+                                        added, skipped
+                                    ),
+                                );
+                                continue;
+                            }
+                            RenderEvent::Annotations(annotations) => {
+                                self.annotations = annotations;
+                                self.annotation_cursor = None;
+                                continue;
+                            }
+                            RenderEvent::PendingFields(fields) => {
+                                self.pending_fields = fields;
+                                self.pending_field_cursor = None;
+                                continue;
+                            }
+                            RenderEvent::Scanned {
+                                active_content,
+                                signatures,
+                                unsigned_fields,
+                            } => {
+                                self.banner = active_content_banner(
+                                    &active_content,
+                                    &self.config,
+                                    &mut self.messages,
+                                );
+                                self.signatures = signatures;
+                                self.unsigned_fields = unsigned_fields;
+                                continue;
+                            }
+                            RenderEvent::Error(e) => {
+                                self.resize_render_in_flight = false;
+                                self.notify(Level::Error, e);
+                                continue;
+                            }
+                        };
+                        self.page_layouts.insert(result.page_index, result.layout);
+                        let is_current = result.page_index == self.current_page_index
+                            && result.color_mode == self.color_mode();
+                        if is_current && result.target_width == self.render_size.0 {
+                            self.resize_render_in_flight = false;
+                        }
+                        // Renders requested before a resize settled; keep them only as a preview.
+                        let stale = result.target_width != self.render_size.0;
+                        if stale && !(is_current && self.current_image.is_none()) {
+                            continue;
+                        }
+
+                        let handle =
+                            image::Handle::from_rgba(result.width, result.height, result.pixels);
+
+                        if !stale {
+                            self.page_cache
+                                .insert((result.page_index, result.color_mode), handle.clone());
+                        }
+                        let cache_size = self.config.cache_size;
+                        let keep_radius = cache_size / 2;
+                        if self.page_cache.len() > cache_size {
+                            let keys: Vec<(usize, ColorMode)> =
+                                self.page_cache.keys().copied().collect();
+                            let mut to_remove = Vec::new();
+                            for key in keys {
+                                let (page, _) = key;
+                                if page < self.current_page_index.saturating_sub(keep_radius)
+                                    || page > self.current_page_index + keep_radius
+                                {
+                                    to_remove.push(key);
+                                    if self.page_cache.len() - to_remove.len() <= cache_size {
+                                        break;
+                                    }
+                                }
+                            }
+                            for key in to_remove {
+                                self.page_cache.remove(&key);
+                            }
+                        }
+
+                        if is_current {
+                            self.current_image = Some(handle.clone());
+                            if let Some(ink) = &mut self.ink {
+                                ink.finished.clear();
+                            }
+                            if result.clamped {
+                                self.notify(
+                                    Level::Warning,
+                                    format!(
+                                        "Page {} exceeds max_render_pixels; rendered at reduced resolution",
+                                        result.page_index + 1
+                                    ),
+                                );
+                            }
+
+                            if !self.window_fitted
+                                && !self.fullscreen
+                                && let Some(id) = self.window_id
+                            {
+                                self.window_fitted = true;
+                                let aspect_ratio = result.width as f32 / result.height as f32;
+                                let new_height = self.config.window_height;
+                                let new_width = new_height * aspect_ratio;
+                                task = window::resize(id, Size::new(new_width, new_height));
+                            }
+                        }
+                    }
+                }
+
+                self.poll_split();
+                self.rerender_after_resize();
+                Task::batch([task, self.apply_window_mode()])
+            }
+            Message::EventOccurred(event) => {
+                match event {
+                    Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => {
+                        return self.handle_key_press(key, modifiers);
+                    }
+                    Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                        self.modifiers = modifiers;
+                    }
+                    Event::Touch(event) => self.handle_touch(event),
+                    _ => {}
+                }
+                Task::none()
+            }
+            Message::Ink(input) => {
+                self.handle_ink(input);
+                Task::none()
+            }
+            Message::ShowBuffer(index) => {
+                self.switch_buffer(index);
+                Task::none()
+            }
+            Message::PageAreaResized(size) => {
+                let before = ui::page_frame(self.page_area_size, self.page_fit());
+                let from = self.page_area_center();
+                self.page_area_size = size;
+                self.keep_anchored(before, from, self.page_area_center());
+                Task::none()
+            }
+            Message::Frame(now) => {
+                self.animate(now);
+                Task::none()
+            }
+            Message::CursorMoved(position) => {
+                self.cursor = position;
+                self.drag_page();
+                Task::none()
+            }
+            Message::PagePressed => {
+                let range = ui::scroll_range(self.page_area_size, self.page_fit());
+                self.drag = Some(Drag {
+                    start: self.cursor,
+                    pan: self.pan.min(range.x),
+                    scroll: self.scroll.min(range.y),
+                });
+                self.scroll_animation = None;
+                Task::none()
+            }
+            Message::PageReleased => {
+                self.drag = None;
+                Task::none()
+            }
+            Message::WheelScrolled(delta) => {
+                self.handle_wheel(delta);
+                Task::none()
+            }
+            Message::ForWindow(_, message) => self.update(*message),
+            Message::WindowEvent(id, event) => {
+                self.window_id = Some(id);
+                match event {
+                    window::Event::CloseRequested => self.quit(),
+                    window::Event::FileDropped(path) if library::is_pdf(&path) => {
+                        self.open_document(path.to_string_lossy().into_owned(), None);
+                    }
+                    window::Event::FileDropped(path) => {
+                        self.notify(Level::Error, format!("{} isn't a PDF", path.display()));
+                    }
+                    window::Event::Opened { position, .. } => {
+                        self.window_position = position;
+                    }
+                    window::Event::Moved(position) => {
+                        self.window_position = Some(position);
+                    }
+                    window::Event::Resized(size) if size != self.window_size => {
+                        self.window_size = size;
+                        self.resize_pending_since = Some(Instant::now());
+                    }
+                    _ => {}
+                }
+                Task::none()
+            }
+        }
+    }
+
+    fn handle_key_press(&mut self, key: Key, modifiers: Modifiers) -> Task<Message> {
+        self.status_message = None;
+        self.banner = None;
+        self.highlights = None;
+
+        if self.overlay.is_some() {
+            return self.handle_overlay_key(key);
+        } else if self.password_prompt.is_some() {
+            self.handle_password_key(key);
+        } else if self.command_input.is_some() {
+            self.handle_command_key(key);
+        } else if self.hints.is_some() {
+            self.handle_hint_key(key);
+        } else if self.selection.is_some() {
+            return self.handle_visual_key(key);
+        } else if self.form.is_some() {
+            self.handle_form_key(key, modifiers);
+        } else if self.toc_open {
+            self.handle_toc_key(key);
+        } else if let Some(pending) = self.pending_key.take() {
+            self.handle_pending_key(pending, key);
+        } else if self.ink.is_some() && key == Key::Named(Named::Escape) {
+            self.ink = None;
+        } else {
+            self.handle_normal_key(key, modifiers);
+        }
+        Task::none()
+    }
+
+    fn handle_normal_key(&mut self, key: Key, modifiers: Modifiers) {
+        if self.scripts.run_key(self.script_view(), &key, modifiers) {
+            return;
+        }
+        if let Some(action) = self.config.keybindings.action_for(&key, modifiers) {
+            if !self.dispatch_plugins(|plugin, cx| plugin.handle_action(action, cx)) {
+                self.perform_action(action);
+            }
+            return;
+        }
+
+        if let Key::Character(c) = key.as_ref()
+            && c.chars().all(|ch| ch.is_numeric())
+        {
+            self.command_input = Some(c.to_string());
+        }
+    }
+
+    fn perform_action(&mut self, action: Action) {
+        let last_page = (self.total_pages as usize).saturating_sub(1);
+        match action {
+            Action::NextPage => {
+                let step = self.config.scroll_step * self.page_area_size.height;
+                self.scroll_or_turn(step, self.config.scroll_amount);
+            }
+            Action::PreviousPage => {
+                let step = self.config.scroll_step * self.page_area_size.height;
+                self.scroll_or_turn(-step, self.config.scroll_amount);
+            }
+            Action::HalfPageDown => self.scroll_or_turn(self.page_area_size.height / 2.0, 1),
+            Action::HalfPageUp => self.scroll_or_turn(-self.page_area_size.height / 2.0, 1),
+            Action::FirstPage => self.jump_to_page(0),
+            Action::LastPage => self.jump_to_page(last_page),
+            Action::CommandLine => {
+                self.command_input = Some(String::new());
+            }
+            Action::SetMark => {
+                self.pending_key = Some(PendingKey::SetMark);
+            }
+            Action::GoToMark => {
+                self.pending_key = Some(PendingKey::GoToMark);
+            }
+            Action::JumpBack => {
+                if let Some(target) = self.jump_list.back(self.current_page_index) {
+                    self.go_to_page(target);
+                }
+            }
+            Action::JumpForward => {
+                if let Some(target) = self.jump_list.forward() {
+                    self.go_to_page(target);
+                }
+            }
+            Action::ToggleToc => self.toggle_toc(),
+            Action::LinkHints => self.show_link_hints(),
+            Action::GoBack => match self.back_stack.pop() {
+                Some(target) => self.go_to_page(target),
+                None => self.notify(Level::Info, "Already at oldest link jump"),
+            },
+            Action::Visual => self.start_visual(),
+            Action::ShowComment => self.show_comment(),
+            Action::Draw => self.toggle_drawing(),
+            Action::Form => self.toggle_form(),
+            Action::Invert => self.set_option("invert", &SetValue::Toggle),
+            Action::ToggleStatusBar => self.set_option("statusbar", &SetValue::Toggle),
+            Action::ToggleZen => self.set_option("zen", &SetValue::Toggle),
+            Action::CycleTint => self.set_option("tint", &SetValue::Toggle),
+            Action::BrightnessUp => self.adjust_level("brightness", self.config.brightness, 0.05),
+            Action::BrightnessDown => {
+                self.adjust_level("brightness", self.config.brightness, -0.05)
+            }
+            Action::ContrastUp => self.adjust_level("contrast", self.config.contrast, 0.1),
+            Action::ContrastDown => self.adjust_level("contrast", self.config.contrast, -0.1),
+            Action::GammaUp => self.adjust_level("gamma", self.config.gamma, 0.1),
+            Action::GammaDown => self.adjust_level("gamma", self.config.gamma, -0.1),
+            Action::NextObject => {
+                self.pending_key = Some(PendingKey::Next);
+            }
+            Action::GPrefix => {
+                self.pending_key = Some(PendingKey::G);
+            }
+            Action::WindowPrefix => {
+                self.pending_key = Some(PendingKey::Window);
+            }
+            Action::PreviousObject => {
+                self.pending_key = Some(PendingKey::Previous);
+            }
+            Action::Quit if self.has_unwritten_buffers() => {
+                self.status_message = Some("Quit without writing annotations? (y/n)".to_string());
+                self.pending_key = Some(PendingKey::ConfirmQuit);
+            }
+            Action::Help => self.show_keybindings(),
+            Action::Quit => self.quit(),
+        }
+    }
+
+    /// Lists the keys of the active keymap, user remaps included, by category.
+    fn show_keybindings(&mut self) {
+        let mut entries = Vec::new();
+        for (category, actions) in self.config.keybindings.cheatsheet() {
+            entries.push(OverlayEntry {
+                label: category.to_string(),
+                target: OverlayTarget::Heading,
+            });
+            entries.extend(actions.into_iter().map(|(action, keys)| OverlayEntry {
+                label: format!("  {}  {}", keys.join(" "), action.name().replace('_', " ")),
+                target: OverlayTarget::Action(action),
+            }));
+        }
+        self.overlay = Some(Overlay::new("Keys (Enter: run)", entries));
+    }
+
+    fn handle_pending_key(&mut self, pending: PendingKey, key: Key) {
+        let Key::Character(c) = key.as_ref() else {
+            return;
+        };
+        let mut chars = c.chars();
+        let (Some(name), None) = (chars.next(), chars.next()) else {
+            return;
+        };
+        if !name.is_ascii_alphabetic() {
+            return;
+        }
+
+        match pending {
+            PendingKey::SetMark => {
+                self.marks.set(name, self.current_page_index);
+            }
+            PendingKey::GoToMark => match self.marks.get(name) {
+                Some(target) => self.jump_to_page(target),
+                None => self.notify(Level::Error, format!("Mark not set: {}", name)),
+            },
+            PendingKey::ConfirmOpen(uri) => {
+                if name == 'y' {
+                    self.open_uri(&uri);
+                }
+            }
+            PendingKey::Next if name == 'a' => self.next_annotation(true),
+            PendingKey::Previous if name == 'a' => self.next_annotation(false),
+            PendingKey::Next if name == 's' => {
+                self.next_pending_field(PendingFieldKind::Signature, true)
+            }
+            PendingKey::Previous if name == 's' => {
+                self.next_pending_field(PendingFieldKind::Signature, false)
+            }
+            PendingKey::Next if name == 'f' => {
+                self.next_pending_field(PendingFieldKind::Required, true)
+            }
+            PendingKey::Previous if name == 'f' => {
+                self.next_pending_field(PendingFieldKind::Required, false)
+            }
+            PendingKey::Next | PendingKey::Previous => {}
+            PendingKey::G if name == 't' => self.cycle_buffer(1),
+            PendingKey::G if name == 'T' => self.cycle_buffer(-1),
+            PendingKey::G => {}
+            PendingKey::Window => match name {
+                'v' => self.vsplit(None),
+                'w' | 'p' => self.focus_other_pane(),
+                'h' | 'l'
+                    if self
+                        .split
+                        .as_ref()
+                        .is_some_and(|split| split.left == (name == 'h')) =>
+                {
+                    self.focus_other_pane()
+                }
+                'o' => self.close_pane(true),
+                'c' | 'q' => self.close_pane(false),
+                _ => {}
+            },
+            PendingKey::ConfirmQuit => {
+                if name == 'y' {
+                    self.quit();
+                }
+            }
+        }
+    }
+
+    fn handle_command_key(&mut self, key: Key) {
+        let Some(input) = &mut self.command_input else {
+            return;
+        };
+
+        match key.as_ref() {
+            Key::Named(Named::Enter) => {
+                let input = self.command_input.take().unwrap_or_default();
+                self.run_command(&input);
+            }
+            Key::Named(Named::Backspace) => {
+                input.pop();
+                if input.is_empty() {
+                    self.command_input = None;
+                }
+            }
+            Key::Named(Named::Escape) => {
+                self.command_input = None;
+            }
+            Key::Named(Named::Space) => {
+                input.push(' ');
+            }
+            Key::Character(c) => {
+                input.push_str(c);
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_password_key(&mut self, key: Key) {
+        let Some(prompt) = &mut self.password_prompt else {
+            return;
+        };
+
+        match key.as_ref() {
+            Key::Named(Named::Enter) if prompt.user_password.is_none() => {
+                prompt.user_password = Some(std::mem::take(&mut prompt.input));
+            }
+            Key::Named(Named::Enter) => {
+                let Some(prompt) = self.password_prompt.take() else {
+                    return;
+                };
+                let user_password = prompt.user_password.unwrap_or_default();
+                // Without an owner password anyone could lift the restrictions, so the
+                // user password doubles as one.
+                let owner_password = if prompt.input.is_empty() {
+                    user_password.clone()
+                } else {
+                    prompt.input
+                };
+                if owner_password.is_empty() {
+                    self.notify(Level::Error, "Not written: no password given");
+                    return;
+                }
+                let _ = self.render_tx.send(RenderCommand::SaveEncrypted {
+                    path: prompt.path,
+                    user_password,
+                    owner_password,
+                });
+            }
+            Key::Named(Named::Backspace) => {
+                prompt.input.pop();
+            }
+            Key::Named(Named::Escape) => {
+                self.password_prompt = None;
+                self.notify(Level::Info, "Not written");
+            }
+            Key::Named(Named::Space) => prompt.input.push(' '),
+            Key::Character(c) => prompt.input.push_str(c),
+            _ => {}
+        }
+    }
+
+    fn handle_toc_key(&mut self, key: Key) {
+        match key.as_ref() {
+            Key::Character("j") | Key::Named(Named::ArrowDown) => self.toc.select_next(),
+            Key::Character("k") | Key::Named(Named::ArrowUp) => self.toc.select_previous(),
+            Key::Character("h") | Key::Named(Named::ArrowLeft) => self.toc.collapse(),
+            Key::Character("l") | Key::Named(Named::ArrowRight) => self.toc.expand(),
+            Key::Named(Named::Enter) => match self.toc.selected_page() {
+                Some(page_index) => self.follow_to_page(page_index),
+                None => self.notify(Level::Info, "Entry has no destination in this document"),
+            },
+            Key::Character(":") => self.command_input = Some(String::new()),
+            Key::Character("q") | Key::Named(Named::Tab) | Key::Named(Named::Escape) => {
+                self.toc_open = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Lists the links on the current page in reading order.
+    fn list_links(&mut self) {
+        let mut links: Vec<&PageLink> = self
+            .page_layouts
+            .get(&self.current_page_index)
+            .map(|layout| layout.links.iter().collect())
+            .unwrap_or_default();
+        if links.is_empty() {
+            self.notify(Level::Info, "No links on this page");
+            return;
+        }
+        links.sort_by(|a, b| {
+            (a.bounds.y, a.bounds.x)
+                .partial_cmp(&(b.bounds.y, b.bounds.x))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let entries = links
+            .into_iter()
+            .map(|link| {
+                let destination = match &link.target {
+                    LinkTarget::Page(page_index) => format!("page {}", page_index + 1),
+                    LinkTarget::Uri(uri) => uri.clone(),
+                };
+                OverlayEntry {
+                    label: format!("{}  -> {}", link.text, destination),
+                    target: OverlayTarget::Link(link.target.clone()),
+                }
+            })
+            .collect();
+        self.overlay = Some(Overlay::new("Links", entries));
+    }
+
+    fn show_link_hints(&mut self) {
+        match self.page_layouts.get(&self.current_page_index) {
+            Some(layout) if !layout.links.is_empty() => {
+                self.hints = Some(LinkHints::new(&layout.links));
+            }
+            _ => self.notify(Level::Info, "No links on this page"),
+        }
+    }
+
+    fn handle_hint_key(&mut self, key: Key) {
+        let Some(hints) = &mut self.hints else {
+            return;
+        };
+
+        match key.as_ref() {
+            Key::Named(Named::Escape) => self.hints = None,
+            Key::Named(Named::Backspace) => hints.pop(),
+            Key::Character(c) => {
+                let Some(c) = c.chars().next() else {
+                    return;
+                };
+                match hints.push(c) {
+                    HintInput::Pending => {}
+                    HintInput::Follow(target) => {
+                        self.hints = None;
+                        self.follow_link(&target);
+                    }
+                    HintInput::NoMatch => {
+                        self.hints = None;
+                        self.notify(Level::Info, "No link hint matches");
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Enters visual mode with the cursor on the first word of the page.
+    fn start_visual(&mut self) {
+        match self.page_layouts.get(&self.current_page_index) {
+            Some(layout) if !layout.words.is_empty() => {
+                self.selection = Some(Selection::new(0));
+            }
+            _ => self.notify(Level::Info, "No text on this page"),
+        }
+    }
+
+    fn handle_visual_key(&mut self, key: Key) -> Task<Message> {
+        let (Some(selection), Some(layout)) = (
+            &mut self.selection,
+            self.page_layouts.get(&self.current_page_index),
+        ) else {
+            self.selection = None;
+            return Task::none();
+        };
+        let words = &layout.words;
+
+        match key.as_ref() {
+            Key::Character("w") | Key::Character("l") | Key::Named(Named::ArrowRight) => {
+                selection.next_word(words)
+            }
+            Key::Character("b") | Key::Named(Named::ArrowLeft) => selection.previous_word(),
+            Key::Character("j") | Key::Named(Named::ArrowDown) => selection.next_line(words),
+            Key::Character("k") | Key::Named(Named::ArrowUp) => selection.previous_line(words),
+            Key::Character("o") => selection.swap_ends(),
+            Key::Named(Named::Space) => selection.anchor = selection.cursor,
+            Key::Character("y")
+                if self.config.respect_copy_restrictions && !self.info.permissions.copy =>
+            {
+                self.selection = None;
+                self.notify(Level::Warning, "The document does not allow copying text");
+            }
+            Key::Character("y") => {
+                let text = selection.text(words);
+                self.selection = None;
+                return self.copy_to_clipboard(text);
+            }
+            Key::Character("h") => self.mark_up_selection(MarkupKind::Highlight, None),
+            Key::Character("u") => self.mark_up_selection(MarkupKind::Underline, None),
+            Key::Character("s") => self.mark_up_selection(MarkupKind::Strikeout, None),
+            Key::Character("~") => self.mark_up_selection(MarkupKind::Squiggly, None),
+            Key::Character("K") => self.show_comment(),
+            Key::Character(":") => self.command_input = Some(String::new()),
+            Key::Character("v") | Key::Named(Named::Escape) => self.selection = None,
+            _ => {}
+        }
+        Task::none()
+    }
+
+    /// Adds a markup annotation over the selection and leaves visual mode; `color`
+    /// defaults to the one configured for `kind`.
+    fn mark_up_selection(&mut self, kind: MarkupKind, color: Option<String>) {
+        let (Some(selection), Some(layout)) = (
+            &self.selection,
+            self.page_layouts.get(&self.current_page_index),
+        ) else {
+            self.notify(Level::Error, "No selection (press v to select text)");
+            return;
+        };
+        let color = match color {
+            Some(color) => match MarkupColor::try_from(color) {
+                Ok(color) => color.0,
+                Err(e) => {
+                    self.notify(Level::Error, e);
+                    return;
+                }
+            },
+            None => self.config.markup_colors.get(kind),
+        };
+
+        let rects = selection.line_rects(&layout.words);
+        self.selection = None;
+        self.modified = true;
+        let _ = self.render_tx.send(RenderCommand::AddMarkup {
+            page_index: self.current_page_index,
+            kind,
+            rects,
+            color,
+        });
+        self.forget_rendered(self.current_page_index);
+        self.request_render(self.current_page_index);
+    }
+
+    fn toggle_drawing(&mut self) {
+        if self.ink.take().is_none() {
+            self.ink = Some(Ink::default());
+            self.status_message = Some("Drag to draw; Esc to stop".to_string());
+        }
+    }
+
+    fn toggle_form(&mut self) {
+        if self.form.take().is_none() {
+            let mut form = Form::default();
+            match self.page_layouts.get(&self.current_page_index) {
+                Some(layout) if !layout.fields.is_empty() => form.next(&layout.fields),
+                _ => self.notify(Level::Info, "No form fields on this page"),
+            }
+            self.form = Some(form);
+        }
+        let _ = self
+            .render_tx
+            .send(RenderCommand::HighlightFields(self.form.is_some()));
+        self.page_cache.clear();
+        self.request_render(self.current_page_index);
+    }
+
+    /// Tab/Shift-Tab move between the fields of the page, typing fills text fields,
+    /// Space toggles checkboxes and radio buttons and j/k cycle choice fields.
+    fn handle_form_key(&mut self, key: Key, modifiers: Modifiers) {
+        let (Some(form), Some(layout)) = (
+            &mut self.form,
+            self.page_layouts.get(&self.current_page_index),
+        ) else {
+            return;
+        };
+        let fields = &layout.fields;
+
+        let field = match key.as_ref() {
+            Key::Named(Named::Escape) => {
+                self.toggle_form();
+                return;
+            }
+            Key::Named(Named::Tab) if modifiers.shift() => {
+                form.previous(fields);
+                return;
+            }
+            Key::Named(Named::Tab) | Key::Named(Named::Enter) => {
+                form.next(fields);
+                return;
+            }
+            _ => match form.focused.and_then(|i| fields.get(i)) {
+                Some(field) => field,
+                None => return,
+            },
+        };
+        let value = match (field.kind, key.as_ref()) {
+            (FieldKind::Checkbox, Key::Named(Named::Space)) => FieldValue::Checked(!field.checked),
+            (FieldKind::RadioButton, Key::Named(Named::Space)) => FieldValue::Checked(true),
+            (FieldKind::Text, Key::Named(Named::Backspace)) => {
+                form.value.pop();
+                FieldValue::Text(form.value.clone())
+            }
+            (FieldKind::Text, Key::Named(Named::Space)) => {
+                form.value.push(' ');
+                FieldValue::Text(form.value.clone())
+            }
+            (FieldKind::Text, Key::Character(c)) if !modifiers.control() => {
+                form.value.push_str(c);
+                FieldValue::Text(form.value.clone())
+            }
+            (FieldKind::Choice, Key::Character(c @ ("j" | "k"))) if !field.options.is_empty() => {
+                let count = field.options.len();
+                let next = match field.options.iter().position(|o| *o == field.value) {
+                    Some(i) if c == "j" => (i + 1) % count,
+                    Some(i) => (i + count - 1) % count,
+                    None if c == "j" => 0,
+                    None => count - 1,
+                };
+                FieldValue::Choice(field.options[next].clone())
+            }
+            _ => return,
+        };
+
+        self.modified = true;
+        let _ = self.render_tx.send(RenderCommand::SetField {
+            page_index: self.current_page_index,
+            annotation_index: field.annotation_index,
+            value,
+        });
+        self.forget_rendered(self.current_page_index);
+        self.request_render(self.current_page_index);
+    }
+
+    fn handle_ink(&mut self, input: InkInput) {
+        let Some(ink) = &mut self.ink else {
+            return;
+        };
+        let stroke = match input {
+            InkInput::Press => {
+                ink.press();
+                return;
+            }
+            InkInput::Move(position) => {
+                ink.move_to(position);
+                return;
+            }
+            InkInput::Release => ink.release(),
+        };
+        if let Some(stroke) = stroke {
+            self.modified = true;
+            let _ = self.render_tx.send(RenderCommand::AddInk {
+                page_index: self.current_page_index,
+                strokes: vec![stroke],
+                color: self.config.ink_color.0,
+                width: self.config.ink_width,
+            });
+            self.forget_rendered(self.current_page_index);
+            self.request_render(self.current_page_index);
+        }
+    }
+
+    /// Adds a note at the start of the selection, or at the top-left of the page.
+    fn add_note(&mut self, text: String) {
+        let position = match (
+            &self.selection,
+            self.page_layouts.get(&self.current_page_index),
+        ) {
+            (Some(selection), Some(layout)) => {
+                let bounds = layout.words[selection.range().0].bounds;
+                Point::new(bounds.x, bounds.y)
+            }
+            _ => Point::new(NOTE_MARGIN, NOTE_MARGIN),
+        };
+        self.selection = None;
+        self.modified = true;
+        let _ = self.render_tx.send(RenderCommand::AddNote {
+            page_index: self.current_page_index,
+            position,
+            text,
+        });
+        self.forget_rendered(self.current_page_index);
+        self.request_render(self.current_page_index);
+    }
+
+    /// Lists the notes on the current page in reading order.
+    fn list_notes(&mut self) {
+        let mut notes: Vec<&PageNote> = self
+            .page_layouts
+            .get(&self.current_page_index)
+            .map(|layout| layout.notes.iter().collect())
+            .unwrap_or_default();
+        if notes.is_empty() {
+            self.notify(Level::Info, "No notes on this page");
+            return;
+        }
+        notes.sort_by(|a, b| {
+            (a.bounds.y, a.bounds.x)
+                .partial_cmp(&(b.bounds.y, b.bounds.x))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let entries = notes
+            .into_iter()
+            .map(|note| OverlayEntry {
+                label: note
+                    .contents
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                target: OverlayTarget::Text(note.contents.clone()),
+            })
+            .collect();
+        self.overlay = Some(Overlay::new("Notes (Enter/y: copy)", entries));
+    }
+
+    fn focused_field(&self) -> Option<&PageField> {
+        let focused = self.form.as_ref()?.focused?;
+        self.page_layouts
+            .get(&self.current_page_index)?
+            .fields
+            .get(focused)
+    }
+
+    /// The annotation under the visual-mode cursor, otherwise the one `]a`/`[a` last
+    /// moved to if it is on the current page.
+    fn annotation_at_cursor(&self) -> Option<&DocumentAnnotation> {
+        let page = self.current_page_index;
+        if let Some(selection) = &self.selection {
+            let word = self.page_layouts.get(&page)?.words.get(selection.cursor)?;
+            return self.annotations.iter().find(|annotation| {
+                annotation.page_index == page && annotation.bounds.contains(word.bounds.center())
+            });
+        }
+        self.annotation_cursor
+            .and_then(|i| self.annotations.get(i))
+            .filter(|annotation| annotation.page_index == page)
+    }
+
+    /// Opens the comment of the annotation at the cursor in a scrollable overlay.
+    fn show_comment(&mut self) {
+        let Some(annotation) = self.annotation_at_cursor() else {
+            self.notify(Level::Info, "No annotation at the cursor");
+            return;
+        };
+        if annotation.note.is_empty() {
+            self.notify(Level::Info, "The annotation has no comment");
+            return;
+        }
+        let title = format!(
+            "{} on p. {} (Enter/y: copy)",
+            annotation.kind,
+            annotation.page_index + 1
+        );
+        let entries = overlay::wrap(&annotation.note, COMMENT_WIDTH)
+            .into_iter()
+            .map(|line| OverlayEntry {
+                label: line,
+                target: OverlayTarget::Text(annotation.note.clone()),
+            })
+            .collect();
+        self.overlay = Some(Overlay::new(title, entries));
+    }
+
+    fn show_info(&mut self) {
+        let permissions = &self.info.permissions;
+        let allowed = |allowed: bool| if allowed { "allowed" } else { "not allowed" };
+        let mut fields: Vec<(&str, String)> = self.info.metadata.clone();
+        fields.push(("Pages", self.total_pages.to_string()));
+        let encrypted = if self.info.encrypted { "yes" } else { "no" };
+        fields.push(("Encrypted", encrypted.to_string()));
+        fields.extend([
+            ("Print", allowed(permissions.print).to_string()),
+            ("Copy", allowed(permissions.copy).to_string()),
+            ("Modify", allowed(permissions.modify).to_string()),
+            ("Annotate", allowed(permissions.annotate).to_string()),
+            ("Fill forms", allowed(permissions.fill_forms).to_string()),
+        ]);
+
+        let entries = fields
+            .into_iter()
+            .map(|(label, value)| OverlayEntry {
+                label: format!("{}: {}", label, value),
+                target: OverlayTarget::Text(value),
+            })
+            .collect();
+        self.overlay = Some(Overlay::new(
+            format!("{} (Enter/y: copy)", self.file_name),
+            entries,
+        ));
+    }
+
+    fn show_buffers(&mut self) {
+        let entries = self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(index, file)| {
+                let current = if index == self.current_buffer {
+                    '%'
+                } else {
+                    ' '
+                };
+                OverlayEntry {
+                    label: format!("{:>2} {} {}", index + 1, current, file),
+                    target: OverlayTarget::Buffer(index),
+                }
+            })
+            .collect();
+        self.overlay = Some(Overlay::new("Buffers (Enter: show)", entries));
+    }
+
+    /// Documents from the state store, most recently left first, filtered as typed.
+    fn show_recent(&mut self) {
+        self.save_document_state();
+        let store = match StateStore::load() {
+            Ok(store) => store,
+            Err(e) => {
+                self.notify(Level::Error, e.to_string());
+                return;
+            }
+        };
+        let entries = store
+            .recent()
+            .into_iter()
+            .filter(|state| state.path.exists())
+            .map(|state| OverlayEntry {
+                label: state.path.display().to_string(),
+                target: OverlayTarget::Document(state.path.clone(), state.last_page),
+            })
+            .collect();
+        let mut overlay = Overlay::new("Recent documents (Enter: open)", entries);
+        overlay.start_filter();
+        self.overlay = Some(overlay);
+    }
+
+    /// The PDFs under the document's directory, filtered as typed.
+    fn show_file_picker(&mut self) {
+        let root = match Path::new(&self.file_name).parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let (mut files, truncated) = library::nearby_pdfs(&root, PICKER_DEPTH, PICKER_ENTRIES);
+        files.sort();
+        let entries = files
+            .into_iter()
+            .map(|path| OverlayEntry {
+                label: path
+                    .strip_prefix(&root)
+                    .unwrap_or(&path)
+                    .display()
+                    .to_string(),
+                target: OverlayTarget::File(path),
+            })
+            .collect();
+        let title = if truncated {
+            format!("Open from {}, partly listed (Enter: open)", root.display())
+        } else {
+            format!("Open from {} (Enter: open)", root.display())
+        };
+        let mut overlay = Overlay::new(title, entries);
+        overlay.start_filter();
+        self.overlay = Some(overlay);
+    }
+
+    fn show_attachments(&mut self) {
+        if self.attachments.is_empty() {
+            self.notify(Level::Info, "No embedded files");
+            return;
+        }
+        let entries = self
+            .attachments
+            .iter()
+            .enumerate()
+            .map(|(index, attachment)| OverlayEntry {
+                label: format!("{}  ({})", attachment.name, format_size(attachment.size)),
+                target: OverlayTarget::Attachment(index),
+            })
+            .collect();
+        self.overlay = Some(Overlay::new("Attachments (Enter: save)", entries));
+    }
+
+    /// Extracts embedded file `index`, by default under its own name next to the
+    /// document without overwriting anything.
+    fn save_attachment(&mut self, index: usize, path: Option<String>) {
+        let Some(attachment) = self.attachments.get(index) else {
+            self.notify(Level::Error, format!("No attachment {}", index + 1));
+            return;
+        };
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => {
+                // Only the last component, so a crafted name cannot point elsewhere.
+                let name = match Path::new(&attachment.name).file_name() {
+                    Some(name) => name.to_os_string(),
+                    None => format!("attachment-{}", index + 1).into(),
+                };
+                let path = Path::new(&self.file_name).with_file_name(name);
+                if path.exists() {
+                    self.notify(
+                        Level::Error,
+                        format!(
+                            "{} exists; use :attachments save {} <file>",
+                            path.display(),
+                            index + 1
+                        ),
+                    );
+                    return;
+                }
+                path
+            }
+        };
+        let _ = self
+            .render_tx
+            .send(RenderCommand::SaveAttachment { index, path });
+    }
+
+    fn show_signatures(&mut self) {
+        if self.signatures.is_empty() {
+            self.notify(Level::Info, "The document is not signed");
+            return;
+        }
+        let entries = self
+            .signatures
+            .iter()
+            .map(|signature| {
+                let summary = signature.summary();
+                OverlayEntry {
+                    label: summary.clone(),
+                    target: OverlayTarget::Text(summary),
+                }
+            })
+            .collect();
+        self.overlay = Some(Overlay::new("Signatures (not verified)", entries));
+    }
+
+    /// "signed" when every signature covers the whole file, otherwise a warning that
+    /// the document changed after signing.
+    fn signature_badge(&self) -> Option<String> {
+        if self.signatures.is_empty() {
+            None
+        } else if self
+            .signatures
+            .iter()
+            .all(|s| s.modified_after == Some(false))
+        {
+            Some("signed".to_string())
+        } else if self
+            .signatures
+            .iter()
+            .any(|s| s.modified_after == Some(true))
+        {
+            Some("signed, modified since".to_string())
+        } else {
+            Some("signed?".to_string())
+        }
+    }
+
+    fn show_annotations(&mut self) {
+        if self.annotations.is_empty() {
+            self.notify(Level::Info, "No annotations in this document");
+            return;
+        }
+        let entries = self
+            .annotations
+            .iter()
+            .map(|annotation| OverlayEntry {
+                label: format!(
+                    "p. {:<4} {:<10} {}",
+                    annotation.page_index + 1,
+                    annotation.kind,
+                    annotation.excerpt()
+                ),
+                target: OverlayTarget::Page(annotation.page_index),
+            })
+            .collect();
+        self.overlay = Some(Overlay::new("Annotations", entries));
+    }
+
+    /// Writes the annotations to `path`, by default `<document>.annots.md` next to the
+    /// document.
+    fn export_annotations(&mut self, path: Option<String>) {
+        if self.annotations.is_empty() {
+            self.notify(Level::Info, "No annotations in this document");
+            return;
+        }
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&self.file_name).with_extension("annots.md"),
+        };
+        let contents = annotations::export(&self.annotations, ExportFormat::from_path(&path));
+        match std::fs::write(&path, contents) {
+            Ok(()) => self.notify(Level::Info, format!("Wrote {}", path.display())),
+            Err(e) => self.notify(
+                Level::Error,
+                format!("Could not write {}: {}", path.display(), e),
+            ),
+        }
+    }
+
+    /// Reads annotations from a JSON export or an XFDF file and adds them to the
+    /// document; markup without a color gets the configured one.
+    fn import_annotations(&mut self, path: &str) {
+        let (mut imported, unsupported) = match annotations::import(Path::new(path)) {
+            Ok(result) => result,
+            Err(e) => {
+                self.notify(Level::Error, e);
+                return;
+            }
+        };
+        if unsupported > 0 {
+            self.notify(
+                Level::Warning,
+                format!("Left out {} unsupported annotations", unsupported),
+            );
+        }
+        for item in &mut imported {
+            if let (None, ImportKind::Markup(kind)) = (item.color, item.kind) {
+                item.color = Some(self.config.markup_colors.get(kind));
+            }
+        }
+        if imported.is_empty() {
+            self.notify(Level::Info, format!("No annotations to import in {}", path));
+            return;
+        }
+        self.modified = true;
+        let _ = self
+            .render_tx
+            .send(RenderCommand::ImportAnnotations(imported));
+        self.page_cache.clear();
+        self.request_render(self.current_page_index);
+    }
+
+    /// Moves to the next (or previous) annotation in reading order. Annotations on the
+    /// current page are stepped through one by one once `]a` has landed on one of them.
+    fn next_annotation(&mut self, forward: bool) {
+        let page = self.current_page_index;
+        let on_page = self.annotation_cursor.filter(|&i| {
+            self.annotations
+                .get(i)
+                .is_some_and(|a| a.page_index == page)
+        });
+        let target = match (on_page, forward) {
+            (Some(i), true) => Some(i + 1).filter(|&i| i < self.annotations.len()),
+            (Some(i), false) => i.checked_sub(1),
+            (None, true) => self.annotations.iter().position(|a| a.page_index > page),
+            (None, false) => self.annotations.iter().rposition(|a| a.page_index < page),
+        };
+        let Some(target) = target else {
+            let direction = if forward { "next" } else { "previous" };
+            self.notify(Level::Info, format!("No {} annotation", direction));
+            return;
+        };
+
+        let annotation = &self.annotations[target];
+        let mut message = format!("{}: {}", annotation.kind, annotation.excerpt());
+        if !annotation.note.is_empty() {
+            message.push_str(" (K: open)");
+        }
+        self.annotation_cursor = Some(target);
+        self.jump_to_page(annotation.page_index);
+        self.notify(Level::Info, message);
+    }
+
+    /// Moves to the next unsigned signature field or empty required field. Forward
+    /// from a page not yet visited this way includes the fields on that page.
+    fn next_pending_field(&mut self, kind: PendingFieldKind, forward: bool) {
+        let page = self.current_page_index;
+        let candidates: Vec<usize> = (0..self.pending_fields.len())
+            .filter(|&i| {
+                let field = &self.pending_fields[i];
+                field.kind == kind
+                    && (kind != PendingFieldKind::Signature || self.is_unsigned(&field.name))
+            })
+            .collect();
+        let on_page = self
+            .pending_field_cursor
+            .filter(|&i| candidates.contains(&i) && self.pending_fields[i].page_index == page);
+        let fields = &self.pending_fields;
+        let mut candidates = candidates.into_iter();
+        let target = match (on_page, forward) {
+            (Some(i), true) => candidates.find(|&c| c > i),
+            (Some(i), false) => candidates.rfind(|&c| c < i),
+            (None, true) => candidates.find(|&c| fields[c].page_index >= page),
+            (None, false) => candidates.rfind(|&c| fields[c].page_index < page),
+        };
+        let Some(target) = target else {
+            let direction = if forward { "next" } else { "previous" };
+            let what = match kind {
+                PendingFieldKind::Signature => "unsigned signature field",
+                PendingFieldKind::Required => "empty required field",
+            };
+            self.notify(Level::Info, format!("No {} {}", direction, what));
+            return;
+        };
+
+        let field = &self.pending_fields[target];
+        let label = match kind {
+            PendingFieldKind::Signature => "Sign here",
+            PendingFieldKind::Required => "Required",
+        };
+        let message = format!("{}: {}", label, field.name);
+        self.pending_field_cursor = Some(target);
+        self.jump_to_page(field.page_index);
+        self.notify(Level::Info, message);
+    }
+
+    /// `name` is fully qualified, while the raw file only gives the last part.
+    fn is_unsigned(&self, name: &str) -> bool {
+        let partial = name.rsplit('.').next().unwrap_or(name);
+        self.unsigned_fields
+            .iter()
+            .any(|unsigned| unsigned == partial)
+    }
+
+    /// Writes the document with its annotations, by default over the open file.
+    fn write_document(&mut self, path: Option<String>) {
+        let path = PathBuf::from(path.unwrap_or_else(|| self.file_name.clone()));
+        let _ = self.render_tx.send(RenderCommand::Save(path));
+    }
+
+    /// Writes pages as PNG files, by default the current page next to the document
+    /// as `<name>-<page>.png`. A file name for several pages needs a `%d`.
+    fn export_images(&mut self, pages: Option<&str>, dpi: Option<f32>, path: Option<String>) {
+        let page_indices = match pages {
+            Some(pages) => match pages::page_indices(Some(pages), self.total_pages as usize) {
+                Ok(page_indices) => page_indices,
+                Err(e) => {
+                    self.notify(Level::Error, e);
+                    return;
+                }
+            },
+            None => vec![self.current_page_index],
+        };
+        let document = Path::new(&self.file_name);
+        let pattern = match path {
+            Some(path) if page_indices.len() > 1 && !path.contains("%d") => {
+                self.notify(Level::Error, "The file name needs a %d for several pages");
+                return;
+            }
+            Some(path) => path,
+            None => {
+                let stem = document.file_stem().unwrap_or_default().to_string_lossy();
+                let name = format!("{}-%d.png", stem);
+                document.with_file_name(name).to_string_lossy().into_owned()
+            }
+        };
+        let pages = page_indices
+            .into_iter()
+            .map(|page_index| {
+                let path = pattern.replace("%d", &(page_index + 1).to_string());
+                (page_index, PathBuf::from(path))
+            })
+            .collect();
+        let _ = self.render_tx.send(RenderCommand::ExportImages {
+            pages,
+            dpi: dpi.unwrap_or(self.config.export_dpi),
+        });
+    }
+
+    fn export_document(&mut self, path: String, flatten: bool) {
+        let path = PathBuf::from(path);
+        if flatten && path == Path::new(&self.file_name) {
+            // The flattened copy could no longer be annotated or filled in.
+            self.notify(Level::Error, "Export the flattened copy to another file");
+            return;
+        }
+        let _ = self.render_tx.send(if flatten {
+            RenderCommand::ExportFlattened(path)
+        } else {
+            RenderCommand::Save(path)
+        });
+    }
+
+    fn document_saved(&mut self, path: &Path) {
+        if path == Path::new(&self.file_name) {
+            self.modified = false;
+            if let Some(watcher) = &mut self.watcher {
+                watcher.refresh();
+            }
+            let file = std::fs::read(&self.file_name).unwrap_or_default();
+            if let Ok(pdfium) = engine::init_pdfium()
+                && let Ok(document) = pdfium.load_pdf_from_byte_slice(&file, None)
+            {
+                self.signatures = signatures::read_signatures(&document, &file);
+            }
+            self.unsigned_fields = signatures::unsigned_fields(&file);
+            // The contents changed, and with them the state store key.
+            match state::document_key(path) {
+                Ok(key) => self.state_key = Some(key),
+                Err(e) => self.notify(Level::Warning, format!("Could not hash document: {}", e)),
+            }
+        }
+        self.notify(Level::Info, format!("Wrote {}", path.display()));
+    }
+
+    /// Follows a link, recording the origin in the jump list.
+    fn follow_link(&mut self, target: &LinkTarget) {
+        match target {
+            LinkTarget::Page(page_index) => self.follow_to_page(*page_index),
+            LinkTarget::Uri(uri) if self.config.confirm_external_links => {
+                self.status_message = Some(format!("Open {}? (y/n)", uri));
+                self.pending_key = Some(PendingKey::ConfirmOpen(uri.clone()));
+            }
+            LinkTarget::Uri(uri) => self.open_uri(uri),
+        }
+    }
+
+    fn open_uri(&mut self, uri: &str) {
+        match external::open_uri(uri, &self.config.link_opener) {
+            Ok(()) => self.notify(Level::Info, format!("Opened {}", uri)),
+            Err(e) => self.notify(Level::Error, e),
+        }
+    }
+
+    /// Writes the outline to `path`, by default `<document>.toc.md` next to the document.
+    fn export_toc(&mut self, path: Option<String>) {
+        if self.toc.items.is_empty() {
+            self.notify(Level::Info, "Document has no table of contents");
+            return;
+        }
+        let path = match path {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&self.file_name).with_extension("toc.md"),
+        };
+        let contents = outline::export(&self.toc.items, ExportFormat::from_path(&path));
+        match std::fs::write(&path, contents) {
+            Ok(()) => self.notify(Level::Info, format!("Wrote {}", path.display())),
+            Err(e) => self.notify(
+                Level::Error,
+                format!("Could not write {}: {}", path.display(), e),
+            ),
+        }
+    }
+
+    fn toggle_toc(&mut self) {
+        if self.toc_open {
+            self.toc_open = false;
+        } else if self.toc.items.is_empty() {
+            self.notify(Level::Info, "Document has no table of contents");
+        } else {
+            self.toc.reveal_page(self.current_page_index);
+            self.toc_open = true;
+        }
+    }
+
+    fn handle_overlay_key(&mut self, key: Key) -> Task<Message> {
+        let Some(overlay) = &mut self.overlay else {
+            return Task::none();
+        };
+
+        // Typing into the filter; Enter and the arrow keys still choose.
+        if overlay.typing {
+            let typed = match key.as_ref() {
+                Key::Character(c) => {
+                    overlay.push_filter(c);
+                    true
+                }
+                Key::Named(Named::Space) => {
+                    overlay.push_filter(" ");
+                    true
+                }
+                Key::Named(Named::Backspace) => {
+                    overlay.pop_filter();
+                    true
+                }
+                Key::Named(Named::Escape) => {
+                    overlay.clear_filter();
+                    true
+                }
+                _ => false,
+            };
+            if typed {
+                return Task::none();
+            }
+        }
+
+        match key.as_ref() {
+            Key::Character("/") => overlay.start_filter(),
+            Key::Character("j") | Key::Named(Named::ArrowDown) => overlay.select_next(),
+            Key::Character("k") | Key::Named(Named::ArrowUp) => overlay.select_previous(),
+            Key::Named(Named::Enter) => {
+                let overlay = self.overlay.take();
+                match overlay.as_ref().and_then(|o| o.selected_entry()) {
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Page(page_index),
+                        ..
+                    }) => self.jump_to_page(*page_index),
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Document(path, page_index),
+                        ..
+                    }) => {
+                        self.open_document(path.to_string_lossy().into_owned(), Some(*page_index))
+                    }
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Link(target),
+                        ..
+                    }) => self.follow_link(target),
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Buffer(index),
+                        ..
+                    }) => self.switch_buffer(*index),
+                    Some(OverlayEntry {
+                        target: OverlayTarget::File(path),
+                        ..
+                    }) => self.open_document(path.to_string_lossy().into_owned(), None),
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Text(text),
+                        ..
+                    }) => {
+                        return self.copy_to_clipboard(text.clone());
+                    }
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Attachment(index),
+                        ..
+                    }) => self.save_attachment(*index, None),
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Action(action),
+                        ..
+                    }) => self.perform_action(*action),
+                    Some(OverlayEntry {
+                        target: OverlayTarget::Heading,
+                        ..
+                    })
+                    | None => {}
+                }
+            }
+            Key::Character("y") => {
+                if let Some(OverlayEntry {
+                    target: OverlayTarget::Text(text),
+                    ..
+                }) = overlay.selected_entry()
+                {
+                    let text = text.clone();
+                    return self.copy_to_clipboard(text);
+                }
+            }
+            Key::Character("q") | Key::Named(Named::Escape) => {
+                self.overlay = None;
+            }
+            _ => {}
+        }
+        Task::none()
+    }
+
+    fn run_command(&mut self, input: &str) {
+        if input.trim().is_empty() {
+            return;
+        }
+
+        match command::parse(input) {
+            Ok(Command::GoToPage(page_num)) => {
+                // A matching label wins, so `:5` means the page printed "5".
+                let target = self
+                    .page_for_label(&page_num.to_string())
+                    .unwrap_or(page_num.saturating_sub(1));
+                if target < self.total_pages as usize {
+                    self.jump_to_page(target);
+                }
+            }
+            Ok(Command::Marks) => {
+                let entries = self
+                    .marks
+                    .iter()
+                    .map(|(name, page_index)| OverlayEntry {
+                        label: format!("{}    page {}", name, page_index + 1),
+                        target: OverlayTarget::Page(page_index),
+                    })
+                    .collect();
+                self.overlay = Some(Overlay::new("Marks", entries));
+            }
+            Ok(Command::Jumps) => {
+                let cursor = self.jump_list.cursor();
+                let entries: Vec<OverlayEntry> = self
+                    .jump_list
+                    .entries()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &page_index)| OverlayEntry {
+                        label: format!(
+                            "{} {:>3}    page {}",
+                            if i == cursor { ">" } else { " " },
+                            i.abs_diff(cursor),
+                            page_index + 1
+                        ),
+                        target: OverlayTarget::Page(page_index),
+                    })
+                    .collect();
+                let mut overlay = Overlay::new("Jumps", entries);
+                overlay.selected = cursor.min(overlay.entries.len().saturating_sub(1));
+                self.overlay = Some(overlay);
+            }
+            Ok(Command::LibFind(query)) => self.library_find(&query),
+            Ok(Command::Toc) => self.toggle_toc(),
+            Ok(Command::TocExport(path)) => self.export_toc(path),
+            Ok(Command::Links) => self.list_links(),
+            Ok(Command::Heading(query)) => match outline::find_heading(&self.toc.items, &query) {
+                Some(index) => {
+                    self.toc.select(index);
+                    if let Some(page_index) = self.toc.selected_page() {
+                        self.follow_to_page(page_index);
+                    }
+                    self.notify(Level::Info, self.toc.items[index].title.clone());
+                }
+                None => self.notify(Level::Info, format!("No heading matches: {}", query)),
+            },
+            Ok(Command::Messages) => {
+                let entries = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .map(|entry| OverlayEntry {
+                        label: entry.label(),
+                        target: OverlayTarget::Text(entry.text.clone()),
+                    })
+                    .collect();
+                self.overlay = Some(Overlay::new("Messages (Enter/y: copy)", entries));
+            }
+            Ok(Command::Markup { kind, color }) => self.mark_up_selection(kind, color),
+            Ok(Command::Draw) => self.toggle_drawing(),
+            Ok(Command::Form) => self.toggle_form(),
+            Ok(Command::Note(Some(text))) => self.add_note(text),
+            Ok(Command::Note(None)) => self.list_notes(),
+            Ok(Command::Annotations) => self.show_annotations(),
+            Ok(Command::Info) => self.show_info(),
+            Ok(Command::Buffers) => self.show_buffers(),
+            Ok(Command::Recent) => self.show_recent(),
+            Ok(Command::Open(Some(file))) => self.open_document(file, None),
+            Ok(Command::Open(None)) => self.show_file_picker(),
+            Ok(Command::VSplit(file)) => self.vsplit(file),
+            Ok(Command::NewWindow(file)) => self.new_windows.push(file),
+            Ok(Command::MakeSession(path)) => self.make_session(path),
+            Ok(Command::Only) => self.close_pane(true),
+            Ok(Command::Close) => self.close_pane(false),
+            Ok(Command::NextBuffer) => self.cycle_buffer(1),
+            Ok(Command::PreviousBuffer) => self.cycle_buffer(-1),
+            Ok(Command::Buffer(number)) => match number.checked_sub(1) {
+                Some(index) if index < self.buffers.len() => self.switch_buffer(index),
+                _ => self.notify(Level::Error, format!("No buffer {}", number)),
+            },
+            Ok(Command::Signatures) => self.show_signatures(),
+            Ok(Command::Attachments) => self.show_attachments(),
+            Ok(Command::AttachmentSave(index, path)) => self.save_attachment(index, path),
+            Ok(Command::AnnotationsExport(path)) => self.export_annotations(path),
+            Ok(Command::AnnotationsImport(path)) => self.import_annotations(&path),
+            Ok(Command::Export { path, flatten }) => self.export_document(path, flatten),
+            Ok(Command::ExportImages { pages, dpi, path }) => {
+                self.export_images(pages.as_deref(), dpi, path)
+            }
+            Ok(Command::Write(path)) => self.write_document(path),
+            Ok(Command::WriteEncrypted(path)) => {
+                self.password_prompt = Some(PasswordPrompt {
+                    path: PathBuf::from(path.unwrap_or_else(|| self.file_name.clone())),
+                    user_password: None,
+                    input: String::new(),
+                });
+            }
+            Ok(Command::Set { option, value }) => self.set_option(&option, &value),
+            Ok(Command::External { name, argument }) => {
+                if let Some(target) = self.page_for_label(input.trim()) {
+                    self.jump_to_page(target);
+                } else if !self
+                    .dispatch_plugins(|plugin, cx| plugin.handle_command(&name, &argument, cx))
+                    && !self.plugins.run_command(&name, &argument)
+                    && !self
+                        .scripts
+                        .run_command(self.script_view(), &name, &argument)
+                {
+                    self.notify(
+                        Level::Error,
+                        format!("Not an editor command: {}", input.trim()),
+                    );
+                }
+            }
+            Err(e) => self.notify(Level::Error, e),
+        }
+    }
+
+    fn page_for_label(&self, label: &str) -> Option<usize> {
+        self.page_labels.iter().position(|l| l == label)
+    }
+
+    /// "<title or file name> — p.12/250 — lukia", using the page label when there is one.
+    fn title(&self) -> String {
+        let name = self
+            .info
+            .metadata
+            .iter()
+            .find(|(label, _)| *label == "Title")
+            .map(|(_, title)| title.clone())
+            .or_else(|| {
+                Path::new(&self.file_name)
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| self.file_name.clone());
+        let page = match self.page_labels.get(self.current_page_index) {
+            Some(label) if !label.is_empty() => label.clone(),
+            _ => (self.current_page_index + 1).to_string(),
+        };
+        format!("{} — p.{}/{} — lukia", name, page, self.total_pages)
+    }
+
+    /// "iv / xii (4 / 12)" when the document has page labels, otherwise "4 / 12".
+    fn page_position(&self) -> String {
+        let last = (self.total_pages as usize).saturating_sub(1);
+        match (
+            self.page_labels.get(self.current_page_index),
+            self.page_labels.get(last),
+        ) {
+            (Some(label), Some(last_label)) if !label.is_empty() => format!(
+                "{} / {} ({} / {})",
+                label,
+                last_label,
+                self.current_page_index + 1,
+                self.total_pages
+            ),
+            _ => format!("{} / {}", self.current_page_index + 1, self.total_pages),
+        }
+    }
+
+    /// The status bar's text in normal mode, laid out by the `statusline` format.
+    fn status_line(&self) -> String {
+        let page_index = self.current_page_index;
+        let line = statusline::expand(&self.config.statusline, |field| match field {
+            "position" => self.page_position(),
+            "page" => (page_index + 1).to_string(),
+            "pages" => self.total_pages.to_string(),
+            "label" => match self.page_labels.get(page_index) {
+                Some(label) if !label.is_empty() => label.clone(),
+                _ => (page_index + 1).to_string(),
+            },
+            "percent" => {
+                let (top, extent) = ui::document_position(
+                    page_index,
+                    self.total_pages,
+                    self.page_area_size,
+                    self.page_fit(),
+                );
+                format!("{}%", ((top + extent) * 100.0).round().min(100.0))
+            }
+            "chapter" => {
+                outline::chapter_position(&self.toc.items, page_index, self.total_pages as usize)
+                    .map(|(page, pages)| format!("{}/{}", page, pages))
+                    .unwrap_or_default()
+            }
+            _ => String::new(),
+        });
+        line.trim().to_string()
+    }
+
+    fn set_option(&mut self, option: &str, value: &SetValue) {
+        let mode = self.color_mode();
+        let frame = ui::page_frame(self.page_area_size, self.page_fit());
+        let result = match option {
+            "osd" => value.apply_flag(option, &mut self.config.osd),
+            "fit_width" => value.apply_flag(option, &mut self.config.fit_width),
+            "statusbar" => value.apply_flag(option, &mut self.config.statusbar),
+            "zen" => value.apply_flag(option, &mut self.config.zen),
+            "zen_dim" => value.apply_flag(option, &mut self.config.zen_dim),
+            "scrollbar" => value.apply_flag(option, &mut self.config.scrollbar),
+            "transitions" => value.apply_flag(option, &mut self.config.transitions),
+            "smooth_scroll" => value.apply_flag(option, &mut self.config.smooth_scroll),
+            "invert" => value.apply_flag(option, &mut self.config.invert),
+            "recolor" => value.apply_flag(option, &mut self.config.recolor),
+            "tint" => self.set_tint(value),
+            "grayscale" => value.apply_flag(option, &mut self.config.grayscale),
+            "eink" => value.apply_flag(option, &mut self.config.eink),
+            "brightness" => set_level(
+                option,
+                value,
+                &mut self.config.brightness,
+                config::BRIGHTNESS_RANGE,
+            ),
+            "contrast" => set_level(
+                option,
+                value,
+                &mut self.config.contrast,
+                config::CONTRAST_RANGE,
+            ),
+            "gamma" => set_level(option, value, &mut self.config.gamma, config::GAMMA_RANGE),
+            _ => Err(format!("Unknown option: {}", option)),
+        };
+        match result {
+            Ok(Some(message)) => self.notify(Level::Info, message),
+            Ok(None) => {}
+            Err(e) => self.notify(Level::Error, e),
+        }
+        if !self.config.osd {
+            self.osd_until = None;
+        }
+        if matches!(option, "fit_width" | "zen") {
+            self.keep_anchored(frame, self.page_area_center(), self.page_area_center());
+        }
+        if matches!(option, "invert" | "recolor" | "tint") {
+            self.document_state.color_mode = Some(self.config.color_choice());
+        }
+        if self.color_mode() != mode {
+            self.show_color_mode();
+        }
+    }
+
+    /// Steps a brightness, contrast or gamma option by `step` from `current`.
+    fn adjust_level(&mut self, option: &str, current: f32, step: f32) {
+        let value = format!("{:.2}", current + step);
+        self.set_option(option, &SetValue::Value(value));
+    }
+
+    /// `:set tint=<name>` picks a tint, `notint` removes it, `tint!` moves on to the
+    /// next of `tints` (or to none after the last) and `tint?` names it.
+    fn set_tint(&mut self, value: &SetValue) -> Result<Option<String>, String> {
+        let name = |tint: Option<TintColor>| {
+            tint.map_or("notint".to_string(), |tint| format!("tint={}", tint.name()))
+        };
+        match value {
+            SetValue::Value(value) => self.config.tint = Some(TintColor::try_from(value.clone())?),
+            SetValue::Off => self.config.tint = None,
+            SetValue::Toggle => {
+                let tints = &self.config.tints;
+                let next = match self.config.tint {
+                    None => tints.first(),
+                    Some(tint) => tints
+                        .iter()
+                        .position(|t| *t == tint)
+                        .and_then(|i| tints.get(i + 1)),
+                };
+                self.config.tint = next.copied();
+                return Ok(Some(name(self.config.tint)));
+            }
+            SetValue::Query => return Ok(Some(name(self.config.tint))),
+            SetValue::On => return Err("Usage: set tint=<sepia|paper-cream|#rrggbb>".to_string()),
+        }
+        Ok(None)
+    }
+
+    fn handle_plugin_requests(&mut self) {
+        for request in self.plugins.poll() {
+            match request {
+                Ok(PluginRequest::GotoPage { page }) => {
+                    let target = page.saturating_sub(1);
+                    if target < self.total_pages as usize {
+                        self.jump_to_page(target);
+                    }
+                }
+                Ok(PluginRequest::Open { file, page }) => {
+                    self.open_document(file, page.map(|page| page.saturating_sub(1)));
+                }
+                Ok(PluginRequest::Message { text }) => self.notify(Level::Info, text),
+                Ok(PluginRequest::Register { .. }) => {}
+                Err(e) => self.notify(Level::Error, e),
+            }
+        }
+    }
+
+    /// Offers something to the compiled-in plugins, returning whether one handled it.
+    fn dispatch_plugins(
+        &mut self,
+        f: impl FnMut(&mut dyn extension::Plugin, &mut extension::Context) -> bool,
+    ) -> bool {
+        let (handled, actions) = extension::dispatch(self.script_view(), f);
+        self.plugin_actions.extend(actions);
+        handled
+    }
+
+    fn dispatch_plugin_event(&mut self, event: PluginEvent) {
+        self.dispatch_plugins(|plugin, cx| {
+            plugin.on_event(&event, cx);
+            false
+        });
+    }
+
+    /// Acts on what the user script and compiled-in plugins asked for.
+    fn handle_script_actions(&mut self) {
+        let script_actions = self.scripts.poll().into_iter().map(|action| (action, true));
+        let plugin_actions = mem::take(&mut self.plugin_actions)
+            .into_iter()
+            .map(|action| (Ok(action), false));
+        for (action, from_script) in script_actions.chain(plugin_actions) {
+            match action {
+                Ok(ScriptAction::Command(line))
+                    if from_script
+                        && command::parse(&line).is_ok_and(|command| command.writes_or_runs()) =>
+                {
+                    self.notify(Level::Error, format!("Scripts cannot run :{}", line.trim()));
+                }
+                Ok(ScriptAction::GotoPage(target)) => {
+                    if target < self.total_pages as usize {
+                        self.jump_to_page(target);
+                    }
+                }
+                Ok(ScriptAction::Open(file)) => self.open_document(file, None),
+                Ok(ScriptAction::Command(line)) => self.run_command(&line),
+                Ok(ScriptAction::Message(text)) => self.notify(Level::Info, text),
+                Err(e) => self.notify(Level::Error, e),
+            }
+        }
+    }
+
+    /// Runs the `on_*` config command `hook`, if set, for the shown page.
+    fn run_hook(&mut self, hook: Option<String>) {
+        let Some(command) = hook.filter(|command| !command.trim().is_empty()) else {
+            return;
+        };
+        let label = self
+            .page_labels
+            .get(self.current_page_index)
+            .map_or("", String::as_str);
+        if let Err(e) = external::run_hook(
+            &command,
+            &self.file_name,
+            self.current_page_index + 1,
+            self.total_pages as usize,
+            label,
+        ) {
+            self.notify(Level::Error, e);
+        }
+    }
+
+    fn script_view(&self) -> ScriptView {
+        ScriptView {
+            file: self.file_name.clone(),
+            page: self.current_page_index,
+            pages: self.total_pages as usize,
+        }
+    }
+
+    fn reload_config(&mut self) {
+        let base_config = match ViewerConfig::load(self.config_watcher.path()) {
+            Ok(config) => config,
+            Err(e) => {
+                self.notify(Level::Error, single_line(&e.to_string()));
+                return;
+            }
+        };
+        self.base_config = base_config;
+        if self.apply_document_config() {
+            self.notify(Level::Info, "Config reloaded");
+        }
+    }
+
+    /// Recomputes `config` for the open document, re-rendering if render settings
+    /// changed. Returns false (and reports the error) when the overrides are invalid.
+    fn apply_document_config(&mut self) -> bool {
+        let (config, ok) = self.document_config();
+
+        let settings = RenderSettings::from_config(&config);
+        let settings_changed = settings != RenderSettings::from_config(&self.config);
+        let mode = self.color_mode();
+        self.config = config;
+
+        if settings_changed {
+            let _ = self.render_tx.send(RenderCommand::UpdateSettings(settings));
+            self.page_cache.clear();
+            self.request_render(self.current_page_index);
+        } else if self.color_mode() != mode {
+            self.show_color_mode();
+        }
+        ok
+    }
+
+    /// The config with the open document's overrides, or without them (reporting the
+    /// error) when they are invalid, and its remembered page colors.
+    fn document_config(&mut self) -> (ViewerConfig, bool) {
+        let (mut config, ok) = match self.base_config.for_document(Path::new(&self.file_name)) {
+            Ok(config) => (config, true),
+            Err(e) => {
+                self.notify(Level::Error, single_line(&e));
+                (self.base_config.clone(), false)
+            }
+        };
+        if let Some(choice) = &self.document_state.color_mode {
+            config.apply_color_choice(choice);
+        }
+        (config, ok)
+    }
+
+    /// Switches the window in or out of fullscreen when the document's config asks for it.
+    fn apply_window_mode(&mut self) -> Task<Message> {
+        let Some(id) = self.window_id else {
+            return Task::none();
+        };
+        if self.fullscreen == self.config.fullscreen {
+            return Task::none();
+        }
+        self.fullscreen = self.config.fullscreen;
+        let mode = if self.fullscreen {
+            window::Mode::Fullscreen
+        } else {
+            window::Mode::Windowed
+        };
+        window::set_mode(id, mode)
+    }
+
+    /// Loads and searches the library index on a thread of its own, as it can be large;
+    /// the hits are shown by `poll_library_search`.
+    fn library_find(&mut self, query: &str) {
+        let (tx, rx) = mpsc::channel();
+        let search = query.to_string();
+        thread::spawn(move || {
+            let hits = LibraryIndex::load()
+                .map_err(|e| e.to_string())
+                .map(|index| (!index.documents.is_empty()).then(|| index.search(&search, 50)));
+            let _ = tx.send(hits);
+        });
+        self.library_search = Some((query.to_string(), rx));
+    }
+
+    fn poll_library_search(&mut self) {
+        let Some((query, rx)) = &self.library_search else {
+            return;
+        };
+        let hits = match rx.try_recv() {
+            Ok(hits) => hits,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => Err("Library search failed".to_string()),
+        };
+        let query = query.clone();
+        self.library_search = None;
+        let hits = match hits {
+            Ok(Some(hits)) => hits,
+            Ok(None) => {
+                self.notify(Level::Info, cli::LIBRARY_EMPTY);
+                return;
+            }
+            Err(e) => {
+                self.notify(Level::Error, e);
+                return;
+            }
+        };
+
+        let entries: Vec<OverlayEntry> = hits
+            .into_iter()
+            .map(|hit| OverlayEntry {
+                label: format!(
+                    "{}  p.{}  {}",
+                    hit.path
+                        .file_name()
+                        .map(|n| n.to_string_lossy())
+                        .unwrap_or_default(),
+                    hit.page_index + 1,
+                    hit.snippet
+                ),
+                target: OverlayTarget::Document(hit.path, hit.page_index),
+            })
+            .collect();
+
+        if entries.is_empty() {
+            self.notify(Level::Info, format!("No matches for: {}", query));
+        } else {
+            self.overlay = Some(Overlay::new(format!("Library: {}", query), entries));
+        }
+    }
+
+    /// Puts `text` on the clipboard and says how much was copied.
+    fn copy_to_clipboard(&mut self, text: String) -> Task<Message> {
+        self.notify(
+            Level::Info,
+            format!("Copied {} characters", text.chars().count()),
+        );
+        clipboard::write(text)
+    }
+
+    /// Shows `text` as a toast and records it for `:messages`.
+    fn notify(&mut self, level: Level, text: impl Into<String>) {
+        let text = text.into();
+        self.messages.push(level, text.clone());
+        self.toasts.push(level, text);
+    }
+
+    fn restore_marks(&mut self) {
+        self.marks = marks_from_state(&self.document_state, self.total_pages);
+    }
+
+    /// Runs a line from the control socket: `open file`, `goto N`, `reload`,
+    /// `search text`, `synctex-forward line:column:file.tex`, or else any `:` command.
+    fn control(&mut self, line: &str) -> Result<(), String> {
+        let (verb, argument) = line
+            .split_once(' ')
+            .map_or((line, ""), |(verb, argument)| (verb, argument.trim()));
+        match verb {
+            "goto" => {
+                let page: usize = argument.parse().map_err(|_| "Usage: goto <page>")?;
+                if page == 0 || page > self.total_pages as usize {
+                    return Err(format!("No page {}", page));
+                }
+                self.jump_to_page(page - 1);
+                Ok(())
+            }
+            "reload" => self.reload_document(),
+            "search" if argument.is_empty() => Err("Usage: search <text>".to_string()),
+            "search" => self.search_text(argument),
+            "synctex-forward" => self.synctex_forward(&Forward::parse(argument)?),
+            "open" if argument.is_empty() => Err("Usage: open <file>".to_string()),
+            "open" if !Path::new(argument).is_file() => Err(format!("No file {}", argument)),
+            "open" => {
+                self.open_document(argument.to_string(), None);
+                Ok(())
+            }
+            _ => {
+                command::parse(line)?;
+                self.run_command(line);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reopens the shown document from disk at the same page, e.g. after it was rebuilt.
+    fn reload_document(&mut self) -> Result<(), String> {
+        if self.modified {
+            return Err("Unwritten annotations; write them first".to_string());
+        }
+        self.save_document_state();
+        let file_name = self.file_name.clone();
+        let mut buffer = self
+            .load_buffer(&file_name, Some(self.current_page_index))
+            .ok_or_else(|| format!("Could not reload {}", file_name))?;
+        self.exchange_shown(&mut buffer);
+        Ok(())
+    }
+
+    /// Reloads the shown document when it changed on disk, unless that would lose
+    /// unwritten annotations.
+    fn poll_file_watcher(&mut self) {
+        if !self.config.auto_reload || !self.watcher.as_mut().is_some_and(FileWatcher::poll) {
+            return;
+        }
+        if self.modified {
+            self.notify(
+                Level::Warning,
+                format!(
+                    "{} changed on disk; not reloaded over unwritten annotations",
+                    self.file_name
+                ),
+            );
+        } else if let Err(e) = self.reload_document() {
+            self.notify(Level::Error, e);
+        }
+    }
+
+    /// The reading position as one line of JSON, answering `status` on the control
+    /// socket. `zoom` is screen pixels per point, as pages are fitted to the window or
+    /// its width.
+    fn status(&self) -> String {
+        let zoom = self
+            .page_layouts
+            .get(&self.current_page_index)
+            .map(|layout| {
+                let padding = 2.0 * self.config.padding() as f32;
+                let width = self.render_size.0 as f32 - padding;
+                let height = self.render_size.1 as f32 - padding;
+                let page_height = layout.width / layout.aspect_ratio;
+                let zoom = if self.config.fit_width {
+                    width / layout.width
+                } else {
+                    (width / layout.width).min(height / page_height)
+                };
+                (zoom * self.zoom * 100.0).round() / 100.0
+            });
+        let label = self
+            .page_labels
+            .get(self.current_page_index)
+            .filter(|label| !label.is_empty());
+        serde_json::json!({
+            "file": std::path::absolute(&self.file_name)
+                .map_or(self.file_name.clone(), |path| path.display().to_string()),
+            "page": self.current_page_index + 1,
+            "label": label,
+            "pages": self.total_pages,
+            "zoom": zoom,
+            "search": self.last_search,
+            "modified": self.modified,
+        })
+        .to_string()
+    }
+
+    /// Work still going on in the background, such as "indexing 120/900" while the
+    /// text of the document is read for searching.
+    fn background_progress(&self) -> Option<String> {
+        let indexed = self.search_index.lock().map_or(0, |pages| pages.len());
+        (indexed < self.total_pages as usize)
+            .then(|| format!("indexing {}/{}", indexed, self.total_pages))
+    }
+
+    /// Goes to the next page containing `query`, ignoring case and wrapping around.
+    fn search_text(&mut self, query: &str) -> Result<(), String> {
+        self.last_search = Some(query.to_string());
+        let query = query.to_lowercase();
+        let (found, indexed) = {
+            let pages = self.search_index.lock().map_err(|e| e.to_string())?;
+            if pages.is_empty() {
+                return Err("The text is still being indexed".to_string());
+            }
+            let found = (1..=pages.len())
+                .map(|offset| (self.current_page_index + offset) % pages.len())
+                .find(|&index| pages[index].to_lowercase().contains(&query));
+            (found, pages.len())
+        };
+        match found {
+            Some(page_index) => {
+                self.jump_to_page(page_index);
+                Ok(())
+            }
+            None if indexed < self.total_pages as usize => Err(format!(
+                "Not found: {} (only {} of {} pages indexed so far)",
+                query, indexed, self.total_pages
+            )),
+            None => Err(format!("Not found: {}", query)),
+        }
+    }
+
+    /// Goes to the lines a TeX source location produced and highlights them briefly.
+    fn synctex_forward(&mut self, forward: &Forward) -> Result<(), String> {
+        let (page, rects) = synctex::find(Path::new(&self.file_name), forward)?;
+        self.show_synctex(page, rects);
+        Ok(())
+    }
+
+    fn show_synctex(&mut self, page: usize, rects: Vec<dbus::Rect>) {
+        self.jump_to_page(page);
+        self.highlights = Some((page, rects));
+        self.highlights_until = Some(Instant::now() + SYNCTEX_HIGHLIGHT);
+    }
+
+    /// Saves the documents' state and has the window closed.
+    fn quit(&mut self) {
+        self.run_hook(self.config.on_close.clone());
+        self.save_on_exit();
+        self.closing = true;
+    }
+
+    /// Saves the document state and window geometry as the window closes. Failures are
+    /// logged and, as the window is going away, printed on one line to stderr too.
+    fn save_on_exit(&mut self) {
+        let mut failures = Vec::new();
+        if let Err(e) = self.write_document_state() {
+            failures.push(e);
+        }
+        if let Some(geometry) = self.window_geometry()
+            && let Err(e) = StateStore::set_window(geometry)
+        {
+            failures.push(format!("Could not save window geometry: {}", e));
+        }
+        if failures.is_empty() {
+            return;
+        }
+        eprintln!("{}", failures.join("; "));
+        for message in failures {
+            self.notify(Level::Error, message);
+        }
+    }
+
+    /// `None` in fullscreen, which is not a geometry worth restoring.
+    fn window_geometry(&self) -> Option<WindowGeometry> {
+        (!self.fullscreen).then(|| WindowGeometry {
+            width: self.window_size.width,
+            height: self.window_size.height,
+            x: self.window_position.map(|p| p.x),
+            y: self.window_position.map(|p| p.y),
+        })
+    }
+
+    /// Writes the open buffers, their pages, the split and the window geometry to
+    /// `path`, or `session.toml` in the working directory.
+    fn make_session(&mut self, path: Option<String>) {
+        let path = PathBuf::from(path.unwrap_or_else(|| session::DEFAULT_FILE.to_string()));
+        let absolute = |file_name: &str| {
+            std::path::absolute(file_name).unwrap_or_else(|_| PathBuf::from(file_name))
+        };
+        let buffers = self
+            .buffers
+            .iter()
+            .map(|file_name| {
+                let page_index = if *file_name == self.file_name {
+                    Some(self.current_page_index)
+                } else {
+                    self.parked
+                        .get(file_name)
+                        .map(|buffer| buffer.current_page_index)
+                };
+                SessionBuffer {
+                    file: absolute(file_name),
+                    page: page_index.map(|index| index + 1),
+                }
+            })
+            .collect();
+        let split = self.split.as_ref().map(|split| SessionSplit {
+            file: absolute(&split.pane.file_name),
+            page: split.pane.current_page_index + 1,
+            left: split.left,
+        });
+        let session = Session {
+            current: self.current_buffer,
+            buffers,
+            split,
+            window: self.window_geometry(),
+        };
+        match session.save(&path) {
+            Ok(()) => self.notify(Level::Info, format!("Wrote session {}", path.display())),
+            Err(e) => self.notify(
+                Level::Error,
+                format!("Could not write session {}: {}", path.display(), e),
+            ),
+        }
+    }
+
+    /// Reopens what `session` lists after the first buffer, which `App::new` opened.
+    fn restore_session(&mut self, session: &Session) {
+        for (file_name, saved) in self.buffers.clone().iter().zip(&session.buffers).skip(1) {
+            if let Some(page) = saved.page
+                && let Some(buffer) = self.load_buffer(file_name, Some(page.saturating_sub(1)))
+            {
+                self.park(buffer);
+            }
+        }
+        self.switch_buffer(session.current);
+
+        if let Some(saved) = &session.split {
+            self.vsplit(Some(saved.file.to_string_lossy().into_owned()));
+            self.go_to_page(saved.page.saturating_sub(1));
+            self.focus_other_pane();
+            if let Some(split) = &mut self.split {
+                split.left = saved.left;
+            }
+        }
+    }
+
+    /// Records the current page and marks of the open document in the state store.
+    fn save_document_state(&mut self) {
+        if let Err(e) = self.write_document_state() {
+            self.notify(Level::Error, e);
+        }
+    }
+
+    fn write_document_state(&mut self) -> Result<(), String> {
+        let Some(key) = &self.state_key else {
+            return Ok(());
+        };
+        self.document_state.path =
+            std::path::absolute(&self.file_name).unwrap_or_else(|_| PathBuf::from(&self.file_name));
+        self.document_state.last_page = self.current_page_index;
+        self.document_state.marks = self.marks.iter().collect();
+
+        StateStore::update(key, self.document_state.clone())
+            .map_err(|e| format!("Could not save document state: {}", e))
+    }
+
+    /// Shows `file_name` in a new buffer, or its existing one, starting at `page_index`
+    /// or else where it was last left.
+    fn open_document(&mut self, file_name: String, page_index: Option<usize>) {
+        if let Some(index) = self.buffers.iter().position(|buffer| *buffer == file_name) {
+            self.switch_buffer(index);
+            if let Some(page_index) = page_index
+                && index == self.current_buffer
+            {
+                self.jump_to_page(page_index);
+            }
+            return;
+        }
+        if let Some(buffer) = self.load_buffer(&file_name, page_index) {
+            self.buffers.push(file_name);
+            self.show_buffer(buffer, self.buffers.len() - 1);
+        }
+    }
+
+    /// Shows buffer `index`, loading it if it has not been shown yet.
+    fn switch_buffer(&mut self, index: usize) {
+        if index == self.current_buffer {
+            return;
+        }
+        let Some(file_name) = self.buffers.get(index).cloned() else {
+            return;
+        };
+        let buffer = match self.parked.remove(&file_name) {
+            Some(buffer) => Some(buffer),
+            None => self.load_buffer(&file_name, None),
+        };
+        if let Some(buffer) = buffer {
+            self.show_buffer(buffer, index);
+        }
+    }
+
+    /// Moves `offset` buffers along the list, wrapping around at either end.
+    fn cycle_buffer(&mut self, offset: isize) {
+        let count = self.buffers.len() as isize;
+        if count < 2 {
+            self.notify(Level::Info, "Only one buffer is open");
+            return;
+        }
+        let index = (self.current_buffer as isize + offset).rem_euclid(count);
+        self.switch_buffer(index as usize);
+    }
+
+    fn load_buffer(&mut self, file_name: &str, page_index: Option<usize>) -> Option<Buffer> {
+        let (state_key, document_state) = match load_document_state(file_name) {
+            Ok((key, state)) => (Some(key), state),
+            Err(e) => {
+                self.notify(
+                    Level::Warning,
+                    format!("Could not load document state: {}", e),
+                );
+                (None, DocumentState::default())
+            }
+        };
+        let page_index = page_index.unwrap_or(document_state.last_page);
+
+        let settings = RenderSettings::from_config(&self.config);
+        let loaded = match load_document(
+            file_name,
+            page_index,
+            self.render_size,
+            settings.clone(),
+            self.color_mode(),
+        ) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                self.notify(Level::Error, e);
+                return None;
+            }
+        };
+        let watcher = loaded
+            .watcher
+            .map_err(|e| self.messages.push(Level::Warning, e))
+            .ok();
+        Some(Buffer {
+            file_name: file_name.to_string(),
+            render_settings: settings,
+            rendered_at: self.render_size.0,
+            current_image: None,
+            current_page_index: loaded.start_page,
+            scroll: 0.0,
+            pan: 0.0,
+            total_pages: loaded.total_pages,
+            render_tx: loaded.render_tx,
+            render_rx: loaded.render_rx,
+            search_index: loaded.search_index,
+            banner: None,
+            page_cache: HashMap::new(),
+            page_layouts: HashMap::new(),
+            annotations: Vec::new(),
+            annotation_cursor: None,
+            pending_fields: Vec::new(),
+            pending_field_cursor: None,
+            modified: false,
+            marks: marks_from_state(&document_state, loaded.total_pages),
+            jump_list: JumpList::default(),
+            back_stack: BackStack::default(),
+            state_key,
+            document_state,
+            toc: TocPanel::new(loaded.outline),
+            toc_open: false,
+            page_labels: loaded.page_labels,
+            signatures: Vec::new(),
+            unsigned_fields: Vec::new(),
+            attachments: loaded.attachments,
+            info: loaded.info,
+            watcher,
+        })
+    }
+
+    /// Puts `buffer` on screen as buffer `index`, keeping the shown document loaded so
+    /// switching back is instant.
+    fn show_buffer(&mut self, mut buffer: Buffer, index: usize) {
+        self.exchange_shown(&mut buffer);
+        self.park(buffer);
+        self.current_buffer = index;
+    }
+
+    /// Keeps `buffer` loaded for switching back to it. Another view of the same document
+    /// may already be parked; the one with unwritten annotations is kept.
+    fn park(&mut self, buffer: Buffer) {
+        let file_name = buffer.file_name.clone();
+        let discarded = match self.parked.remove(&file_name) {
+            Some(parked) if parked.modified && !buffer.modified => {
+                self.parked.insert(file_name.clone(), parked);
+                buffer
+            }
+            Some(parked) => {
+                self.parked.insert(file_name.clone(), buffer);
+                parked
+            }
+            None => {
+                self.parked.insert(file_name, buffer);
+                return;
+            }
+        };
+        if discarded.modified {
+            self.notify(
+                Level::Warning,
+                format!(
+                    "Discarded unwritten annotations in another view of {}",
+                    file_name
+                ),
+            );
+        }
+    }
+
+    /// Shows `buffer` in the focused viewport and leaves the shown document in `buffer`.
+    fn exchange_shown(&mut self, buffer: &mut Buffer) {
+        self.save_document_state();
+        // Modes belong to the page on screen.
+        if self.form.take().is_some() {
+            let _ = self.render_tx.send(RenderCommand::HighlightFields(false));
+            self.page_cache.clear();
+        }
+        self.ink = None;
+        self.hints = None;
+        self.selection = None;
+        self.overlay = None;
+        self.resize_render_in_flight = false;
+
+        let settings = buffer.render_settings.clone();
+        let rendered_at = buffer.rendered_at;
+        buffer.render_settings = RenderSettings::from_config(&self.config);
+        buffer.rendered_at = self.render_size.0;
+        self.swap_buffer(buffer);
+
+        self.plugins.broadcast(PluginEvent::DocumentOpened {
+            file: &self.file_name,
+            total_pages: self.total_pages as usize,
+        });
+        self.scripts.document_opened(self.script_view());
+        self.dispatch_plugin_event(PluginEvent::DocumentOpened {
+            file: &self.file_name.clone(),
+            total_pages: self.total_pages as usize,
+        });
+        // The config may have been reloaded or the window resized while it was parked.
+        self.config = self.document_config().0;
+        let wanted = RenderSettings::from_config(&self.config);
+        if wanted != settings {
+            let _ = self.render_tx.send(RenderCommand::UpdateSettings(wanted));
+            self.page_cache.clear();
+        } else if rendered_at != self.render_size.0 {
+            self.page_cache.clear();
+        }
+        match self
+            .page_cache
+            .get(&(self.current_page_index, self.color_mode()))
+        {
+            Some(cached) => self.current_image = Some(cached.clone()),
+            None => self.request_render(self.current_page_index),
+        }
+        self.run_hook(self.config.on_open.clone());
+    }
+
+    /// Exchanges the shown document's state with `buffer`'s.
+    fn swap_buffer(&mut self, buffer: &mut Buffer) {
+        mem::swap(&mut self.file_name, &mut buffer.file_name);
+        mem::swap(&mut self.current_image, &mut buffer.current_image);
+        mem::swap(&mut self.current_page_index, &mut buffer.current_page_index);
+        mem::swap(&mut self.total_pages, &mut buffer.total_pages);
+        mem::swap(&mut self.render_tx, &mut buffer.render_tx);
+        mem::swap(&mut self.render_rx, &mut buffer.render_rx);
+        mem::swap(&mut self.search_index, &mut buffer.search_index);
+        mem::swap(&mut self.banner, &mut buffer.banner);
+        mem::swap(&mut self.scroll, &mut buffer.scroll);
+        mem::swap(&mut self.pan, &mut buffer.pan);
+        self.scroll_animation = None;
+        self.transition = None;
+        mem::swap(&mut self.page_cache, &mut buffer.page_cache);
+        mem::swap(&mut self.page_layouts, &mut buffer.page_layouts);
+        mem::swap(&mut self.annotations, &mut buffer.annotations);
+        mem::swap(&mut self.annotation_cursor, &mut buffer.annotation_cursor);
+        mem::swap(&mut self.pending_fields, &mut buffer.pending_fields);
+        mem::swap(
+            &mut self.pending_field_cursor,
+            &mut buffer.pending_field_cursor,
+        );
+        mem::swap(&mut self.modified, &mut buffer.modified);
+        mem::swap(&mut self.marks, &mut buffer.marks);
+        mem::swap(&mut self.jump_list, &mut buffer.jump_list);
+        mem::swap(&mut self.back_stack, &mut buffer.back_stack);
+        mem::swap(&mut self.state_key, &mut buffer.state_key);
+        mem::swap(&mut self.document_state, &mut buffer.document_state);
+        mem::swap(&mut self.toc, &mut buffer.toc);
+        mem::swap(&mut self.toc_open, &mut buffer.toc_open);
+        mem::swap(&mut self.page_labels, &mut buffer.page_labels);
+        mem::swap(&mut self.signatures, &mut buffer.signatures);
+        mem::swap(&mut self.unsigned_fields, &mut buffer.unsigned_fields);
+        mem::swap(&mut self.attachments, &mut buffer.attachments);
+        mem::swap(&mut self.info, &mut buffer.info);
+        mem::swap(&mut self.watcher, &mut buffer.watcher);
+    }
+
+    /// Any open document has annotations that were not written.
+    fn has_unwritten_buffers(&self) -> bool {
+        self.modified
+            || self.parked.values().any(|buffer| buffer.modified)
+            || self.split.as_ref().is_some_and(|split| split.pane.modified)
+    }
+
+    /// Splits the view in two, showing `file_name` (or else the current document at the
+    /// current page) in a new viewport on the right, which takes the focus.
+    fn vsplit(&mut self, file_name: Option<String>) {
+        if self.split.is_some() {
+            self.notify(Level::Info, "The view is already split");
+            return;
+        }
+        let page_index = file_name.is_none().then_some(self.current_page_index);
+        let file_name = file_name.unwrap_or_else(|| self.file_name.clone());
+
+        let full_size = self.render_size;
+        self.render_size = self.pane_size(true);
+        let Some(mut pane) = self.load_buffer(&file_name, page_index) else {
+            self.render_size = full_size;
+            return;
+        };
+        // Narrower now; the old bitmap is scaled until the new one arrives.
+        self.page_cache.clear();
+        self.resize_render_in_flight = true;
+        self.request_render(self.current_page_index);
+
+        if !self.buffers.contains(&file_name) {
+            self.buffers.push(file_name);
+        }
+        self.exchange_shown(&mut pane);
+        self.split = Some(Split { pane, left: true });
+        self.current_buffer = self.buffer_index();
+    }
+
+    /// Moves the focus to the other viewport.
+    fn focus_other_pane(&mut self) {
+        let Some(mut split) = self.split.take() else {
+            self.notify(Level::Info, "The view is not split");
+            return;
+        };
+        self.exchange_shown(&mut split.pane);
+        split.left = !split.left;
+        self.split = Some(split);
+        self.current_buffer = self.buffer_index();
+    }
+
+    /// Closes the other viewport (`only`) or the focused one, keeping its document as a
+    /// buffer unless the remaining viewport shows it too.
+    fn close_pane(&mut self, only: bool) {
+        if self.split.is_none() {
+            self.notify(Level::Info, "The view is not split");
+            return;
+        }
+        if !only {
+            self.focus_other_pane();
+        }
+        let Some(split) = self.split.take() else {
+            return;
+        };
+        if split.pane.file_name != self.file_name {
+            self.park(split.pane);
+        } else if split.pane.modified {
+            self.notify(
+                Level::Warning,
+                "Discarded unwritten annotations in the closed view",
+            );
+        }
+        self.resize_pending_since = Some(Instant::now());
+    }
+
+    /// The index in `buffers` of the document in the focused viewport.
+    fn buffer_index(&self) -> usize {
+        self.buffers
+            .iter()
+            .position(|file_name| *file_name == self.file_name)
+            .unwrap_or(self.current_buffer)
+    }
+
+    /// The size pages are rendered at: the window's, or half its width when `split`,
+    /// scaled by the zoom.
+    fn pane_size(&self, split: bool) -> (u16, u16) {
+        let width = self.window_size.width as u16;
+        let width = if split { width / 2 } else { width };
+        (
+            (width as f32 * self.zoom) as u16,
+            self.window_size.height as u16,
+        )
+    }
+
+    /// Takes in what the unfocused viewport's render thread sent: its page once it is
+    /// rendered at the current size.
+    fn poll_split(&mut self) {
+        let Some(split) = &mut self.split else {
+            return;
+        };
+        let mode = ColorMode::from_config(&self.config);
+        let pane = &mut split.pane;
+        let mut errors = Vec::new();
+        if let Ok(rx) = pane.render_rx.lock() {
+            while let Ok(event) = rx.try_recv() {
+                match event {
+                    RenderEvent::Page(result) => {
+                        pane.page_layouts.insert(result.page_index, result.layout);
+                        if result.target_width != self.render_size.0 {
+                            continue;
+                        }
+                        let handle =
+                            image::Handle::from_rgba(result.width, result.height, result.pixels);
+                        if result.page_index == pane.current_page_index && result.color_mode == mode
+                        {
+                            pane.current_image = Some(handle.clone());
+                        }
+                        pane.page_cache
+                            .insert((result.page_index, result.color_mode), handle);
+                    }
+                    RenderEvent::Annotations(annotations) => pane.annotations = annotations,
+                    RenderEvent::PendingFields(fields) => pane.pending_fields = fields,
+                    RenderEvent::Error(e) => errors.push(e),
+                    _ => {}
+                }
+            }
+        }
+        for e in errors {
+            self.notify(Level::Error, e);
+        }
+    }
+
+    /// A link or outline jump: recorded in both the jump list and the back stack.
+    fn follow_to_page(&mut self, target: usize) {
+        if target != self.current_page_index {
+            self.back_stack.push(self.current_page_index);
+            self.jump_to_page(target);
+        }
+    }
+
+    /// Moves to `target` and records the origin in the jump list.
+    fn jump_to_page(&mut self, target: usize) {
+        if target != self.current_page_index {
+            self.jump_list.push(self.current_page_index);
+            self.go_to_page(target);
+        }
+    }
+
+    fn go_to_page(&mut self, target: usize) {
+        if target == self.current_page_index || target >= self.total_pages as usize {
+            return;
+        }
+
+        let now = Instant::now();
+        let rapid = self
+            .last_page_change
+            .is_some_and(|last| now.duration_since(last) < OSD_REPEAT_WINDOW);
+        let big_jump = target.abs_diff(self.current_page_index) > self.config.scroll_amount;
+        if self.config.osd && !self.config.eink && (rapid || big_jump) {
+            self.osd_until = Some(now + OSD_DURATION);
+        }
+        if !self.config.statusbar {
+            self.page_toast_until = Some(now + OSD_DURATION);
+        }
+        self.last_page_change = Some(now);
+
+        self.transition = match &self.current_image {
+            Some(previous) if self.config.transitions && !self.config.eink => Some(Transition {
+                previous: previous.clone(),
+                fit: self.page_fit(),
+                forward: target > self.current_page_index,
+                started: now,
+                progress: 0.0,
+            }),
+            _ => None,
+        };
+        self.current_page_index = target;
+        self.scroll = 0.0;
+        self.scroll_animation = None;
+        if let Some(ink) = &mut self.ink {
+            ink.finished.clear();
+        }
+        if let Some(form) = &mut self.form {
+            form.unfocus();
+        }
+        self.plugins
+            .broadcast(PluginEvent::PageChanged { page: target + 1 });
+
+        let mode = self.color_mode();
+        if let Some(cached) = self.page_cache.get(&(self.current_page_index, mode)) {
+            self.current_image = Some(cached.clone());
+        } else {
+            self.request_render(self.current_page_index);
+        }
+
+        if self.current_page_index > 0
+            && !self
+                .page_cache
+                .contains_key(&(self.current_page_index - 1, mode))
+        {
+            self.request_render(self.current_page_index - 1);
+        }
+        if self.current_page_index < (self.total_pages as usize - 1)
+            && !self
+                .page_cache
+                .contains_key(&(self.current_page_index + 1, mode))
+        {
+            self.request_render(self.current_page_index + 1);
+        }
+
+        self.scripts.page_changed(self.script_view());
+        self.run_hook(self.config.on_page_change.clone());
+        self.dispatch_plugin_event(PluginEvent::PageChanged { page: target + 1 });
+    }
+
+    fn request_render(&self, page_index: usize) {
+        let (width, height) = self.render_size;
+        let _ = self.render_tx.send(RenderCommand::RenderPage(
+            page_index,
+            width,
+            height,
+            self.color_mode(),
+        ));
+    }
+
+    /// Waiting on a resize or a timed highlight, which need prompt ticks.
+    fn is_settling(&self) -> bool {
+        self.resize_pending_since.is_some()
+            || self.resize_render_in_flight
+            || self.current_image.is_none()
+            || self.highlights_until.is_some()
+    }
+
+    /// How the current page is laid out in the page area.
+    fn page_fit(&self) -> ui::PageFit {
+        let scroll = self
+            .scroll_animation
+            .as_ref()
+            .map_or(self.scroll, |animation| animation.shown);
+        page_fit(
+            &self.config,
+            self.page_layouts.get(&self.current_page_index),
+            self.zoom,
+            self.pan,
+            scroll,
+        )
+    }
+
+    /// Scrolls for the mouse wheel, turning the page once it has been scrolled a notch
+    /// past an edge; with Ctrl held it zooms around the cursor instead.
+    fn handle_wheel(&mut self, delta: mouse::ScrollDelta) {
+        let line = self.config.scroll_step * self.page_area_size.height;
+        let (x, y, animate) = match delta {
+            mouse::ScrollDelta::Lines { x, y } => (x * line, y * line, true),
+            mouse::ScrollDelta::Pixels { x, y } => (x, y, false),
+        };
+        if self.modifiers.control() {
+            self.zoom_at(ZOOM_STEP.powf(y / line), self.cursor);
+            return;
+        }
+
+        let range = ui::scroll_range(self.page_area_size, self.page_fit());
+        self.pan = (self.pan.min(range.x) - x).clamp(0.0, range.x);
+        self.scroll = self.scroll.min(range.y);
+        let target = (self.scroll - y).clamp(0.0, range.y);
+        if target != self.scroll {
+            self.wheel_overscroll = 0.0;
+            if animate {
+                self.scroll_to(target);
+            } else {
+                self.scroll = target;
+                self.scroll_animation = None;
+            }
+        } else if y != 0.0 {
+            self.wheel_overscroll -= y;
+            if self.wheel_overscroll.abs() >= line {
+                self.scroll_or_turn(self.wheel_overscroll, 1);
+                self.wheel_overscroll = 0.0;
+            }
+        }
+    }
+
+    /// Touch screen gestures: one finger drags the page like the mouse does, or swipes
+    /// sideways to turn it when it is no wider than the window; two fingers pinch to
+    /// zoom around the point between them and move together to pan.
+    fn handle_touch(&mut self, event: touch::Event) {
+        match event {
+            touch::Event::FingerPressed { id, position }
+            | touch::Event::FingerMoved { id, position } => {
+                if matches!(event, touch::Event::FingerPressed { .. }) {
+                    self.swipe = self.touches.is_empty().then(|| Swipe {
+                        finger: id,
+                        start: position,
+                        at: Instant::now(),
+                    });
+                }
+                // The page area has just seen this finger at `cursor`.
+                let origin = position - self.cursor;
+                self.touches.insert(id, position);
+                let fingers: Vec<Point> = self.touches.values().copied().collect();
+                let [a, b] = fingers[..] else {
+                    return;
+                };
+                self.drag = None;
+                self.swipe = None;
+                let distance = a.distance(b);
+                let middle = Point::new((a.x + b.x) / 2.0, (a.y + b.y) / 2.0) - origin;
+                if let Some((previous, previous_middle)) = self.pinch
+                    && previous > 0.0
+                {
+                    self.zoom_at(distance / previous, middle);
+                    let range = ui::scroll_range(self.page_area_size, self.page_fit());
+                    let moved = middle - previous_middle;
+                    self.pan = (self.pan.min(range.x) - moved.x).clamp(0.0, range.x);
+                    self.scroll = (self.scroll.min(range.y) - moved.y).clamp(0.0, range.y);
+                    self.scroll_animation = None;
+                }
+                self.pinch = Some((distance, middle));
+            }
+            touch::Event::FingerLifted { id, position }
+            | touch::Event::FingerLost { id, position } => {
+                self.touches.remove(&id);
+                self.pinch = None;
+                let Some(swipe) = self.swipe.take() else {
+                    return;
+                };
+                let moved = position - swipe.start;
+                let range = ui::scroll_range(self.page_area_size, self.page_fit());
+                if swipe.finger == id
+                    && matches!(event, touch::Event::FingerLifted { .. })
+                    && swipe.at.elapsed() < SWIPE_TIME
+                    && moved.x.abs() > SWIPE_DISTANCE
+                    && moved.x.abs() > 2.0 * moved.y.abs()
+                    && range.x == 0.0
+                {
+                    let last_page = (self.total_pages as usize).saturating_sub(1);
+                    let target = if moved.x < 0.0 {
+                        (self.current_page_index + self.config.scroll_amount).min(last_page)
+                    } else {
+                        self.current_page_index
+                            .saturating_sub(self.config.scroll_amount)
+                    };
+                    self.go_to_page(target);
+                }
+            }
+        }
+    }
+
+    /// Moves a grabbed page along with the pointer.
+    fn drag_page(&mut self) {
+        let Some(drag) = &self.drag else {
+            return;
+        };
+        let range = ui::scroll_range(self.page_area_size, self.page_fit());
+        self.pan = (drag.pan - (self.cursor.x - drag.start.x)).clamp(0.0, range.x);
+        self.scroll = (drag.scroll - (self.cursor.y - drag.start.y)).clamp(0.0, range.y);
+    }
+
+    /// Zooms by `factor`, keeping the point of the page under `cursor` in place.
+    fn zoom_at(&mut self, factor: f32, cursor: Point) {
+        let zoom = (self.zoom * factor).clamp(*ZOOM_RANGE.start(), *ZOOM_RANGE.end());
+        if zoom == self.zoom {
+            return;
+        }
+        let before = ui::page_frame(self.page_area_size, self.page_fit());
+        self.zoom = zoom;
+        self.keep_anchored(before, cursor, cursor);
+        // Rendered again at the new scale once the wheel settles.
+        self.resize_pending_since = Some(Instant::now());
+    }
+
+    /// Once the page has been scaled or the page area resized, pans and scrolls so that
+    /// the point of the page that was at `from` when it was framed by `before` is at
+    /// `to`, rather than the view jumping elsewhere on the page.
+    fn keep_anchored(&mut self, before: Rectangle, from: Point, to: Point) {
+        if !self.page_layouts.contains_key(&self.current_page_index) {
+            return;
+        }
+        let anchor_x = ((from.x - before.x) / before.width).clamp(0.0, 1.0);
+        let anchor_y = ((from.y - before.y) / before.height).clamp(0.0, 1.0);
+        let fit = ui::PageFit {
+            pan: 0.0,
+            scroll: 0.0,
+            ..self.page_fit()
+        };
+        let page = ui::page_frame(self.page_area_size, fit);
+        let range = ui::scroll_range(self.page_area_size, fit);
+        self.pan = (page.x + anchor_x * page.width - to.x).clamp(0.0, range.x);
+        self.scroll = (page.y + anchor_y * page.height - to.y).clamp(0.0, range.y);
+        self.scroll_animation = None;
+    }
+
+    /// The middle of the page area, which stays put when the page is scaled without
+    /// the mouse.
+    fn page_area_center(&self) -> Point {
+        Point::new(
+            self.page_area_size.width / 2.0,
+            self.page_area_size.height / 2.0,
+        )
+    }
+
+    /// Scrolls the current page `distance` pixels down, or up when negative, or turns
+    /// `pages` pages once already at its bottom or top edge.
+    fn scroll_or_turn(&mut self, distance: f32, pages: usize) {
+        let range = self.scroll_range();
+        self.scroll = self.scroll.min(range);
+        let page = self.current_page_index;
+        if distance > 0.0 {
+            if self.scroll < range {
+                self.scroll_to((self.scroll + distance).min(range));
+            } else {
+                let last_page = (self.total_pages as usize).saturating_sub(1);
+                self.go_to_page((page + pages).min(last_page));
+            }
+        } else if self.scroll > 0.0 {
+            self.scroll_to((self.scroll + distance).max(0.0));
+        } else {
+            self.go_to_page(page.saturating_sub(pages));
+            if self.current_page_index != page {
+                // Clamped once the page is laid out, leaving it at its bottom.
+                self.scroll = f32::MAX;
+            }
+        }
+    }
+
+    /// Scrolls the current page to `scroll`, gliding there when `smooth_scroll` is on.
+    fn scroll_to(&mut self, scroll: f32) {
+        let from = self.page_fit().scroll.min(self.scroll_range());
+        self.scroll = scroll;
+        self.scroll_animation =
+            (self.config.smooth_scroll && !self.config.eink).then(|| ScrollAnimation {
+                from,
+                started: Instant::now(),
+                shown: from,
+            });
+    }
+
+    /// Moves smooth scrolls and page turns along for the frame drawn at `now`, by the
+    /// time passed so they take as long however often frames come.
+    fn animate(&mut self, now: Instant) {
+        if let Some(animation) = &mut self.scroll_animation {
+            let duration = Duration::from_millis(self.config.smooth_scroll_duration);
+            match progress(animation.started, now, duration) {
+                Some(t) => {
+                    animation.shown = animation.from + (self.scroll - animation.from) * ease_out(t);
+                }
+                None => self.scroll_animation = None,
+            }
+        }
+        if let Some(transition) = &mut self.transition {
+            let duration = Duration::from_millis(self.config.transition_duration);
+            match progress(transition.started, now, duration) {
+                Some(t) => transition.progress = t,
+                None => self.transition = None,
+            }
+        }
+    }
+
+    /// How far the current page can scroll down in the page area.
+    fn scroll_range(&self) -> f32 {
+        ui::scroll_range(self.page_area_size, self.page_fit()).y
+    }
+
+    fn color_mode(&self) -> ColorMode {
+        ColorMode::from_config(&self.config)
+    }
+
+    /// Drops page `page_index` from the cache in every color mode, e.g. once it has
+    /// been annotated.
+    fn forget_rendered(&mut self, page_index: usize) {
+        self.page_cache.retain(|&(page, _), _| page != page_index);
+    }
+
+    /// Shows both viewports in the current color mode, from the cache where it can.
+    fn show_color_mode(&mut self) {
+        let mode = self.color_mode();
+        match self.page_cache.get(&(self.current_page_index, mode)) {
+            Some(cached) => self.current_image = Some(cached.clone()),
+            None => self.request_render(self.current_page_index),
+        }
+        let (width, height) = self.render_size;
+        if let Some(split) = &mut self.split {
+            let pane = &mut split.pane;
+            match pane.page_cache.get(&(pane.current_page_index, mode)) {
+                Some(cached) => pane.current_image = Some(cached.clone()),
+                None => {
+                    let _ = pane.render_tx.send(RenderCommand::RenderPage(
+                        pane.current_page_index,
+                        width,
+                        height,
+                        mode,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Re-renders the current page once the window size has been stable for
+    /// `RESIZE_DEBOUNCE`, keeping at most one resize render in flight. Until then the
+    /// existing bitmap is scaled to fit.
+    fn rerender_after_resize(&mut self) {
+        let Some(since) = self.resize_pending_since else {
+            return;
+        };
+        if since.elapsed() < RESIZE_DEBOUNCE || self.resize_render_in_flight {
+            return;
+        }
+        self.resize_pending_since = None;
+
+        let size = self.pane_size(self.split.is_some());
+        if size.0 == self.render_size.0 {
+            self.render_size = size;
+            return;
+        }
+
+        self.render_size = size;
+        self.page_cache.clear();
+        self.resize_render_in_flight = true;
+        self.request_render(self.current_page_index);
+        if let Some(split) = &mut self.split {
+            let pane = &mut split.pane;
+            pane.page_cache.clear();
+            pane.rendered_at = size.0;
+            let _ = pane.render_tx.send(RenderCommand::RenderPage(
+                pane.current_page_index,
+                size.0,
+                size.1,
+                ColorMode::from_config(&self.config),
+            ));
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let zen = self.config.zen;
+        let zen_theme;
+        let theme = if zen {
+            zen_theme = theme::Theme {
+                background: theme::HexColor(if self.config.zen_dim {
+                    ZEN_DIMMED_BACKGROUND
+                } else {
+                    ZEN_BACKGROUND
+                }),
+                ..self.config.theme.clone()
+            };
+            &zen_theme
+        } else {
+            &self.config.theme
+        };
+        let fit = self.page_fit();
+        let page_area = ui::page_area(self.current_image.as_ref(), fit, theme);
+        let page_area = match &self.transition {
+            Some(transition) => {
+                let eased = ease_out(transition.progress);
+                let (opacity, offset) = match self.config.transition_style.as_str() {
+                    "slide" if transition.forward => (1.0, -eased),
+                    "slide" => (1.0, eased),
+                    _ => (1.0 - eased, 0.0),
+                };
+                let previous =
+                    ui::page_transition(&transition.previous, transition.fit, opacity, offset);
+                stack![page_area, previous].into()
+            }
+            None => page_area,
+        };
+
+        let page_area = if let Some(overlay) = &self.overlay {
+            stack![page_area, ui::overlay_panel(overlay, theme)].into()
+        } else if let Some(hints) = &self.hints
+            && self.page_layouts.contains_key(&self.current_page_index)
+        {
+            let hints = ui::link_hints(hints, fit, theme);
+            stack![page_area, hints].into()
+        } else if let Some(selection) = &self.selection
+            && let Some(layout) = self.page_layouts.get(&self.current_page_index)
+        {
+            let rects = selection.line_rects(&layout.words);
+            let shade = ui::selection(rects, fit, theme.selection());
+            stack![page_area, shade].into()
+        } else if let Some(field) = self.focused_field()
+            && self.page_layouts.contains_key(&self.current_page_index)
+        {
+            let focus = ui::selection(vec![field.bounds], fit, theme.selection());
+            stack![page_area, focus].into()
+        } else if let Some(ink) = &self.ink
+            && let Some(layout) = self.page_layouts.get(&self.current_page_index)
+        {
+            let layer = ui::ink_layer(
+                ink.strokes().cloned().collect(),
+                fit,
+                layout.width,
+                self.config.ink_color.0,
+                self.config.ink_width,
+            );
+            stack![page_area, layer].into()
+        } else if let Some((page_index, rects)) = &self.highlights
+            && *page_index == self.current_page_index
+            && self
+                .highlights_until
+                .is_none_or(|until| Instant::now() < until)
+            && let Some(layout) = self.page_layouts.get(page_index)
+        {
+            let width = layout.width as f64;
+            let height = width / layout.aspect_ratio as f64;
+            let rects = rects
+                .iter()
+                .map(|&(x1, y1, x2, y2)| Rectangle {
+                    x: (x1 / width) as f32,
+                    y: (y1 / height) as f32,
+                    width: ((x2 - x1) / width) as f32,
+                    height: ((y2 - y1) / height) as f32,
+                })
+                .collect();
+            let shade = ui::selection(rects, fit, theme.search());
+            stack![page_area, shade].into()
+        } else if !zen && self.osd_until.is_some_and(|until| Instant::now() < until) {
+            let label = match self.page_labels.get(self.current_page_index) {
+                Some(label) if !label.is_empty() => label.clone(),
+                _ => format!("{} / {}", self.current_page_index + 1, self.total_pages),
+            };
+            stack![page_area, ui::osd(label, theme)].into()
+        } else {
+            page_area
+        };
+        let overlays = if zen {
+            Vec::new()
+        } else {
+            extension::overlays(self.script_view())
+        };
+        let page_area = overlays.into_iter().fold(page_area, |page_area, overlay| {
+            stack![page_area, overlay.map(|()| Message::Tick)].into()
+        });
+        let page_area = if self.config.scrollbar && !zen {
+            let (top, extent) = ui::document_position(
+                self.current_page_index,
+                self.total_pages,
+                self.page_area_size,
+                fit,
+            );
+            stack![page_area, ui::scrollbar(top, extent, theme)].into()
+        } else {
+            page_area
+        };
+        let range = ui::scroll_range(self.page_area_size, fit);
+        let grab = if self.drag.is_some() {
+            mouse::Interaction::Grabbing
+        } else if range != iced::Vector::ZERO {
+            mouse::Interaction::Grab
+        } else {
+            mouse::Interaction::default()
+        };
+        // Clipped, as layers follow a scrolled page past the edges.
+        let page_area = sensor(
+            mouse_area(
+                container(page_area)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .clip(true),
+            )
+            .on_move(Message::CursorMoved)
+            .on_scroll(Message::WheelScrolled)
+            .on_press(Message::PagePressed)
+            .on_release(Message::PageReleased)
+            .on_exit(Message::PageReleased)
+            .interaction(grab),
+        )
+        .on_show(Message::PageAreaResized)
+        .on_resize(Message::PageAreaResized)
+        .into();
+
+        let page_area = match &self.split {
+            Some(split) => {
+                let pane = &split.pane;
+                let fit = page_fit(
+                    &self.config,
+                    pane.page_layouts.get(&pane.current_page_index),
+                    self.zoom,
+                    pane.pan,
+                    pane.scroll,
+                );
+                let other = ui::page_area(pane.current_image.as_ref(), fit, theme);
+                let other = if self.config.scrollbar && !zen {
+                    let (top, extent) = ui::document_position(
+                        pane.current_page_index,
+                        pane.total_pages,
+                        self.page_area_size,
+                        fit,
+                    );
+                    stack![other, ui::scrollbar(top, extent, theme)].into()
+                } else {
+                    other
+                };
+                let focused = ui::pane(page_area, true, theme);
+                let other = ui::pane(other, false, theme);
+                if split.left {
+                    row![other, focused].into()
+                } else {
+                    row![focused, other].into()
+                }
+            }
+            None => page_area,
+        };
+
+        let page_area = if self.toc_open {
+            let height = self.window_size.height;
+            row![ui::toc_panel(&self.toc, height, theme), page_area].into()
+        } else {
+            page_area
+        };
+
+        let status_text = if let Some(prompt) = &self.password_prompt {
+            let label = match prompt.user_password {
+                None => "Password to open (empty: none)",
+                Some(_) => "Owner password (empty: same)",
+            };
+            format!("{}: {}", label, "*".repeat(prompt.input.chars().count()))
+        } else if let Some(input) = &self.command_input {
+            format!(":{}", input)
+        } else if let Some(hints) = &self.hints {
+            format!("Follow link: {}", hints.typed)
+        } else if let Some(annotation) = self
+            .annotation_at_cursor()
+            .filter(|annotation| self.selection.is_some() && !annotation.note.is_empty())
+        {
+            format!("{}: {} (K: open)", annotation.kind, annotation.note)
+        } else if let Some(selection) = &self.selection
+            && let Some(layout) = self.page_layouts.get(&self.current_page_index)
+        {
+            selection.text(&layout.words)
+        } else if let Some(message) = &self.status_message {
+            message.clone()
+        } else if let (Some(form), Some(field)) = (&self.form, self.focused_field()) {
+            let value = match field.kind {
+                FieldKind::Text => form.value.clone(),
+                FieldKind::Choice => format!("{}  [{}]", field.value, field.options.join(" | ")),
+                _ if field.checked => "[x]".to_string(),
+                _ => "[ ]".to_string(),
+            };
+            format!("{}: {}", field.name, value)
+        } else {
+            self.status_line()
+        };
+
+        let mode = if self.overlay.is_some() {
+            ui::Mode::Overlay
+        } else if self.command_input.is_some() || self.password_prompt.is_some() {
+            ui::Mode::Command
+        } else if self.hints.is_some() {
+            ui::Mode::Hint
+        } else if self.selection.is_some() {
+            ui::Mode::Visual
+        } else if self.ink.is_some() {
+            ui::Mode::Draw
+        } else if self.form.is_some() {
+            ui::Mode::Form
+        } else if self.toc_open {
+            ui::Mode::Toc
+        } else {
+            ui::Mode::Normal
+        };
+
+        let section = outline::section_at(&self.toc.items, self.current_page_index)
+            .map(|index| self.toc.items[index].title.clone());
+
+        let now = Instant::now();
+        let mut toasts: Vec<(Level, String)> = self
+            .toasts
+            .shown(now)
+            .map(|toast| (toast.level, toast.text.clone()))
+            .collect();
+        let hide_status_bar = (!self.config.statusbar || zen) && mode == ui::Mode::Normal;
+        if zen {
+            toasts.clear();
+        } else if hide_status_bar {
+            if let Some(message) = &self.status_message {
+                toasts.push((Level::Info, message.clone()));
+            } else if self.page_toast_until.is_some_and(|until| now < until) {
+                toasts.push((Level::Info, self.page_position()));
+            }
+        }
+        let page_area = if toasts.is_empty() {
+            page_area
+        } else {
+            stack![page_area, ui::toasts(toasts, theme)].into()
+        };
+
+        let (tabline, banner) = if zen {
+            (None, None)
+        } else {
+            let banner = self
+                .banner
+                .clone()
+                .map(|message| ui::banner(message, theme));
+            (self.tabline(theme), banner)
+        };
+        if hide_status_bar {
+            return column![tabline, banner, page_area].into();
+        }
+
+        let badge = self.signature_badge();
+        column![
+            tabline,
+            banner,
+            page_area,
+            ui::status_bar(
+                mode,
+                status_text,
+                self.background_progress(),
+                section,
+                badge,
+                self.config.eink,
+                theme
+            )
+        ]
+        .into()
+    }
+
+    fn tabline(&self, theme: &theme::Theme) -> Option<Element<'_, Message>> {
+        if !self.config.tabline || self.buffers.len() < 2 {
+            return None;
+        }
+        let labels = self
+            .buffers
+            .iter()
+            .enumerate()
+            .map(|(index, file_name)| {
+                let name = Path::new(file_name)
+                    .file_name()
+                    .map_or(file_name.clone(), |name| {
+                        name.to_string_lossy().into_owned()
+                    });
+                let modified = if index == self.current_buffer {
+                    self.modified
+                } else {
+                    self.parked
+                        .get(file_name)
+                        .is_some_and(|buffer| buffer.modified)
+                };
+                let flag = if modified { " +" } else { "" };
+                format!("{} {}{}", index + 1, name, flag)
+            })
+            .collect();
+        Some(ui::tabline(labels, self.current_buffer, theme))
+    }
+}
+
+/// Condenses a multi-line error (such as a TOML parse report) to its heading and cause.
+fn single_line(message: &str) -> String {
+    let mut lines = message.lines().map(str::trim).filter(|l| !l.is_empty());
+    let first = lines.next().unwrap_or_default().trim_end_matches(':');
+    let rest: Vec<&str> = lines
+        .filter(|l| !l.starts_with('|') && !l.contains(" | "))
+        .collect();
+    match rest.last() {
+        Some(cause) => format!("{}: {}", first, cause),
+        None => first.to_string(),
+    }
+}
+
+/// The state store key for `file_name` and its saved state.
+fn load_document_state(
+    file_name: &str,
+) -> Result<(String, DocumentState), Box<dyn std::error::Error>> {
+    let key = state::document_key(Path::new(file_name))?;
+    let state = StateStore::load()?.get(&key);
+    Ok((key, state))
+}
+
+/// How far an animation started at `started` is at `now`, from 0 to 1, or `None`
+/// once it has run for `duration`.
+fn progress(started: Instant, now: Instant, duration: Duration) -> Option<f32> {
+    let elapsed = now.saturating_duration_since(started);
+    (elapsed < duration).then(|| elapsed.as_secs_f32() / duration.as_secs_f32())
+}
+
+/// Cubic ease-out: fast at first, settling gently.
+fn ease_out(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// How `layout`'s page is laid out at `zoom`, panned and scrolled where it is larger
+/// than the page area; shown whole until its layout is known.
+fn page_fit(
+    config: &ViewerConfig,
+    layout: Option<&PageLayout>,
+    zoom: f32,
+    pan: f32,
+    scroll: f32,
+) -> ui::PageFit {
+    ui::PageFit {
+        aspect_ratio: layout.map_or(1.0, |layout| layout.aspect_ratio),
+        padding: config.padding() as f32,
+        fit_width: config.fit_width && layout.is_some(),
+        zoom: layout.map_or(1.0, |_| zoom),
+        pan,
+        scroll,
+    }
+}
+
+/// The marks saved for a document, leaving out pages it no longer has.
+fn marks_from_state(state: &DocumentState, total_pages: u16) -> Marks {
+    let mut marks = Marks::default();
+    for (&name, &page_index) in &state.marks {
+        if page_index < total_pages as usize {
+            marks.set(name, page_index);
+        }
+    }
+    marks
+}
+
+fn active_content_banner(
+    content: &ActiveContent,
+    config: &ViewerConfig,
+    messages: &mut MessageLog,
+) -> Option<String> {
+    if !config.warn_active_content || content.is_empty() {
+        return None;
+    }
+    let warning = content.warning();
+    messages.push(Level::Warning, warning.clone());
+    Some(warning)
+}
+
+/// `1536` as `1.5 KB`.
+fn format_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Applies `:set option=value` to a number option that must stay within `range`,
+/// answering with its new value.
+fn set_level(
+    option: &str,
+    value: &SetValue,
+    level: &mut f32,
+    range: std::ops::RangeInclusive<f32>,
+) -> Result<Option<String>, String> {
+    match value {
+        SetValue::Value(value) => {
+            let parsed: f32 = value
+                .parse()
+                .map_err(|_| format!("Invalid number for {}: {}", option, value))?;
+            if !range.contains(&parsed) {
+                return Err(format!(
+                    "{} must be between {} and {}",
+                    option,
+                    range.start(),
+                    range.end()
+                ));
+            }
+            *level = parsed;
+        }
+        SetValue::Query => {}
+        _ => return Err(format!("Usage: set {}=<number>", option)),
+    }
+    Ok(Some(format!("{}={}", option, level)))
+}
+
+/// Opens `file_name` and spawns its render and text-index threads.
+fn load_document(
+    file_name: &str,
+    initial_page: usize,
+    (width, height): (u16, u16),
+    settings: RenderSettings,
+    color_mode: ColorMode,
+) -> Result<LoadedDocument, String> {
+    let file_name_for_render = file_name.to_string();
+    let file_name_for_index = file_name.to_string();
+
+    let (total_pages, outline, page_labels, attachments, info) = {
+        let pdfium =
+            engine::init_pdfium().map_err(|e| format!("Fatal Error loading PDFium: {}", e))?;
+        let document = pdfium
+            .load_pdf_from_file(file_name, None)
+            .map_err(|e| format!("Could not open file: {}", e))?;
+        let total_pages = document.pages().len();
+        let outline = outline::read_outline(&document);
+        let attachments = pdf::read_attachments(&document);
+        let info = pdf::read_info(&document);
+        let page_labels = pdf::read_page_labels(&document);
+        (total_pages, outline, page_labels, attachments, info)
+    };
+    let start_page = initial_page.min((total_pages as usize).saturating_sub(1));
+
+    let search_index = Arc::new(Mutex::new(Vec::new()));
+
+    let (render_tx, render_thread_rx) = mpsc::channel::<RenderCommand>();
+    let (ui_tx, ui_rx) = mpsc::channel::<RenderEvent>();
+
+    let index_store = search_index.clone();
+    let index_tx = ui_tx.clone();
+    thread::spawn(move || {
+        let pdfium = match engine::init_pdfium() {
+            Ok(f) => f,
+            Err(e) => {
+                let _ = index_tx.send(RenderEvent::Error(format!("Search unavailable: {}", e)));
+                return;
+            }
+        };
+        let document = match pdfium.load_pdf_from_file(&file_name_for_index, None) {
+            Ok(doc) => doc,
+            Err(e) => {
+                let _ = index_tx.send(RenderEvent::Error(format!(
+                    "Search unavailable: could not read the document: {}",
+                    e
+                )));
+                return;
+            }
+        };
+        let file = std::fs::read(&file_name_for_index).unwrap_or_default();
+        let _ = index_tx.send(RenderEvent::Scanned {
+            active_content: active::scan(&file),
+            signatures: signatures::read_signatures(&document, &file),
+            unsigned_fields: signatures::unsigned_fields(&file),
+        });
+        drop(file);
+
+        let page_count = document.pages().len();
+        for i in 0..page_count {
+            // Pages without readable text are indexed as empty so the rest stay in place.
+            let text_content = document
+                .pages()
+                .get(i)
+                .ok()
+                .and_then(|page| page.text().ok().map(|text_page| text_page.all()))
+                .unwrap_or_default();
+            index_store.lock().unwrap().push(text_content);
+        }
+    });
+
+    thread::spawn(move || {
+        let pdfium = match engine::init_pdfium() {
+            Ok(f) => f,
+            Err(e) => {
+                let _ = ui_tx.send(RenderEvent::Error(format!("Cannot render pages: {}", e)));
+                return;
+            }
+        };
+        let document = match pdfium.load_pdf_from_file(&file_name_for_render, None) {
+            Ok(doc) => doc,
+            Err(e) => {
+                let _ = ui_tx.send(RenderEvent::Error(format!(
+                    "Cannot render pages: could not read the document: {}",
+                    e
+                )));
+                return;
+            }
+        };
+        let mut renderer = PdfRenderer::new(document, settings);
+
+        while let Ok(cmd) = render_thread_rx.recv() {
+            match cmd {
+                RenderCommand::RenderPage(idx, w, h, color_mode) => {
+                    let _ = ui_tx.send(match renderer.render_page(idx, w, h, color_mode) {
+                        Ok(result) => RenderEvent::Page(result),
+                        Err(e) => RenderEvent::Error(e),
+                    });
+                }
+                RenderCommand::UpdateSettings(settings) => renderer.set_settings(settings),
+                RenderCommand::AddMarkup {
+                    page_index,
+                    kind,
+                    rects,
+                    color,
+                } => {
+                    let _ = ui_tx.send(
+                        match renderer.add_markup(page_index, kind, &rects, color, "") {
+                            Ok(()) => RenderEvent::Annotations(renderer.annotations()),
+                            Err(e) => RenderEvent::Error(e),
+                        },
+                    );
+                }
+                RenderCommand::SetField {
+                    page_index,
+                    annotation_index,
+                    value,
+                } => {
+                    let _ = ui_tx.send(
+                        match renderer.set_field(page_index, annotation_index, &value) {
+                            Ok(()) => RenderEvent::PendingFields(renderer.pending_fields()),
+                            Err(e) => RenderEvent::Error(e),
+                        },
+                    );
+                }
+                RenderCommand::HighlightFields(highlight) => {
+                    renderer.set_highlight_fields(highlight)
+                }
+                RenderCommand::AddInk {
+                    page_index,
+                    strokes,
+                    color,
+                    width,
+                } => {
+                    let _ =
+                        ui_tx.send(match renderer.add_ink(page_index, &strokes, color, width) {
+                            Ok(()) => RenderEvent::Annotations(renderer.annotations()),
+                            Err(e) => RenderEvent::Error(e),
+                        });
+                }
+                RenderCommand::AddNote {
+                    page_index,
+                    position,
+                    text,
+                } => {
+                    let _ = ui_tx.send(match renderer.add_note(page_index, position, &text) {
+                        Ok(()) => RenderEvent::Annotations(renderer.annotations()),
+                        Err(e) => RenderEvent::Error(e),
+                    });
+                }
+                RenderCommand::ListAnnotations => {
+                    let _ = ui_tx.send(RenderEvent::Annotations(renderer.annotations()));
+                }
+                RenderCommand::ListPendingFields => {
+                    let _ = ui_tx.send(RenderEvent::PendingFields(renderer.pending_fields()));
+                }
+                RenderCommand::ImportAnnotations(imported) => {
+                    match renderer.import_annotations(&imported) {
+                        Ok((added, skipped)) => {
+                            let _ = ui_tx.send(RenderEvent::Imported { added, skipped });
+                        }
+                        Err(e) => {
+                            let _ = ui_tx.send(RenderEvent::Error(e));
+                        }
+                    }
+                    let _ = ui_tx.send(RenderEvent::Annotations(renderer.annotations()));
+                }
+                RenderCommand::ExportFlattened(path) => {
+                    let _ = ui_tx.send(match renderer.export_flattened(&pdfium, &path) {
+                        Ok(()) => RenderEvent::Exported(path),
+                        Err(e) => RenderEvent::Error(e),
+                    });
+                }
+                RenderCommand::ExportImages { pages, dpi } => {
+                    let mut written = Vec::new();
+                    for (page_index, path) in pages {
+                        match renderer.export_image(page_index, dpi, &path) {
+                            Ok(()) => written.push(path),
+                            Err(e) => {
+                                let _ = ui_tx.send(RenderEvent::Error(e));
+                                break;
+                            }
+                        }
+                    }
+                    let _ = ui_tx.send(RenderEvent::ImagesExported(written));
+                }
+                RenderCommand::SaveAttachment { index, path } => {
+                    let _ = ui_tx.send(match renderer.save_attachment(index, &path) {
+                        Ok(()) => RenderEvent::Exported(path),
+                        Err(e) => RenderEvent::Error(e),
+                    });
+                }
+                RenderCommand::SaveEncrypted {
+                    path,
+                    user_password,
+                    owner_password,
+                } => {
+                    let _ = ui_tx.send(
+                        match renderer.save_encrypted(&path, &user_password, &owner_password) {
+                            Ok(()) => RenderEvent::Saved(path),
+                            Err(e) => RenderEvent::Error(e),
+                        },
+                    );
+                }
+                RenderCommand::Save(path) => {
+                    let _ = ui_tx.send(match renderer.save(&path) {
+                        Ok(()) => RenderEvent::Saved(path),
+                        Err(e) => RenderEvent::Error(e),
+                    });
+                }
+            }
+        }
+    });
+
+    render_tx
+        .send(RenderCommand::RenderPage(
+            start_page, width, height, color_mode,
+        ))
+        .unwrap();
+    let _ = render_tx.send(RenderCommand::ListAnnotations);
+    let _ = render_tx.send(RenderCommand::ListPendingFields);
+
+    Ok(LoadedDocument {
+        total_pages,
+        start_page,
+        render_tx,
+        render_rx: Arc::new(Mutex::new(ui_rx)),
+        search_index,
+        outline,
+        page_labels,
+        attachments,
+        info,
+        watcher: FileWatcher::new(Path::new(file_name)),
+    })
+}
@@ -0,0 +1,325 @@
+use pdfium_render::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::paths;
+
+const INDEX_FILE: &str = "library.json";
+const SAVE_EVERY: usize = 25;
+
+/// Persistent page-text index for every PDF under the directories passed to `index`.
+#[derive(Serialize, Deserialize, Default)]
+pub struct LibraryIndex {
+    pub roots: Vec<PathBuf>,
+    pub documents: BTreeMap<PathBuf, IndexedDocument>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct IndexedDocument {
+    pub modified: u64,
+    pub size: u64,
+    pub pages: Vec<String>,
+    /// `pages` lowercased for searching, filled in when the index is loaded.
+    #[serde(skip)]
+    lowercase: Vec<String>,
+}
+
+pub struct LibraryHit {
+    pub path: PathBuf,
+    pub page_index: usize,
+    pub score: f32,
+    pub snippet: String,
+}
+
+#[derive(Default)]
+pub struct IndexStats {
+    pub indexed: usize,
+    pub unchanged: usize,
+    pub removed: usize,
+    pub failed: usize,
+}
+
+impl LibraryIndex {
+    pub fn file_path() -> Result<PathBuf, Box<dyn Error>> {
+        let dir = paths::data_dir().ok_or("Could not determine the data directory")?;
+        Ok(dir.join(INDEX_FILE))
+    }
+
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = Self::file_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = fs::read(&path)?;
+        let mut index: Self = serde_json::from_slice(&data)
+            .map_err(|e| format!("Corrupt library index {}: {}", path.display(), e))?;
+        for document in index.documents.values_mut() {
+            document.lowercase = document
+                .pages
+                .iter()
+                .map(|page| page.to_lowercase())
+                .collect();
+        }
+        Ok(index)
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::file_path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Re-indexes new or modified PDFs under `root` and drops entries whose files are gone.
+    pub fn update_root(
+        &mut self,
+        pdfium: &Pdfium,
+        root: &Path,
+    ) -> Result<IndexStats, Box<dyn Error>> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| format!("Cannot index {}: {}", root.display(), e))?;
+        if !self.roots.contains(&root) {
+            self.roots.push(root.clone());
+        }
+
+        let mut files = Vec::new();
+        collect_pdfs(&root, &mut files);
+
+        let mut stats = IndexStats::default();
+        let found: HashSet<&PathBuf> = files.iter().collect();
+        let stale: Vec<PathBuf> = self
+            .documents
+            .keys()
+            .filter(|path| path.starts_with(&root) && !found.contains(path))
+            .cloned()
+            .collect();
+        for path in stale {
+            self.documents.remove(&path);
+            stats.removed += 1;
+        }
+
+        let total = files.len();
+        for (i, path) in files.iter().enumerate() {
+            let Some((modified, size)) = file_stamp(path) else {
+                stats.failed += 1;
+                continue;
+            };
+            if let Some(existing) = self.documents.get(path)
+                && existing.modified == modified
+                && existing.size == size
+            {
+                stats.unchanged += 1;
+                continue;
+            }
+
+            println!("[{}/{}] {}", i + 1, total, path.display());
+            match extract_pages(pdfium, path) {
+                Ok(pages) => {
+                    self.documents.insert(
+                        path.clone(),
+                        IndexedDocument {
+                            modified,
+                            size,
+                            lowercase: pages.iter().map(|page| page.to_lowercase()).collect(),
+                            pages,
+                        },
+                    );
+                    stats.indexed += 1;
+                    if stats.indexed % SAVE_EVERY == 0 {
+                        self.save()?;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Skipping {}: {}", path.display(), e);
+                    stats.failed += 1;
+                }
+            }
+        }
+
+        self.save()?;
+        Ok(stats)
+    }
+
+    /// The indexed text of `path` by page, if it has not changed since it was indexed.
+    pub fn cached_pages(&self, path: &Path) -> Option<&[String]> {
+        let document = self.documents.get(&path.canonicalize().ok()?)?;
+        let (modified, size) = file_stamp(path)?;
+        (document.modified == modified && document.size == size).then_some(&document.pages[..])
+    }
+
+    /// Ranks documents by their best-matching page; file names matching the query rank higher.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<LibraryHit> {
+        let query = query.to_lowercase();
+        let terms: Vec<&str> = query.split_whitespace().collect();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let mut hits: Vec<LibraryHit> = self
+            .documents
+            .iter()
+            .filter_map(|(path, document)| {
+                let file_name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let name_score =
+                    terms.iter().filter(|t| file_name.contains(*t)).count() as f32 * 3.0;
+
+                let best_page = document
+                    .lowercase
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, page)| score_page(page, &query, &terms).map(|s| (i, s)))
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+
+                let (page_index, page_score) = match best_page {
+                    Some(best) => best,
+                    None if name_score > 0.0 => (0, 0.0),
+                    None => return None,
+                };
+                let snippet = document
+                    .pages
+                    .get(page_index)
+                    .map(|page| snippet(page, terms[0]))
+                    .unwrap_or_default();
+
+                Some(LibraryHit {
+                    path: path.clone(),
+                    page_index,
+                    score: page_score + name_score,
+                    snippet,
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+fn score_page(lower_text: &str, phrase: &str, terms: &[&str]) -> Option<f32> {
+    let mut matched = 0;
+    let mut frequency = 0.0;
+    for term in terms {
+        let count = lower_text.matches(term).count();
+        if count > 0 {
+            matched += 1;
+            frequency += (1.0 + count as f32).ln();
+        }
+    }
+    if matched == 0 {
+        return None;
+    }
+
+    let phrase_bonus = if terms.len() > 1 && lower_text.contains(phrase) {
+        5.0
+    } else {
+        0.0
+    };
+    Some((matched * matched) as f32 * 2.0 + frequency + phrase_bonus)
+}
+
+/// A single-line excerpt of `text` around the first case-insensitive occurrence of `term`.
+pub fn snippet(text: &str, term: &str) -> String {
+    const CONTEXT: usize = 40;
+
+    let lower = text.to_lowercase();
+    let start = match lower.find(&term.to_lowercase()) {
+        Some(pos) => lower[..pos].chars().count().saturating_sub(CONTEXT),
+        None => 0,
+    };
+    let excerpt: String = text
+        .chars()
+        .skip(start)
+        .take(CONTEXT * 2 + term.chars().count())
+        .collect();
+    excerpt.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn collect_pdfs(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_pdfs(&path, out);
+        } else if file_type.is_file() && is_pdf(&path) {
+            out.push(path);
+        }
+    }
+}
+
+/// PDFs at most `max_depth` directories below `dir`, nearest first, leaving out hidden
+/// directories and stopping after `max_entries` directory entries, so that a document
+/// in the home directory does not mean walking all of it. Also says whether it stopped.
+pub fn nearby_pdfs(dir: &Path, max_depth: usize, max_entries: usize) -> (Vec<PathBuf>, bool) {
+    let mut found = Vec::new();
+    let mut seen = 0;
+    let mut queue = VecDeque::from([(dir.to_path_buf(), 0)]);
+    while let Some((dir, depth)) = queue.pop_front() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            seen += 1;
+            if seen > max_entries {
+                return (found, true);
+            }
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+            if file_type.is_dir() {
+                let hidden = entry.file_name().to_string_lossy().starts_with('.');
+                if !hidden && depth < max_depth {
+                    queue.push_back((path, depth + 1));
+                }
+            } else if file_type.is_file() && is_pdf(&path) {
+                found.push(path);
+            }
+        }
+    }
+    (found, false)
+}
+
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("pdf"))
+}
+
+fn file_stamp(path: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((modified, metadata.len()))
+}
+
+pub fn extract_pages(pdfium: &Pdfium, path: &Path) -> Result<Vec<String>, PdfiumError> {
+    let document = pdfium.load_pdf_from_file(path, None)?;
+    let pages = document
+        .pages()
+        .iter()
+        .map(|page| page.text().map(|t| t.all()).unwrap_or_default())
+        .collect();
+    Ok(pages)
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeMap;
+
+const MAX_JUMPS: usize = 100;
+
+#[derive(Debug, Default)]
+pub struct Marks {
+    pages: BTreeMap<char, usize>,
+}
+
+impl Marks {
+    pub fn set(&mut self, name: char, page_index: usize) {
+        self.pages.insert(name, page_index);
+    }
+
+    pub fn get(&self, name: char) -> Option<usize> {
+        self.pages.get(&name).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (char, usize)> + '_ {
+        self.pages.iter().map(|(name, page)| (*name, *page))
+    }
+}
+
+/// Vim-style jump list: `cursor == entries.len()` means we are at the newest position.
+#[derive(Debug, Default)]
+pub struct JumpList {
+    entries: Vec<usize>,
+    cursor: usize,
+}
+
+impl JumpList {
+    pub fn push(&mut self, from_page: usize) {
+        self.entries.truncate(self.cursor);
+        if self.entries.last() != Some(&from_page) {
+            self.entries.push(from_page);
+        }
+        if self.entries.len() > MAX_JUMPS {
+            self.entries.remove(0);
+        }
+        self.cursor = self.entries.len();
+    }
+
+    pub fn back(&mut self, current_page: usize) -> Option<usize> {
+        if self.cursor == 0 {
+            return None;
+        }
+        if self.cursor == self.entries.len() && self.entries.last() != Some(&current_page) {
+            self.entries.push(current_page);
+            if self.entries.len() > MAX_JUMPS {
+                self.entries.remove(0);
+                self.cursor -= 1;
+            }
+        }
+        // Going back to the page already shown would seem to do nothing.
+        if self.entries[self.cursor - 1] == current_page {
+            self.entries.remove(self.cursor - 1);
+            self.cursor -= 1;
+            if self.cursor == 0 {
+                return None;
+            }
+        }
+        self.cursor -= 1;
+        Some(self.entries[self.cursor])
+    }
+
+    pub fn forward(&mut self) -> Option<usize> {
+        if self.cursor + 1 >= self.entries.len() {
+            return None;
+        }
+        self.cursor += 1;
+        Some(self.entries[self.cursor])
+    }
+
+    pub fn entries(&self) -> &[usize] {
+        &self.entries
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+}
+
+/// Pages left by following a link or outline entry, most recent last. Unlike the
+/// jump list it ignores `G`, marks and `:N`, so Backspace retraces only those hops.
+#[derive(Debug, Default)]
+pub struct BackStack {
+    pages: Vec<usize>,
+}
+
+impl BackStack {
+    pub fn push(&mut self, from_page: usize) {
+        self.pages.push(from_page);
+        if self.pages.len() > MAX_JUMPS {
+            self.pages.remove(0);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<usize> {
+        self.pages.pop()
+    }
+}
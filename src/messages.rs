@@ -0,0 +1,112 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const MAX_MESSAGES: usize = 200;
+/// How long a toast stays up; warnings and errors stay twice as long.
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+/// Toasts shown at once; the oldest makes way for a new one.
+const MAX_TOASTS: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    fn label(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+pub struct LogEntry {
+    pub level: Level,
+    pub text: String,
+    pub time: Instant,
+}
+
+impl LogEntry {
+    /// One line for the `:messages` overlay, e.g. `error  2m ago  Could not open file`.
+    pub fn label(&self) -> String {
+        format!(
+            "{:<5}  {:>7}  {}",
+            self.level.label(),
+            format_age(self.time.elapsed().as_secs()),
+            self.text
+        )
+    }
+}
+
+/// Every notification shown in the status bar, oldest first, capped at `MAX_MESSAGES`.
+#[derive(Default)]
+pub struct MessageLog {
+    entries: VecDeque<LogEntry>,
+}
+
+impl MessageLog {
+    pub fn push(&mut self, level: Level, text: String) {
+        if self.entries.len() == MAX_MESSAGES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry {
+            level,
+            text,
+            time: Instant::now(),
+        });
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogEntry> {
+        self.entries.iter()
+    }
+}
+
+pub struct Toast {
+    pub level: Level,
+    pub text: String,
+    until: Instant,
+}
+
+/// Notifications shown over the page for a few seconds without waiting for a key.
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+}
+
+impl Toasts {
+    /// Shows `text`, or keeps it up longer when it is already the newest toast.
+    pub fn push(&mut self, level: Level, text: String) {
+        let duration = match level {
+            Level::Info => TOAST_DURATION,
+            Level::Warning | Level::Error => TOAST_DURATION * 2,
+        };
+        let until = Instant::now() + duration;
+        if let Some(last) = self.queue.back_mut()
+            && last.text == text
+        {
+            last.until = until;
+            return;
+        }
+        if self.queue.len() == MAX_TOASTS {
+            self.queue.pop_front();
+        }
+        self.queue.push_back(Toast { level, text, until });
+    }
+
+    /// The toasts still up at `now`, oldest first.
+    pub fn shown(&self, now: Instant) -> impl Iterator<Item = &Toast> {
+        self.queue.iter().filter(move |toast| toast.until > now)
+    }
+}
+
+fn format_age(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{}s ago", secs),
+        60..3600 => format!("{}m ago", secs / 60),
+        _ => format!("{}h ago", secs / 3600),
+    }
+}
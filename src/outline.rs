@@ -0,0 +1,281 @@
+use pdfium_render::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+
+use crate::export::ExportFormat;
+
+/// One bookmark of the document outline, flattened in reading order.
+#[derive(Debug, Clone)]
+pub struct OutlineItem {
+    pub title: String,
+    /// `None` when the bookmark points outside the document or has no destination.
+    pub page_index: Option<usize>,
+    pub depth: usize,
+}
+
+/// Reads the outline depth-first so children directly follow their parent.
+pub fn read_outline(document: &PdfDocument) -> Vec<OutlineItem> {
+    let mut items = Vec::new();
+    if let Some(root) = document.bookmarks().root() {
+        collect(root, 0, &mut items);
+    }
+    items
+}
+
+fn collect(first: PdfBookmark, depth: usize, items: &mut Vec<OutlineItem>) {
+    let mut next = Some(first);
+    while let Some(bookmark) = next {
+        let page_index = match bookmark.destination() {
+            Some(destination) => destination.page_index().ok(),
+            None => bookmark.action().and_then(|action| {
+                action
+                    .as_local_destination_action()?
+                    .destination()
+                    .ok()?
+                    .page_index()
+                    .ok()
+            }),
+        };
+        items.push(OutlineItem {
+            title: bookmark.title().unwrap_or_default().trim().to_string(),
+            page_index: page_index.map(|i| i as usize),
+            depth,
+        });
+        if let Some(child) = bookmark.first_child() {
+            collect(child, depth + 1, items);
+        }
+        next = bookmark.next_sibling();
+    }
+}
+
+/// Index of the innermost section containing `page_index`: the last entry that starts
+/// on or before it.
+pub fn section_at(items: &[OutlineItem], page_index: usize) -> Option<usize> {
+    items
+        .iter()
+        .rposition(|item| item.page_index.is_some_and(|p| p <= page_index))
+}
+
+/// Where `page_index` is in its top-level chapter, counting from 1, and how many pages
+/// the chapter has; `None` before the first chapter or without an outline.
+pub fn chapter_position(
+    items: &[OutlineItem],
+    page_index: usize,
+    total_pages: usize,
+) -> Option<(usize, usize)> {
+    let starts = || {
+        items
+            .iter()
+            .filter(|item| item.depth == 0)
+            .filter_map(|item| item.page_index)
+    };
+    let start = starts().filter(|&start| start <= page_index).max()?;
+    let end = starts()
+        .filter(|&start| start > page_index)
+        .min()
+        .unwrap_or(total_pages);
+    Some((page_index - start + 1, end - start))
+}
+
+/// The outline entry whose title best fuzzy-matches `query`, among those with a
+/// destination in the document.
+pub fn find_heading(items: &[OutlineItem], query: &str) -> Option<usize> {
+    let query: Vec<char> = query
+        .to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect();
+    items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| item.page_index.is_some())
+        .filter_map(|(i, item)| fuzzy_score(&item.title, &query).map(|score| (i, score)))
+        .max_by_key(|&(i, score)| (score, std::cmp::Reverse(i)))
+        .map(|(i, _)| i)
+}
+
+/// Scores `title` if every query character appears in order, rewarding runs of
+/// consecutive matches and matches at the start of words.
+fn fuzzy_score(title: &str, query: &[char]) -> Option<i64> {
+    let title: Vec<char> = title.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for &wanted in query {
+        let found = position + title[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !title[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    // Prefer short titles among equally good matches.
+    Some(score * 100 - title.len() as i64)
+}
+
+#[derive(Serialize)]
+struct TocNode<'a> {
+    title: &'a str,
+    /// 1-based, omitted for entries without a destination.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    children: Vec<TocNode<'a>>,
+}
+
+/// Renders the outline as nested JSON or as an indented Markdown list with page numbers.
+pub fn export(items: &[OutlineItem], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Json => {
+            let mut position = 0;
+            let tree = build_tree(items, &mut position, 0);
+            serde_json::to_string_pretty(&tree).unwrap_or_default()
+        }
+        ExportFormat::Markdown => items
+            .iter()
+            .map(|item| {
+                let page = item
+                    .page_index
+                    .map(|p| format!(" (p. {})", p + 1))
+                    .unwrap_or_default();
+                format!("{}- {}{}\n", "  ".repeat(item.depth), item.title, page)
+            })
+            .collect(),
+    }
+}
+
+fn build_tree<'a>(
+    items: &'a [OutlineItem],
+    position: &mut usize,
+    depth: usize,
+) -> Vec<TocNode<'a>> {
+    let mut nodes = Vec::new();
+    while let Some(item) = items.get(*position) {
+        if item.depth < depth {
+            break;
+        }
+        *position += 1;
+        let children = build_tree(items, position, item.depth + 1);
+        nodes.push(TocNode {
+            title: &item.title,
+            page: item.page_index.map(|p| p + 1),
+            children,
+        });
+    }
+    nodes
+}
+
+/// The table-of-contents sidebar: the outline plus selection and collapsed entries.
+pub struct TocPanel {
+    pub items: Vec<OutlineItem>,
+    collapsed: HashSet<usize>,
+    /// Index into `items`; always a visible entry.
+    pub selected: usize,
+}
+
+impl TocPanel {
+    pub fn new(items: Vec<OutlineItem>) -> Self {
+        Self {
+            items,
+            collapsed: HashSet::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn has_children(&self, index: usize) -> bool {
+        self.items
+            .get(index + 1)
+            .is_some_and(|next| next.depth > self.items[index].depth)
+    }
+
+    pub fn is_collapsed(&self, index: usize) -> bool {
+        self.collapsed.contains(&index)
+    }
+
+    /// Indices of the entries not hidden under a collapsed parent.
+    pub fn visible(&self) -> Vec<usize> {
+        let mut visible = Vec::new();
+        let mut hidden_below: Option<usize> = None;
+        for (i, item) in self.items.iter().enumerate() {
+            if let Some(depth) = hidden_below {
+                if item.depth > depth {
+                    continue;
+                }
+                hidden_below = None;
+            }
+            visible.push(i);
+            if self.collapsed.contains(&i) {
+                hidden_below = Some(item.depth);
+            }
+        }
+        visible
+    }
+
+    pub fn select_next(&mut self) {
+        let visible = self.visible();
+        if let Some(pos) = visible.iter().position(|&i| i == self.selected)
+            && let Some(&next) = visible.get(pos + 1)
+        {
+            self.selected = next;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let visible = self.visible();
+        if let Some(pos) = visible.iter().position(|&i| i == self.selected)
+            && pos > 0
+        {
+            self.selected = visible[pos - 1];
+        }
+    }
+
+    /// Collapses the selected entry, or moves to its parent if it is already collapsed
+    /// or has no children.
+    pub fn collapse(&mut self) {
+        if self.has_children(self.selected) && self.collapsed.insert(self.selected) {
+            return;
+        }
+        let depth = self.items[self.selected].depth;
+        if let Some(parent) = (0..self.selected)
+            .rev()
+            .find(|&i| self.items[i].depth < depth)
+        {
+            self.selected = parent;
+        }
+    }
+
+    pub fn expand(&mut self) {
+        self.collapsed.remove(&self.selected);
+    }
+
+    /// Selects the section containing `page_index`.
+    pub fn reveal_page(&mut self, page_index: usize) {
+        if let Some(target) = section_at(&self.items, page_index) {
+            self.select(target);
+        }
+    }
+
+    /// Selects `index`, expanding its parents so it is visible.
+    pub fn select(&mut self, index: usize) {
+        let Some(item) = self.items.get(index) else {
+            return;
+        };
+        let mut depth = item.depth;
+        for i in (0..index).rev() {
+            if self.items[i].depth < depth {
+                self.collapsed.remove(&i);
+                depth = self.items[i].depth;
+            }
+        }
+        self.selected = index;
+    }
+
+    pub fn selected_page(&self) -> Option<usize> {
+        self.items.get(self.selected)?.page_index
+    }
+}
@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+
+use crate::keymap::Action;
+use crate::pdf::LinkTarget;
+
+pub enum OverlayTarget {
+    Page(usize),
+    Document(PathBuf, usize),
+    Link(LinkTarget),
+    /// Copied to the clipboard when chosen.
+    Text(String),
+    /// An embedded file, extracted next to the document when chosen.
+    Attachment(usize),
+    /// An open document, by its index in the buffer list.
+    Buffer(usize),
+    /// A document to open where it was last left.
+    File(PathBuf),
+    /// A key binding in the cheatsheet, performed when chosen.
+    Action(Action),
+    /// A line that only groups the entries below it.
+    Heading,
+}
+
+pub struct OverlayEntry {
+    pub label: String,
+    pub target: OverlayTarget,
+}
+
+pub struct Overlay {
+    pub title: String,
+    pub entries: Vec<OverlayEntry>,
+    /// Indices of the entries matching `filter`; `selected` is a position in it.
+    pub matches: Vec<usize>,
+    pub selected: usize,
+    /// Typed after `/` to narrow the entries down.
+    pub filter: Option<String>,
+    /// Keys go to `filter` instead of moving the selection.
+    pub typing: bool,
+}
+
+impl Overlay {
+    pub fn new(title: impl Into<String>, entries: Vec<OverlayEntry>) -> Self {
+        Self {
+            title: title.into(),
+            matches: (0..entries.len()).collect(),
+            entries,
+            selected: 0,
+            filter: None,
+            typing: false,
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        if self.selected + 1 < self.matches.len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    pub fn selected_entry(&self) -> Option<&OverlayEntry> {
+        self.matches
+            .get(self.selected)
+            .map(|&index| &self.entries[index])
+    }
+
+    /// The entries matching the filter, with their positions in `matches`.
+    pub fn shown(&self) -> impl Iterator<Item = (usize, &OverlayEntry)> {
+        self.matches
+            .iter()
+            .enumerate()
+            .map(|(position, &index)| (position, &self.entries[index]))
+    }
+
+    pub fn start_filter(&mut self) {
+        self.filter.get_or_insert_default();
+        self.typing = true;
+    }
+
+    pub fn push_filter(&mut self, text: &str) {
+        self.filter.get_or_insert_default().push_str(text);
+        self.refilter();
+    }
+
+    pub fn pop_filter(&mut self) {
+        if let Some(filter) = &mut self.filter {
+            filter.pop();
+        }
+        self.refilter();
+    }
+
+    pub fn clear_filter(&mut self) {
+        self.filter = None;
+        self.typing = false;
+        self.refilter();
+    }
+
+    fn refilter(&mut self) {
+        let filter = self.filter.as_deref().unwrap_or("");
+        self.matches = (0..self.entries.len())
+            .filter(|&index| fuzzy_match(filter, &self.entries[index].label))
+            .collect();
+        self.selected = 0;
+    }
+}
+
+/// Whether the characters of `filter` appear in `text` in order, ignoring case.
+fn fuzzy_match(filter: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    filter
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+        .all(|c| text.any(|t| t == c))
+}
+
+/// Splits `text` into lines of at most `width` characters at word boundaries, for
+/// showing long text one entry per line.
+pub fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(word);
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
@@ -0,0 +1,100 @@
+use pdfium_render::prelude::*;
+
+/// Copies pages `page_indices` of `source`, in that order, to the end of `target`.
+pub fn copy_pages(
+    target: &mut PdfDocument,
+    source: &PdfDocument,
+    page_indices: &[usize],
+) -> Result<(), PdfiumError> {
+    // Consecutive pages are imported together, which is much faster than one at a time.
+    let mut rest = page_indices;
+    while let Some(&first) = rest.first() {
+        let run = rest
+            .iter()
+            .zip(first..)
+            .take_while(|&(&index, expected)| index == expected)
+            .count();
+        let last = first + run - 1;
+        let destination = target.pages().len();
+        target.pages_mut().copy_page_range_from_document(
+            source,
+            first as PdfPageIndex..=last as PdfPageIndex,
+            destination,
+        )?;
+        rest = &rest[run..];
+    }
+    Ok(())
+}
+
+/// Turns pages clockwise by `quarter_turns` quarter turns, on top of their current
+/// rotation.
+pub fn rotate_pages(
+    document: &PdfDocument,
+    page_indices: &[usize],
+    quarter_turns: i32,
+) -> Result<(), PdfiumError> {
+    const ROTATIONS: [PdfPageRenderRotation; 4] = [
+        PdfPageRenderRotation::None,
+        PdfPageRenderRotation::Degrees90,
+        PdfPageRenderRotation::Degrees180,
+        PdfPageRenderRotation::Degrees270,
+    ];
+    for &page_index in page_indices {
+        let mut page = document.pages().get(page_index as PdfPageIndex)?;
+        let current = page
+            .rotation()
+            .ok()
+            .and_then(|current| ROTATIONS.iter().position(|&rotation| rotation == current))
+            .unwrap_or(0) as i32;
+        page.set_rotation(ROTATIONS[(current + quarter_turns).rem_euclid(4) as usize]);
+    }
+    Ok(())
+}
+
+/// Removes pages `page_indices`, which may repeat and come in any order.
+pub fn delete_pages(document: &PdfDocument, page_indices: &[usize]) -> Result<(), PdfiumError> {
+    let mut page_indices = page_indices.to_vec();
+    page_indices.sort_unstable();
+    page_indices.dedup();
+    // From the end, so the remaining indices stay valid.
+    for &page_index in page_indices.iter().rev() {
+        document.pages().get(page_index as PdfPageIndex)?.delete()?;
+    }
+    Ok(())
+}
+
+/// Zero-based page indices for a list of 1-based pages and ranges such as `1-5,8,10-`,
+/// in the order given; every page when `spec` is `None`.
+pub fn page_indices(spec: Option<&str>, page_count: usize) -> Result<Vec<usize>, String> {
+    let Some(spec) = spec else {
+        return Ok((0..page_count).collect());
+    };
+    let page = |number: &str| match number.trim().parse::<usize>() {
+        Ok(n) if (1..=page_count).contains(&n) => Ok(n - 1),
+        Ok(n) => Err(format!(
+            "Page {} is out of range; the document has {} pages",
+            n, page_count
+        )),
+        Err(_) => Err(format!("Not a page number: {}", number.trim())),
+    };
+
+    let mut indices = Vec::new();
+    for part in spec.split(',') {
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let first = page(first)?;
+                let last = if last.trim().is_empty() {
+                    page_count.saturating_sub(1)
+                } else {
+                    page(last)?
+                };
+                if first > last {
+                    return Err(format!("Backwards page range: {}", part.trim()));
+                }
+                indices.extend(first..=last);
+            }
+            None => indices.push(page(part)?),
+        }
+    }
+    Ok(indices)
+}
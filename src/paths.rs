@@ -0,0 +1,26 @@
+use std::env;
+use std::path::PathBuf;
+
+const APP_DIR: &str = "rufium";
+
+/// `$XDG_DATA_HOME/rufium`, falling back to `~/.local/share/rufium` (or `%APPDATA%\rufium`).
+pub fn data_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_DATA_HOME", &[".local", "share"])
+}
+
+/// `$XDG_CONFIG_HOME/rufium`, falling back to `~/.config/rufium` (or `%APPDATA%\rufium`).
+pub fn config_dir() -> Option<PathBuf> {
+    xdg_dir("XDG_CONFIG_HOME", &[".config"])
+}
+
+fn xdg_dir(var: &str, home_fallback: &[&str]) -> Option<PathBuf> {
+    if let Some(dir) = env::var_os(var).filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(dir).join(APP_DIR));
+    }
+    if cfg!(target_os = "windows") {
+        return env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join(APP_DIR));
+    }
+    let mut dir = PathBuf::from(env::var_os("HOME")?);
+    dir.extend(home_fallback);
+    Some(dir.join(APP_DIR))
+}
@@ -0,0 +1,1445 @@
+use iced::{Color, Point, Rectangle, Size};
+use image::RgbaImage;
+use pdfium_render::prelude::*;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::active::ActiveContent;
+use crate::annotations::{
+    Coordinates, DocumentAnnotation, ImportKind, ImportedAnnotation, MarkupKind,
+};
+use crate::config::ViewerConfig;
+use crate::external;
+use crate::signatures::Signature;
+
+const MIN_RENDER_WIDTH: u16 = 800;
+/// Size of the icon of a new note annotation, in points.
+const NOTE_ICON_SIZE: f32 = 20.0;
+/// Imported annotations whose edges are all this close (relative to the page size) to
+/// an existing one of the same type are taken to be the same annotation.
+const IMPORT_TOLERANCE: f32 = 0.005;
+/// How much e-ink mode stretches the page's contrast around mid-gray, so faint text
+/// darkens and light backgrounds become white.
+const EINK_CONTRAST: f32 = 1.4;
+/// Translucent tint over form fields in form mode.
+const FIELD_HIGHLIGHT: PdfColor = PdfColor::new(80, 140, 255, 60);
+
+pub enum RenderCommand {
+    RenderPage(usize, u16, u16, ColorMode),
+    UpdateSettings(RenderSettings),
+    /// Adds a markup annotation covering `rects` (relative to the page).
+    AddMarkup {
+        page_index: usize,
+        kind: MarkupKind,
+        rects: Vec<Rectangle>,
+        color: Color,
+    },
+    /// Adds an ink annotation; strokes are lists of points relative to the page and
+    /// `width` is the pen width in points.
+    AddInk {
+        page_index: usize,
+        strokes: Vec<Vec<Point>>,
+        color: Color,
+        width: f32,
+    },
+    /// Adds a sticky note whose icon's top-left corner is at `position` (relative).
+    AddNote {
+        page_index: usize,
+        position: Point,
+        text: String,
+    },
+    /// Sets the value of the form field whose widget is annotation `annotation_index`.
+    SetField {
+        page_index: usize,
+        annotation_index: usize,
+        value: FieldValue,
+    },
+    /// Tints form fields in rendered pages, for form mode.
+    HighlightFields(bool),
+    /// Writes the document, including new annotations, to a file.
+    Save(PathBuf),
+    /// Like `Save`, then encrypts the file with these passwords.
+    SaveEncrypted {
+        path: PathBuf,
+        user_password: String,
+        owner_password: String,
+    },
+    /// Writes a copy with annotations and form fields merged into the page content.
+    ExportFlattened(PathBuf),
+    /// Writes pages as PNG images at `dpi`, each to its own file.
+    ExportImages {
+        pages: Vec<(usize, PathBuf)>,
+        dpi: f32,
+    },
+    /// Writes embedded file `index` to a file.
+    SaveAttachment {
+        index: usize,
+        path: PathBuf,
+    },
+    /// Lists every annotation in the document, including unsaved ones.
+    ListAnnotations,
+    /// Lists signature fields and empty required fields, including unsaved values.
+    ListPendingFields,
+    ImportAnnotations(Vec<ImportedAnnotation>),
+}
+
+/// Messages from the render thread to the UI.
+pub enum RenderEvent {
+    Page(RenderResult),
+    Saved(PathBuf),
+    /// A copy of the document or an embedded file was written.
+    Exported(PathBuf),
+    /// Page images were written.
+    ImagesExported(Vec<PathBuf>),
+    Annotations(Vec<DocumentAnnotation>),
+    PendingFields(Vec<PendingField>),
+    Imported {
+        added: usize,
+        skipped: usize,
+    },
+    /// What scanning the raw file found, once it has been read.
+    Scanned {
+        active_content: ActiveContent,
+        signatures: Vec<Signature>,
+        unsigned_fields: Vec<String>,
+    },
+    Error(String),
+}
+
+pub struct RenderResult {
+    pub page_index: usize,
+    pub target_width: u16,
+    pub color_mode: ColorMode,
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    /// The requested size exceeded `max_render_pixels` and was scaled down.
+    pub clamped: bool,
+    pub layout: PageLayout,
+}
+
+/// Page geometry that does not depend on the render size.
+#[derive(Debug, Clone)]
+pub struct PageLayout {
+    /// Width divided by height.
+    pub aspect_ratio: f32,
+    /// Page width in points.
+    pub width: f32,
+    pub links: Vec<PageLink>,
+    /// Words in reading order, for visual-mode selection.
+    pub words: Vec<PageWord>,
+    pub notes: Vec<PageNote>,
+    /// Fillable form fields in annotation order, which is usually the tab order.
+    pub fields: Vec<PageField>,
+}
+
+/// A text (sticky note) annotation.
+#[derive(Debug, Clone)]
+pub struct PageNote {
+    pub bounds: Rectangle,
+    pub contents: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldKind {
+    Text,
+    Checkbox,
+    RadioButton,
+    /// A combo or list box.
+    Choice,
+}
+
+/// A form field that is not read-only and of a kind form mode can fill.
+#[derive(Debug, Clone)]
+pub struct PageField {
+    pub annotation_index: usize,
+    pub name: String,
+    pub kind: FieldKind,
+    pub bounds: Rectangle,
+    /// Text of text fields.
+    pub value: String,
+    /// State of checkboxes and radio buttons.
+    pub checked: bool,
+    /// Option labels of choice fields.
+    pub options: Vec<String>,
+}
+
+/// A field that may still need filling in, for the field motions.
+#[derive(Debug, Clone)]
+pub struct PendingField {
+    pub page_index: usize,
+    /// The fully qualified field name.
+    pub name: String,
+    pub kind: PendingFieldKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingFieldKind {
+    /// Any signature field; pdfium cannot tell whether it is signed.
+    Signature,
+    /// A required field that is empty, unchecked or has no button of its group chosen.
+    Required,
+}
+
+#[derive(Debug, Clone)]
+pub enum FieldValue {
+    Text(String),
+    Checked(bool),
+    /// The label of the chosen option of a combo or list box.
+    Choice(String),
+}
+
+/// What the document's security handler allows; everything for unprotected files.
+#[derive(Debug, Clone, Serialize)]
+pub struct Permissions {
+    pub print: bool,
+    pub copy: bool,
+    pub modify: bool,
+    pub annotate: bool,
+    pub fill_forms: bool,
+}
+
+/// Metadata and permissions shown by `:info`.
+#[derive(Debug, Clone)]
+pub struct DocumentInfo {
+    /// Non-empty metadata fields by label.
+    pub metadata: Vec<(&'static str, String)>,
+    pub encrypted: bool,
+    pub permissions: Permissions,
+}
+
+/// A file embedded in the document.
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    /// In bytes.
+    pub size: usize,
+}
+
+/// A run of non-whitespace text; `bounds` is relative like `PageLink::bounds`.
+#[derive(Debug, Clone)]
+pub struct PageWord {
+    pub text: String,
+    pub bounds: Rectangle,
+}
+
+/// A link annotation; `bounds` is relative to the page size with the origin top-left.
+#[derive(Debug, Clone)]
+pub struct PageLink {
+    pub bounds: Rectangle,
+    /// The text under the link, if any.
+    pub text: String,
+    pub target: LinkTarget,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkTarget {
+    Page(usize),
+    Uri(String),
+}
+
+/// Render options copied into the render thread.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderSettings {
+    pub max_render_pixels: u64,
+    pub page_background: Color,
+    pub antialias_text: bool,
+    pub antialias_paths: bool,
+    pub antialias_images: bool,
+    pub lcd_text: bool,
+    pub render_for_printing: bool,
+}
+
+impl RenderSettings {
+    pub fn from_config(config: &ViewerConfig) -> Self {
+        Self {
+            max_render_pixels: config.max_render_pixels,
+            page_background: config.page_background.0,
+            antialias_text: config.antialias_text,
+            antialias_paths: config.antialias_paths,
+            antialias_images: config.antialias_images,
+            lcd_text: config.lcd_text,
+            render_for_printing: config.render_for_printing,
+        }
+    }
+}
+
+/// How rendered pages are colored; pages are cached per mode, so switching back and
+/// forth does not render them again.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ColorMode {
+    pub palette: Palette,
+    /// Rendered in shades of gray by PDFium, before `palette` is applied.
+    pub grayscale: bool,
+    /// Text and lines drawn without anti-aliasing and contrast raised, which e-ink
+    /// displays show without gray fringes.
+    pub eink: bool,
+    pub levels: Levels,
+}
+
+/// Brightness, contrast and gamma in hundredths, so that modes can be compared and
+/// hashed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Levels {
+    brightness: i32,
+    contrast: i32,
+    gamma: i32,
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self {
+            brightness: 0,
+            contrast: 100,
+            gamma: 100,
+        }
+    }
+}
+
+impl Levels {
+    fn from_config(config: &ViewerConfig) -> Self {
+        let hundredths = |value: f32| (value * 100.0).round() as i32;
+        Self {
+            brightness: hundredths(config.brightness),
+            contrast: hundredths(config.contrast),
+            gamma: hundredths(config.gamma),
+        }
+    }
+
+    /// What each channel value becomes: gamma first, then contrast around mid-gray,
+    /// then brightness.
+    fn table(self) -> [u8; 256] {
+        let brightness = self.brightness as f32 / 100.0;
+        let contrast = self.contrast as f32 / 100.0;
+        let gamma = (self.gamma as f32 / 100.0).max(0.01);
+        std::array::from_fn(|value| {
+            let value = (value as f32 / 255.0).powf(1.0 / gamma);
+            let value = (value - 0.5) * contrast + 0.5 + brightness;
+            (value * 255.0).round().clamp(0.0, 255.0) as u8
+        })
+    }
+}
+
+impl ColorMode {
+    pub fn from_config(config: &ViewerConfig) -> Self {
+        Self {
+            palette: Palette::from_config(config),
+            grayscale: config.grayscale,
+            eink: config.eink,
+            levels: Levels::from_config(config),
+        }
+    }
+
+    fn apply(self, page: &PdfPage, pixels: &mut [u8], width: u32) {
+        if self.eink {
+            for pixel in pixels.chunks_exact_mut(4) {
+                for channel in &mut pixel[..3] {
+                    let value = (f32::from(*channel) - 128.0) * EINK_CONTRAST + 128.0;
+                    *channel = value.round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+        if self.levels != Levels::default() {
+            let table = self.levels.table();
+            for pixel in pixels.chunks_exact_mut(4) {
+                for channel in &mut pixel[..3] {
+                    *channel = table[*channel as usize];
+                }
+            }
+        }
+        self.palette.apply(page, pixels, width);
+    }
+}
+
+/// The recoloring applied to rendered pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Palette {
+    #[default]
+    Normal,
+    /// Light on dark, for reading at night.
+    Inverted,
+    /// White mapped to `background` and black to `foreground`, like zathura's recolor.
+    /// Colored content keeps its hue and images are left as they are.
+    Recolor {
+        background: [u8; 3],
+        foreground: [u8; 3],
+    },
+    /// Every channel scaled so white becomes `color`, e.g. sepia.
+    Tint([u8; 3]),
+}
+
+impl Palette {
+    fn from_config(config: &ViewerConfig) -> Self {
+        if config.recolor {
+            let [r, g, b, _] = config.recolor_background.0.into_rgba8();
+            let background = [r, g, b];
+            let [r, g, b, _] = config.recolor_foreground.0.into_rgba8();
+            Self::Recolor {
+                background,
+                foreground: [r, g, b],
+            }
+        } else if config.invert {
+            Self::Inverted
+        } else if let Some(tint) = config.tint {
+            let [r, g, b, _] = tint.0.into_rgba8();
+            Self::Tint([r, g, b])
+        } else {
+            Self::Normal
+        }
+    }
+
+    /// Recolors the RGBA `pixels` of `page`, rendered `width` pixels wide, in place.
+    fn apply(self, page: &PdfPage, pixels: &mut [u8], width: u32) {
+        match self {
+            Self::Normal => {}
+            Self::Inverted => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    for channel in &mut pixel[..3] {
+                        *channel = 255 - *channel;
+                    }
+                }
+            }
+            Self::Tint(color) => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    for (channel, tint) in pixel[..3].iter_mut().zip(color) {
+                        *channel = (u16::from(*channel) * u16::from(tint) / 255) as u8;
+                    }
+                }
+            }
+            Self::Recolor {
+                background,
+                foreground,
+            } => {
+                let width = width.max(1) as usize;
+                let height = (pixels.len() / 4 / width) as f32;
+                // Pixel columns and rows covered by each image.
+                let images: Vec<_> = page_images(page)
+                    .iter()
+                    .map(|rect| {
+                        let columns = (rect.x * width as f32) as usize
+                            ..((rect.x + rect.width) * width as f32).ceil() as usize;
+                        let rows = (rect.y * height) as usize
+                            ..((rect.y + rect.height) * height).ceil() as usize;
+                        (columns, rows)
+                    })
+                    .collect();
+                for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+                    let (x, y) = (i % width, i / width);
+                    if !images
+                        .iter()
+                        .any(|(columns, rows)| columns.contains(&x) && rows.contains(&y))
+                    {
+                        recolor(pixel, background, foreground);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Blends pixels that are not fully opaque onto `paper`, leaving every pixel opaque, so
+/// a page with a transparent background looks the same whatever it is drawn over.
+fn composite_onto(image: &mut RgbaImage, paper: [u8; 3]) {
+    for pixel in image.pixels_mut() {
+        let alpha = u32::from(pixel[3]);
+        if alpha == 255 {
+            continue;
+        }
+        for (channel, paper) in pixel.0[..3].iter_mut().zip(paper) {
+            let blended = u32::from(*channel) * alpha + u32::from(paper) * (255 - alpha);
+            *channel = ((blended + 127) / 255) as u8;
+        }
+        pixel[3] = 255;
+    }
+}
+
+/// Maps the lightness of an RGBA pixel from black..white to `foreground..background`,
+/// keeping how far each channel is from gray so colored pixels keep their hue.
+fn recolor(pixel: &mut [u8], background: [u8; 3], foreground: [u8; 3]) {
+    let rgb = [pixel[0], pixel[1], pixel[2]].map(f32::from);
+    let lightness = (0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2]) / 255.0;
+    for i in 0..3 {
+        let (background, foreground) = (f32::from(background[i]), f32::from(foreground[i]));
+        let mapped = foreground + (background - foreground) * lightness;
+        let hue = rgb[i] - lightness * 255.0;
+        pixel[i] = (mapped + hue).round().clamp(0.0, 255.0) as u8;
+    }
+}
+
+pub struct PdfRenderer<'a> {
+    document: PdfDocument<'a>,
+    settings: RenderSettings,
+    highlight_fields: bool,
+}
+
+impl<'a> PdfRenderer<'a> {
+    pub fn new(document: PdfDocument<'a>, settings: RenderSettings) -> Self {
+        Self {
+            document,
+            settings,
+            highlight_fields: false,
+        }
+    }
+
+    pub fn set_highlight_fields(&mut self, highlight: bool) {
+        self.highlight_fields = highlight;
+    }
+
+    pub fn set_settings(&mut self, settings: RenderSettings) {
+        self.settings = settings;
+    }
+
+    pub fn render_page(
+        &self,
+        page_index: usize,
+        target_w: u16,
+        _target_h: u16,
+        color_mode: ColorMode,
+    ) -> Result<RenderResult, String> {
+        let failed = |e: PdfiumError| format!("Page {} failed to render: {}", page_index + 1, e);
+        let page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+
+        let requested_width = if target_w > 0 {
+            target_w.max(MIN_RENDER_WIDTH) as f32
+        } else {
+            2000.0
+        };
+        let aspect_ratio = page.width().value / page.height().value;
+        let (rgba, clamped) = self
+            .render_rgba(&page, requested_width, self.highlight_fields, color_mode)
+            .map_err(failed)?;
+        let width = rgba.width();
+        let height = rgba.height();
+        let mut pixels = rgba.into_raw();
+        color_mode.apply(&page, &mut pixels, width);
+        let layout = PageLayout {
+            aspect_ratio,
+            width: page.width().value,
+            links: page_links(&page),
+            words: page_words(&page),
+            notes: page_notes(&page),
+            fields: page_fields(&page),
+        };
+
+        Ok(RenderResult {
+            page_index,
+            target_width: target_w,
+            color_mode,
+            pixels,
+            width,
+            height,
+            clamped,
+            layout,
+        })
+    }
+
+    /// Renders page `page_index` at `width` pixels (or less, if that exceeds
+    /// `max_render_pixels`) without reading its text or links, for headless use.
+    pub fn render_image(&self, page_index: usize, width: u16) -> Result<(RgbaImage, bool), String> {
+        let failed = |e: PdfiumError| format!("Page {} failed to render: {}", page_index + 1, e);
+        let page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+        self.render_rgba(&page, width.max(1) as f32, false, ColorMode::default())
+            .map_err(failed)
+    }
+
+    /// Renders page `page_index` to fit within `size` pixels in both directions.
+    pub fn render_thumbnail(&self, page_index: usize, size: u16) -> Result<RgbaImage, String> {
+        let failed = |e: PdfiumError| format!("Page {} failed to render: {}", page_index + 1, e);
+        let page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+        let aspect_ratio = page.width().value / page.height().value;
+        let width = size.max(1) as f32 * aspect_ratio.min(1.0);
+        let (image, _) = self
+            .render_rgba(&page, width.round().max(1.0), false, ColorMode::default())
+            .map_err(failed)?;
+        Ok(image)
+    }
+
+    /// Writes page `page_index` as a PNG at `dpi`, or smaller if that would exceed
+    /// `max_render_pixels`.
+    pub fn export_image(&self, page_index: usize, dpi: f32, path: &Path) -> Result<(), String> {
+        let failed = |e: PdfiumError| format!("Page {} failed to render: {}", page_index + 1, e);
+        let page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+        let width = (page.width().value / 72.0 * dpi).round().max(1.0);
+        let (image, _) = self
+            .render_rgba(&page, width, false, ColorMode::default())
+            .map_err(failed)?;
+        image
+            .save(path)
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))
+    }
+
+    /// The rendered page and whether it was scaled down to fit `max_render_pixels`.
+    /// Form fields are tinted only with `highlight_fields`, which is for the viewer, and
+    /// only PDFium's part of `color_mode` is used; the caller recolors the pixels.
+    fn render_rgba(
+        &self,
+        page: &PdfPage,
+        width: f32,
+        highlight_fields: bool,
+        color_mode: ColorMode,
+    ) -> Result<(RgbaImage, bool), PdfiumError> {
+        let aspect_ratio = page.width().value / page.height().value;
+        let (width, clamped) = self.clamp_width(width, aspect_ratio);
+        let [r, g, b, a] = self.settings.page_background.into_rgba8();
+
+        let mut render_config = PdfRenderConfig::new()
+            .set_clear_color(PdfColor::new(r, g, b, a))
+            .rotate_if_landscape(PdfPageRenderRotation::None, true)
+            .set_target_width(width as i32)
+            .render_form_data(true)
+            .use_grayscale_rendering(color_mode.grayscale)
+            .set_text_smoothing(self.settings.antialias_text && !color_mode.eink)
+            .set_path_smoothing(self.settings.antialias_paths && !color_mode.eink)
+            .set_image_smoothing(self.settings.antialias_images)
+            .use_lcd_text_rendering(self.settings.lcd_text)
+            .use_print_quality(self.settings.render_for_printing);
+        if highlight_fields {
+            render_config = render_config.highlight_all_form_fields(FIELD_HIGHLIGHT);
+        }
+
+        let bitmap = page.render_with_config(&render_config)?;
+        let mut image = bitmap.as_image().to_rgba8();
+        composite_onto(&mut image, [r, g, b]);
+        Ok((image, clamped))
+    }
+
+    pub fn add_markup(
+        &mut self,
+        page_index: usize,
+        kind: MarkupKind,
+        rects: &[Rectangle],
+        color: Color,
+        note: &str,
+    ) -> Result<(), String> {
+        let failed = |e: PdfiumError| format!("Could not add {}: {}", kind.name(), e);
+        let mut page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+        let size = page_size(&page);
+        let Some(bounds) = rects.iter().copied().reduce(|a, b| a.union(&b)) else {
+            return Ok(());
+        };
+        let [r, g, b, a] = color.into_rgba8();
+        let color = PdfColor::new(r, g, b, a);
+        let bounds = pdf_rect(&bounds, size);
+        let quads: Vec<PdfRect> = rects.iter().map(|rect| pdf_rect(rect, size)).collect();
+
+        let annotations = page.annotations_mut();
+        match kind {
+            MarkupKind::Highlight => place_markup(
+                annotations.create_highlight_annotation().map_err(failed)?,
+                PdfPageHighlightAnnotation::attachment_points_mut,
+                bounds,
+                &quads,
+                color,
+                note,
+            ),
+            MarkupKind::Underline => place_markup(
+                annotations.create_underline_annotation().map_err(failed)?,
+                PdfPageUnderlineAnnotation::attachment_points_mut,
+                bounds,
+                &quads,
+                color,
+                note,
+            ),
+            MarkupKind::Strikeout => place_markup(
+                annotations.create_strikeout_annotation().map_err(failed)?,
+                PdfPageStrikeoutAnnotation::attachment_points_mut,
+                bounds,
+                &quads,
+                color,
+                note,
+            ),
+            MarkupKind::Squiggly => place_markup(
+                annotations.create_squiggly_annotation().map_err(failed)?,
+                PdfPageSquigglyAnnotation::attachment_points_mut,
+                bounds,
+                &quads,
+                color,
+                note,
+            ),
+        }
+        .map_err(failed)
+    }
+
+    pub fn set_field(
+        &mut self,
+        page_index: usize,
+        annotation_index: usize,
+        value: &FieldValue,
+    ) -> Result<(), String> {
+        let failed = |e: PdfiumError| format!("Could not fill field: {}", e);
+        let page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+        let mut annotation = page.annotations().get(annotation_index).map_err(failed)?;
+        let field = annotation
+            .as_form_field_mut()
+            .ok_or_else(|| "Not a form field".to_string())?;
+        match value {
+            FieldValue::Text(text) => match field.as_text_field_mut() {
+                Some(field) => field.set_value(text),
+                None => return Err("Not a text field".to_string()),
+            },
+            FieldValue::Checked(checked) => {
+                if let Some(field) = field.as_checkbox_field_mut() {
+                    field.set_checked(*checked)
+                } else if let Some(field) = field.as_radio_button_field_mut() {
+                    field.set_checked()
+                } else {
+                    return Err("Not a checkbox or radio button".to_string());
+                }
+            }
+            FieldValue::Choice(option) => return set_choice(&page, annotation_index, option),
+        }
+        .map_err(failed)
+    }
+
+    pub fn add_note(
+        &mut self,
+        page_index: usize,
+        position: Point,
+        text: &str,
+    ) -> Result<(), String> {
+        let failed = |e: PdfiumError| format!("Could not add note: {}", e);
+        let mut page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+        let (page_width, page_height) = page_size(&page);
+        let left = (position.x * page_width).clamp(0.0, page_width - NOTE_ICON_SIZE);
+        let top = (page_height * (1.0 - position.y)).clamp(NOTE_ICON_SIZE, page_height);
+        let bounds =
+            PdfRect::new_from_values(top - NOTE_ICON_SIZE, left, top, left + NOTE_ICON_SIZE);
+        place_note(&mut page, bounds, text, None).map_err(failed)
+    }
+
+    pub fn add_ink(
+        &mut self,
+        page_index: usize,
+        strokes: &[Vec<Point>],
+        color: Color,
+        width: f32,
+    ) -> Result<(), String> {
+        let failed = |e: PdfiumError| format!("Could not add ink: {}", e);
+        let mut page = self
+            .document
+            .pages()
+            .get(page_index as u16)
+            .map_err(failed)?;
+        let (page_width, page_height) = page_size(&page);
+        let to_points = |point: &Point| {
+            (
+                PdfPoints::new(point.x * page_width),
+                PdfPoints::new((1.0 - point.y) * page_height),
+            )
+        };
+        let Some(bounds) = strokes
+            .iter()
+            .flatten()
+            .map(|point| Rectangle::new(*point, Size::ZERO))
+            .reduce(|a, b| a.union(&b))
+        else {
+            return Ok(());
+        };
+        let [r, g, b, a] = color.into_rgba8();
+        let color = PdfColor::new(r, g, b, a);
+
+        let mut annotation = page
+            .annotations_mut()
+            .create_ink_annotation()
+            .map_err(failed)?;
+        for stroke in strokes {
+            let Some((first, rest)) = stroke.split_first() else {
+                continue;
+            };
+            let (x, y) = to_points(first);
+            let mut path = PdfPagePathObject::new(
+                &self.document,
+                x,
+                y,
+                Some(color),
+                Some(PdfPoints::new(width)),
+                None,
+            )
+            .map_err(failed)?;
+            // A single point still needs a segment to show up as a dot.
+            for point in rest.iter().chain(rest.is_empty().then_some(first)) {
+                let (x, y) = to_points(point);
+                path.line_to(x, y).map_err(failed)?;
+            }
+            annotation
+                .objects_mut()
+                .add_path_object(path)
+                .map_err(failed)?;
+        }
+
+        let bounds = pdf_rect(&bounds, (page_width, page_height));
+        let margin = width / 2.0;
+        annotation
+            .set_bounds(PdfRect::new_from_values(
+                bounds.bottom().value - margin,
+                bounds.left().value - margin,
+                bounds.top().value + margin,
+                bounds.right().value + margin,
+            ))
+            .map_err(failed)?;
+        annotation.set_stroke_color(color).map_err(failed)
+    }
+
+    /// Adds `imported` annotations, skipping any with the same type and position as an
+    /// existing one so importing a file twice changes nothing. Returns how many were
+    /// added and skipped.
+    pub fn import_annotations(
+        &mut self,
+        imported: &[ImportedAnnotation],
+    ) -> Result<(usize, usize), String> {
+        let mut existing = self.annotations();
+        let (mut added, mut skipped) = (0, 0);
+
+        for item in imported {
+            let Ok(mut page) = self.document.pages().get(item.page_index as u16) else {
+                skipped += 1;
+                continue;
+            };
+            let size = page_size(&page);
+            let rect = import_rect(&item.rect, item.coordinates, size);
+            let duplicate = existing.iter().any(|annotation| {
+                annotation.page_index == item.page_index
+                    && annotation.kind == item.kind.name()
+                    && same_rect(&annotation.bounds, &rect)
+            });
+            if duplicate {
+                skipped += 1;
+                continue;
+            }
+
+            match item.kind {
+                ImportKind::Markup(kind) => {
+                    let quads: Vec<Rectangle> = if item.quads.is_empty() {
+                        vec![rect]
+                    } else {
+                        item.quads
+                            .iter()
+                            .map(|quad| import_rect(quad, item.coordinates, size))
+                            .collect()
+                    };
+                    drop(page);
+                    let color = item.color.unwrap_or(Color::BLACK);
+                    self.add_markup(item.page_index, kind, &quads, color, &item.note)?;
+                }
+                ImportKind::Note => {
+                    let color = item.color.map(|color| {
+                        let [r, g, b, a] = color.into_rgba8();
+                        PdfColor::new(r, g, b, a)
+                    });
+                    place_note(&mut page, pdf_rect(&rect, size), &item.note, color)
+                        .map_err(|e| format!("Could not add note: {}", e))?;
+                }
+            }
+            // Later entries of the same file are reconciled against this one too.
+            existing.push(DocumentAnnotation {
+                page_index: item.page_index,
+                bounds: rect,
+                quads: Vec::new(),
+                kind: item.kind.name().to_string(),
+                color: item.color,
+                text: String::new(),
+                note: item.note.clone(),
+            });
+            added += 1;
+        }
+        Ok((added, skipped))
+    }
+
+    pub fn annotations(&self) -> Vec<DocumentAnnotation> {
+        document_annotations(&self.document)
+    }
+
+    pub fn pending_fields(&self) -> Vec<PendingField> {
+        pending_fields(&self.document)
+    }
+
+    /// Writes the document to `path` through a temporary file, so the file the
+    /// document was loaded from is never left half-written.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        write_document(&self.document, path)
+    }
+
+    /// Flattens a copy rather than the document itself, which stays editable.
+    pub fn export_flattened(&self, pdfium: &Pdfium, path: &Path) -> Result<(), String> {
+        let failed = |e: PdfiumError| format!("Could not export {}: {}", path.display(), e);
+        let bytes = self.document.save_to_bytes().map_err(failed)?;
+        let copy = pdfium.load_pdf_from_byte_vec(bytes, None).map_err(failed)?;
+        for mut page in copy.pages().iter() {
+            page.flatten().map_err(failed)?;
+        }
+        write_document(&copy, path)
+    }
+
+    pub fn save_attachment(&self, index: usize, path: &Path) -> Result<(), String> {
+        let failed = |e: PdfiumError| format!("Could not write {}: {}", path.display(), e);
+        let bytes = self
+            .document
+            .attachments()
+            .get(index as PdfAttachmentIndex)
+            .map_err(failed)?
+            .save_to_bytes()
+            .map_err(failed)?;
+        write_atomically(&bytes, path)
+    }
+
+    /// Writes an unencrypted copy next to `path`, encrypts it into place and removes it.
+    pub fn save_encrypted(&self, path: &Path, user: &str, owner: &str) -> Result<(), String> {
+        let failed = |e: String| format!("Could not write {}: {}", path.display(), e);
+        let bytes = self
+            .document
+            .save_to_bytes()
+            .map_err(|e| failed(e.to_string()))?;
+        let mut plain = path.as_os_str().to_owned();
+        plain.push(".plain.tmp");
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+
+        fs::write(&plain, bytes).map_err(|e| failed(e.to_string()))?;
+        let encrypted = external::encrypt_pdf(Path::new(&plain), Path::new(&tmp), user, owner);
+        let _ = fs::remove_file(&plain);
+        encrypted.map_err(failed)?;
+        fs::rename(&tmp, path).map_err(|e| failed(e.to_string()))
+    }
+
+    /// Scales `width` down so the bitmap stays within `max_render_pixels`.
+    fn clamp_width(&self, width: f32, aspect_ratio: f32) -> (f32, bool) {
+        let pixels = width * width / aspect_ratio;
+        let max_pixels = self.settings.max_render_pixels as f32;
+        if !pixels.is_finite() || pixels <= max_pixels {
+            return (width, false);
+        }
+        let scale = (max_pixels / pixels).sqrt();
+        ((width * scale).floor().max(1.0), true)
+    }
+}
+
+/// Writes `document` to `path` through a temporary file, so an existing file is never
+/// left half-written.
+pub fn write_document(document: &PdfDocument, path: &Path) -> Result<(), String> {
+    let bytes = document
+        .save_to_bytes()
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    write_atomically(&bytes, path)
+}
+
+/// Writes `bytes` to a temporary file next to `path` and renames it into place.
+fn write_atomically(bytes: &[u8], path: &Path) -> Result<(), String> {
+    let failed = |e: std::io::Error| format!("Could not write {}: {}", path.display(), e);
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, bytes).map_err(failed)?;
+    fs::rename(&tmp, path).map_err(failed)
+}
+
+/// Internal and URI links on `page`, in the order pdfium reports them.
+fn page_links(page: &PdfPage) -> Vec<PageLink> {
+    let size = page_size(page);
+    let text = page.text().ok();
+
+    page.links()
+        .iter()
+        .filter_map(|link| {
+            let target = match link.destination() {
+                Some(destination) => LinkTarget::Page(destination.page_index().ok()? as usize),
+                None => {
+                    let action = link.action()?;
+                    if let Some(uri) = action.as_uri_action() {
+                        LinkTarget::Uri(uri.uri().ok()?)
+                    } else {
+                        let destination = action.as_local_destination_action()?.destination();
+                        LinkTarget::Page(destination.ok()?.page_index().ok()? as usize)
+                    }
+                }
+            };
+            let rect = link.rect().ok()?;
+            Some(PageLink {
+                bounds: relative_rect(&rect, size),
+                text: text
+                    .as_ref()
+                    .map(|text| squeeze_whitespace(&text.inside_rect(rect)))
+                    .unwrap_or_default(),
+                target,
+            })
+        })
+        .collect()
+}
+
+/// Where the images on the page are, relative to it, including those inside form
+/// XObjects.
+fn page_images(page: &PdfPage) -> Vec<Rectangle> {
+    fn collect<'a>(
+        objects: impl Iterator<Item = PdfPageObject<'a>>,
+        size: (f32, f32),
+        images: &mut Vec<Rectangle>,
+    ) {
+        for object in objects {
+            if let Some(form) = object.as_x_object_form_object() {
+                collect(form.iter(), size, images);
+            } else if object.object_type() == PdfPageObjectType::Image
+                && let Ok(bounds) = object.bounds()
+            {
+                images.push(relative_rect(&bounds.to_rect(), size));
+            }
+        }
+    }
+    let mut images = Vec::new();
+    collect(page.objects().iter(), page_size(page), &mut images);
+    images
+}
+
+/// Splits the page text into words on whitespace, with the union of their character
+/// boxes as bounds.
+fn page_words(page: &PdfPage) -> Vec<PageWord> {
+    let Ok(text) = page.text() else {
+        return Vec::new();
+    };
+    let size = page_size(page);
+    let mut words = Vec::new();
+    let mut current: Option<PageWord> = None;
+
+    for char in text.chars().iter() {
+        let (c, rect) = match (char.unicode_char(), char.loose_bounds()) {
+            (Some(c), Ok(rect)) if !c.is_whitespace() => (c, rect),
+            _ => {
+                words.extend(current.take());
+                continue;
+            }
+        };
+        let bounds = relative_rect(&rect, size);
+        match &mut current {
+            Some(word) => {
+                word.text.push(c);
+                word.bounds = word.bounds.union(&bounds);
+            }
+            None => {
+                current = Some(PageWord {
+                    text: c.to_string(),
+                    bounds,
+                })
+            }
+        }
+    }
+    words.extend(current);
+    words
+}
+
+/// The text annotations on `page`, in the order pdfium reports them.
+fn page_notes(page: &PdfPage) -> Vec<PageNote> {
+    let size = page_size(page);
+    page.annotations()
+        .iter()
+        .filter(|annotation| annotation.annotation_type() == PdfPageAnnotationType::Text)
+        .filter_map(|annotation| {
+            Some(PageNote {
+                bounds: relative_rect(&annotation.bounds().ok()?, size),
+                contents: annotation.contents().unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn page_fields(page: &PdfPage) -> Vec<PageField> {
+    let size = page_size(page);
+    page.annotations()
+        .iter()
+        .enumerate()
+        .filter_map(|(annotation_index, annotation)| {
+            let field = annotation.as_form_field()?;
+            if field.is_read_only() {
+                return None;
+            }
+            let mut options = Vec::new();
+            let (kind, value, checked) = if let Some(text) = field.as_text_field() {
+                (FieldKind::Text, text.value().unwrap_or_default(), false)
+            } else if let Some(checkbox) = field.as_checkbox_field() {
+                let checked = checkbox.is_checked().unwrap_or(false);
+                (FieldKind::Checkbox, String::new(), checked)
+            } else if let Some(radio) = field.as_radio_button_field() {
+                let checked = radio.is_checked().unwrap_or(false);
+                (FieldKind::RadioButton, String::new(), checked)
+            } else if let Some(combo) = field.as_combo_box_field() {
+                options = option_labels(combo.options());
+                (FieldKind::Choice, combo.value().unwrap_or_default(), false)
+            } else if let Some(list) = field.as_list_box_field() {
+                options = option_labels(list.options());
+                (FieldKind::Choice, list.value().unwrap_or_default(), false)
+            } else {
+                return None;
+            };
+            Some(PageField {
+                annotation_index,
+                name: field.name().unwrap_or_default(),
+                kind,
+                bounds: relative_rect(&annotation.bounds().ok()?, size),
+                value,
+                checked,
+                options,
+            })
+        })
+        .collect()
+}
+
+fn option_labels(options: &PdfFormFieldOptions) -> Vec<String> {
+    options
+        .iter()
+        .filter_map(|option| option.label().cloned())
+        .collect()
+}
+
+/// Sets the value of the choice field behind annotation `annotation_index` to
+/// `option`, one of its option labels.
+fn set_choice(page: &PdfPage, annotation_index: usize, option: &str) -> Result<(), String> {
+    // pdfium-render has no setter for choice fields; set `V` as its text fields do.
+    let bindings = page.bindings();
+    let annotation =
+        bindings.FPDFPage_GetAnnot(bindings.get_handle_from_page(page), annotation_index as i32);
+    if annotation.is_null() {
+        return Err("Not a form field".to_string());
+    }
+    let set = bindings.is_true(bindings.FPDFAnnot_SetStringValue_str(annotation, "V", option));
+    bindings.FPDFPage_CloseAnnot(annotation);
+    if set {
+        Ok(())
+    } else {
+        Err(format!("Could not choose {}", option))
+    }
+}
+
+/// Signature fields and empty required fields in reading order, listing each field
+/// once however many widgets it has.
+pub fn pending_fields(document: &PdfDocument) -> Vec<PendingField> {
+    let mut chosen_groups = HashSet::new();
+    let mut fields = Vec::new();
+    for (page_index, page) in document.pages().iter().enumerate() {
+        for annotation in page.annotations().iter() {
+            let Some(field) = annotation.as_form_field() else {
+                continue;
+            };
+            let name = field.name().unwrap_or_default();
+            if field.as_signature_field().is_some() {
+                fields.push(PendingField {
+                    page_index,
+                    name,
+                    kind: PendingFieldKind::Signature,
+                });
+                continue;
+            }
+            if let Some(radio) = field.as_radio_button_field()
+                && radio.is_checked().unwrap_or(false)
+            {
+                chosen_groups.insert(name.clone());
+            }
+            if !field.is_required() || field.is_read_only() {
+                continue;
+            }
+            let empty = if let Some(text) = field.as_text_field() {
+                text.value().unwrap_or_default().is_empty()
+            } else if let Some(checkbox) = field.as_checkbox_field() {
+                !checkbox.is_checked().unwrap_or(false)
+            } else if let Some(combo) = field.as_combo_box_field() {
+                combo.value().unwrap_or_default().is_empty()
+            } else if let Some(list) = field.as_list_box_field() {
+                list.value().unwrap_or_default().is_empty()
+            } else {
+                // Radio buttons are decided by their group below.
+                field.as_radio_button_field().is_some()
+            };
+            if empty {
+                fields.push(PendingField {
+                    page_index,
+                    name,
+                    kind: PendingFieldKind::Required,
+                });
+            }
+        }
+    }
+
+    // A radio group is filled in once any of its buttons is chosen, wherever it is.
+    let mut listed = HashSet::new();
+    fields.retain(|field| {
+        field.kind == PendingFieldKind::Signature
+            || (!chosen_groups.contains(&field.name) && listed.insert(field.name.clone()))
+    });
+    fields
+}
+
+/// Annotations in reading order, leaving out links, form widgets and popups, which are
+/// not review marks.
+pub fn document_annotations(document: &PdfDocument) -> Vec<DocumentAnnotation> {
+    let mut annotations = Vec::new();
+    for (page_index, page) in document.pages().iter().enumerate() {
+        let size = page_size(&page);
+        let text = page.text().ok();
+        let first = annotations.len();
+        for annotation in page.annotations().iter() {
+            let kind = annotation.annotation_type();
+            if matches!(
+                kind,
+                PdfPageAnnotationType::Link
+                    | PdfPageAnnotationType::Widget
+                    | PdfPageAnnotationType::Popup
+            ) {
+                continue;
+            }
+            let Ok(bounds) = annotation.bounds() else {
+                continue;
+            };
+            let is_markup = matches!(
+                kind,
+                PdfPageAnnotationType::Highlight
+                    | PdfPageAnnotationType::Underline
+                    | PdfPageAnnotationType::Strikeout
+                    | PdfPageAnnotationType::Squiggly
+            );
+            let quads: Vec<PdfRect> = if is_markup {
+                annotation
+                    .attachment_points()
+                    .iter()
+                    .map(|quad| quad.to_rect())
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let covered = match &text {
+                Some(text) => quads
+                    .iter()
+                    .map(|quad| text.inside_rect(*quad))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                None => String::new(),
+            };
+            annotations.push(DocumentAnnotation {
+                page_index,
+                bounds: relative_rect(&bounds, size),
+                quads: quads.iter().map(|quad| relative_rect(quad, size)).collect(),
+                kind: format!("{:?}", kind).to_lowercase(),
+                color: annotation.stroke_color().ok().map(|color| {
+                    Color::from_rgba8(
+                        color.red(),
+                        color.green(),
+                        color.blue(),
+                        color.alpha() as f32 / 255.0,
+                    )
+                }),
+                text: squeeze_whitespace(&covered),
+                note: squeeze_whitespace(&annotation.contents().unwrap_or_default()),
+            });
+        }
+        annotations[first..].sort_by(|a, b| {
+            (a.bounds.y, a.bounds.x)
+                .partial_cmp(&(b.bounds.y, b.bounds.x))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+    annotations
+}
+
+fn squeeze_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Positions and colors a new markup annotation; each line of text gets its own quad.
+fn place_markup<'a, A: PdfPageAnnotationCommon>(
+    mut annotation: A,
+    attachment_points: fn(&mut A) -> &mut PdfPageAnnotationAttachmentPoints<'a>,
+    bounds: PdfRect,
+    quads: &[PdfRect],
+    color: PdfColor,
+    note: &str,
+) -> Result<(), PdfiumError> {
+    annotation.set_bounds(bounds)?;
+    annotation.set_stroke_color(color)?;
+    if !note.is_empty() {
+        annotation.set_contents(note)?;
+    }
+    for quad in quads {
+        attachment_points(&mut annotation)
+            .create_attachment_point_at_end(PdfQuadPoints::from_rect(quad))?;
+    }
+    Ok(())
+}
+
+fn place_note(
+    page: &mut PdfPage,
+    bounds: PdfRect,
+    text: &str,
+    color: Option<PdfColor>,
+) -> Result<(), PdfiumError> {
+    let mut annotation = page.annotations_mut().create_text_annotation(text)?;
+    annotation.set_bounds(bounds)?;
+    if let Some(color) = color {
+        annotation.set_stroke_color(color)?;
+    }
+    Ok(())
+}
+
+/// An imported rectangle relative to the page, like `DocumentAnnotation::bounds`.
+fn import_rect(rect: &Rectangle, coordinates: Coordinates, size: (f32, f32)) -> Rectangle {
+    match coordinates {
+        Coordinates::Relative => *rect,
+        Coordinates::Points => relative_rect(
+            &PdfRect::new_from_values(rect.y, rect.x, rect.y + rect.height, rect.x + rect.width),
+            size,
+        ),
+    }
+}
+
+fn same_rect(a: &Rectangle, b: &Rectangle) -> bool {
+    (a.x - b.x).abs() < IMPORT_TOLERANCE
+        && (a.y - b.y).abs() < IMPORT_TOLERANCE
+        && (a.width - b.width).abs() < IMPORT_TOLERANCE
+        && (a.height - b.height).abs() < IMPORT_TOLERANCE
+}
+
+fn page_size(page: &PdfPage) -> (f32, f32) {
+    (page.width().value, page.height().value)
+}
+
+/// Converts a rectangle in page points to one relative to the page with the origin
+/// top-left.
+fn relative_rect(rect: &PdfRect, (page_width, page_height): (f32, f32)) -> Rectangle {
+    Rectangle {
+        x: rect.left().value / page_width,
+        y: (page_height - rect.top().value) / page_height,
+        width: rect.width().value / page_width,
+        height: rect.height().value / page_height,
+    }
+}
+
+/// The inverse of `relative_rect`.
+fn pdf_rect(rect: &Rectangle, (page_width, page_height): (f32, f32)) -> PdfRect {
+    PdfRect::new_from_values(
+        page_height * (1.0 - rect.y - rect.height),
+        page_width * rect.x,
+        page_height * (1.0 - rect.y),
+        page_width * (rect.x + rect.width),
+    )
+}
+
+/// Resolves a named destination such as `section.3` to a page index.
+pub fn resolve_named_destination(document: &PdfDocument, name: &str) -> Result<usize, String> {
+    // pdfium-render has no wrapper for named destinations, so use the raw bindings.
+    let bindings = document.bindings();
+    let handle = bindings.get_handle_from_document(document);
+    let destination = bindings.FPDF_GetNamedDestByName(handle, name);
+    let page_index = if destination.is_null() {
+        -1
+    } else {
+        bindings.FPDFDest_GetDestPageIndex(handle, destination)
+    };
+
+    usize::try_from(page_index).map_err(|_| format!("No such destination: {}", name))
+}
+
+pub fn read_info(document: &PdfDocument) -> DocumentInfo {
+    let fields = [
+        ("Title", PdfDocumentMetadataTagType::Title),
+        ("Author", PdfDocumentMetadataTagType::Author),
+        ("Subject", PdfDocumentMetadataTagType::Subject),
+        ("Keywords", PdfDocumentMetadataTagType::Keywords),
+        ("Creator", PdfDocumentMetadataTagType::Creator),
+        ("Producer", PdfDocumentMetadataTagType::Producer),
+    ];
+    let metadata = fields
+        .into_iter()
+        .filter_map(|(label, tag)| {
+            let value = document.metadata().get(tag)?.value().trim().to_string();
+            (!value.is_empty()).then_some((label, value))
+        })
+        .collect();
+
+    let permissions = document.permissions();
+    // An unreadable flag is treated as granted, like an unprotected file.
+    let allowed = |flag: Result<bool, PdfiumError>| flag.unwrap_or(true);
+    DocumentInfo {
+        metadata,
+        // Revisions 5 and 6 (AES-256) are unknown to the binding, but still encrypted.
+        encrypted: !matches!(
+            permissions.security_handler_revision(),
+            Ok(PdfSecurityHandlerRevision::Unprotected)
+        ),
+        permissions: Permissions {
+            print: allowed(permissions.can_print_high_quality())
+                || allowed(permissions.can_print_only_low_quality()),
+            copy: allowed(permissions.can_extract_text_and_graphics()),
+            modify: allowed(permissions.can_modify_document_content()),
+            annotate: allowed(permissions.can_add_or_modify_text_annotations()),
+            fill_forms: allowed(permissions.can_fill_existing_interactive_form_fields()),
+        },
+    }
+}
+
+pub fn read_attachments(document: &PdfDocument) -> Vec<Attachment> {
+    document
+        .attachments()
+        .iter()
+        .map(|attachment| Attachment {
+            name: attachment.name(),
+            size: attachment.len(),
+        })
+        .collect()
+}
+
+/// The page labels of `document` (`"iv"`, `"A-3"`, ...), or an empty list when it
+/// has none or they just repeat the physical page numbers.
+pub fn read_page_labels(document: &PdfDocument) -> Vec<String> {
+    let bindings = document.bindings();
+    let handle = bindings.get_handle_from_document(document);
+
+    let labels: Vec<String> = (0..document.pages().len() as i32)
+        .map(|page_index| {
+            let len = bindings.FPDF_GetPageLabel(handle, page_index, std::ptr::null_mut(), 0);
+            if len <= 2 {
+                return String::new();
+            }
+            let mut buffer = vec![0u8; len as usize];
+            bindings.FPDF_GetPageLabel(handle, page_index, buffer.as_mut_ptr().cast(), len);
+            let units: Vec<u16> = buffer
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .take_while(|&unit| unit != 0)
+                .collect();
+            String::from_utf16_lossy(&units)
+        })
+        .collect();
+
+    let trivial = labels
+        .iter()
+        .enumerate()
+        .all(|(i, label)| label.is_empty() || *label == (i + 1).to_string());
+    if trivial {
+        Vec::new()
+    } else {
+        labels
+    }
+}
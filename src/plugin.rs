@@ -0,0 +1,181 @@
+//! External plugins: executables that exchange newline-delimited JSON with lukia over stdio.
+//!
+//! lukia writes `hello`, `event` and `command` messages to the plugin's stdin; the plugin
+//! answers on stdout with `register`, `goto_page`, `open` and `message` requests. Page numbers
+//! are 1-based on the wire.
+
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PluginConfig {
+    pub name: String,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HostMessage<'a> {
+    Hello { version: u32 },
+    Event { event: PluginEvent<'a> },
+    Command { name: &'a str, args: &'a str },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "name", rename_all = "snake_case")]
+pub enum PluginEvent<'a> {
+    DocumentOpened { file: &'a str, total_pages: usize },
+    PageChanged { page: usize },
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PluginRequest {
+    Register { commands: Vec<String> },
+    GotoPage { page: usize },
+    Open { file: String, page: Option<usize> },
+    Message { text: String },
+}
+
+struct PluginProcess {
+    name: String,
+    commands: Vec<String>,
+    stdin_tx: mpsc::Sender<String>,
+    child: Child,
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+pub struct PluginHost {
+    plugins: Vec<PluginProcess>,
+    request_tx: mpsc::Sender<(usize, Result<PluginRequest, String>)>,
+    request_rx: mpsc::Receiver<(usize, Result<PluginRequest, String>)>,
+}
+
+impl PluginHost {
+    /// Spawns every configured plugin; failures are returned alongside the host.
+    pub fn start(configs: &[PluginConfig]) -> (Self, Vec<String>) {
+        let (request_tx, request_rx) = mpsc::channel();
+        let mut host = Self {
+            plugins: Vec::new(),
+            request_tx,
+            request_rx,
+        };
+        let errors = configs
+            .iter()
+            .filter_map(|config| host.spawn(config).err())
+            .collect();
+        (host, errors)
+    }
+
+    fn spawn(&mut self, config: &PluginConfig) -> Result<(), String> {
+        let mut child = Command::new(&config.command)
+            .args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| format!("Plugin {}: failed to start: {}", config.name, e))?;
+
+        let id = self.plugins.len();
+        let mut stdin = child.stdin.take().expect("piped stdin");
+        let stdout = child.stdout.take().expect("piped stdout");
+
+        let (stdin_tx, stdin_rx) = mpsc::channel::<String>();
+        thread::spawn(move || {
+            while let Ok(line) = stdin_rx.recv() {
+                if writeln!(stdin, "{}", line)
+                    .and_then(|_| stdin.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        let request_tx = self.request_tx.clone();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let request = serde_json::from_str(&line).map_err(|e| e.to_string());
+                if request_tx.send((id, request)).is_err() {
+                    break;
+                }
+            }
+        });
+
+        self.plugins.push(PluginProcess {
+            name: config.name.clone(),
+            commands: Vec::new(),
+            stdin_tx,
+            child,
+        });
+        self.send_to(
+            id,
+            &HostMessage::Hello {
+                version: PROTOCOL_VERSION,
+            },
+        );
+        Ok(())
+    }
+
+    fn send_to(&self, id: usize, message: &HostMessage) {
+        if let (Some(plugin), Ok(line)) = (self.plugins.get(id), serde_json::to_string(message)) {
+            let _ = plugin.stdin_tx.send(line);
+        }
+    }
+
+    pub fn broadcast(&self, event: PluginEvent) {
+        let message = HostMessage::Event { event };
+        for id in 0..self.plugins.len() {
+            self.send_to(id, &message);
+        }
+    }
+
+    /// Forwards `:name args` to the plugin that registered `name`; false if none did.
+    pub fn run_command(&self, name: &str, args: &str) -> bool {
+        let Some(id) = self
+            .plugins
+            .iter()
+            .position(|p| p.commands.iter().any(|c| c == name))
+        else {
+            return false;
+        };
+        self.send_to(id, &HostMessage::Command { name, args });
+        true
+    }
+
+    /// Drains pending plugin requests. `register` is handled here; the rest go to the caller.
+    pub fn poll(&mut self) -> Vec<Result<PluginRequest, String>> {
+        let mut requests = Vec::new();
+        while let Ok((id, request)) = self.request_rx.try_recv() {
+            let name = self.plugins[id].name.clone();
+            match request {
+                Ok(PluginRequest::Register { commands }) => {
+                    self.plugins[id].commands.extend(commands);
+                }
+                Ok(request) => requests.push(Ok(request)),
+                Err(e) => requests.push(Err(format!("Plugin {}: bad message: {}", name, e))),
+            }
+        }
+        requests
+    }
+}
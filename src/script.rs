@@ -0,0 +1,308 @@
+//! User scripts in Rhai, run when lukia is built with the `scripting` feature.
+//!
+//! The script named by the `script` config key may define the hooks `on_open(file, pages)`
+//! and `on_page_change(page)`, and adds commands and keys with
+//! `register_command("name", |args| ...)` and `bind_key("<C-x>", || ...)`. It reads the
+//! document with `file()`, `page()` and `pages()`, and acts through `goto_page(n)`,
+//! `open(file)`, `command(line)` and `message(text)` (as does `print`). Page numbers are
+//! 1-based. Scripts cannot touch files or start processes: `command` refuses `:w`,
+//! `:export`, `:mksession` and the other commands that write files, and commands
+//! provided by plugins.
+
+/// What a script asked for.
+#[derive(Debug, Clone)]
+pub enum ScriptAction {
+    /// 0-based.
+    GotoPage(usize),
+    Open(String),
+    /// A `:` command line.
+    Command(String),
+    Message(String),
+}
+
+/// The shown document, as scripts see it.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptView {
+    pub file: String,
+    /// 0-based.
+    pub page: usize,
+    pub pages: usize,
+}
+
+#[cfg(not(feature = "scripting"))]
+pub use disabled::ScriptHost;
+#[cfg(feature = "scripting")]
+pub use rhai_host::ScriptHost;
+
+#[cfg(feature = "scripting")]
+mod rhai_host {
+    use super::{ScriptAction, ScriptView};
+    use crate::keymap::KeyChord;
+    use iced::keyboard::{Key, Modifiers};
+    use rhai::{Dynamic, Engine, EvalAltResult, FnPtr, Position, AST};
+    use std::collections::HashMap;
+    use std::path::Path;
+    use std::sync::{Arc, Mutex};
+
+    /// Operations a single hook, command or key may take, so a runaway loop cannot
+    /// hang the viewer.
+    const MAX_OPERATIONS: u64 = 10_000_000;
+
+    #[derive(Default)]
+    struct Shared {
+        view: ScriptView,
+        actions: Vec<Result<ScriptAction, String>>,
+        commands: HashMap<String, FnPtr>,
+        keys: HashMap<KeyChord, FnPtr>,
+    }
+
+    struct Script {
+        name: String,
+        engine: Engine,
+        ast: AST,
+        shared: Arc<Mutex<Shared>>,
+    }
+
+    #[derive(Default)]
+    pub struct ScriptHost {
+        script: Option<Script>,
+    }
+
+    impl ScriptHost {
+        /// Runs the script at `path`, if any, letting it register commands and keys.
+        /// A script that fails to load is reported and left out.
+        pub fn start(path: Option<&Path>) -> (Self, Option<String>) {
+            let Some(path) = path else {
+                return (Self::default(), None);
+            };
+            let name = path.display().to_string();
+            let shared = Arc::new(Mutex::new(Shared::default()));
+            let engine = engine(&shared);
+            let loaded = engine.compile_file(path.to_path_buf()).and_then(|ast| {
+                engine.run_ast(&ast)?;
+                Ok(ast)
+            });
+            let mut host = Self::default();
+            match loaded {
+                Ok(ast) => {
+                    host.script = Some(Script {
+                        name,
+                        engine,
+                        ast,
+                        shared,
+                    });
+                    (host, None)
+                }
+                Err(e) => (host, Some(format!("Script {}: {}", name, e))),
+            }
+        }
+
+        pub fn document_opened(&mut self, view: ScriptView) {
+            let args = (view.file.clone(), view.pages as i64);
+            self.call_hook(view, "on_open", 2, args);
+        }
+
+        pub fn page_changed(&mut self, view: ScriptView) {
+            let args = (view.page as i64 + 1,);
+            self.call_hook(view, "on_page_change", 1, args);
+        }
+
+        /// Runs `:name args` if the script registered `name`; false if it did not.
+        pub fn run_command(&mut self, view: ScriptView, name: &str, args: &str) -> bool {
+            let function = self.shared(|shared| shared.commands.get(name).cloned());
+            let Some(function) = function.flatten() else {
+                return false;
+            };
+            self.call(view, |script| {
+                function.call::<Dynamic>(&script.engine, &script.ast, (args.to_string(),))
+            });
+            true
+        }
+
+        /// Runs the function bound to the key, if the script bound one.
+        pub fn run_key(&mut self, view: ScriptView, key: &Key, modifiers: Modifiers) -> bool {
+            let Some(chord) = KeyChord::from_event(key, modifiers) else {
+                return false;
+            };
+            let function = self.shared(|shared| shared.keys.get(&chord).cloned());
+            let Some(function) = function.flatten() else {
+                return false;
+            };
+            self.call(view, |script| {
+                function.call::<Dynamic>(&script.engine, &script.ast, ())
+            });
+            true
+        }
+
+        /// Drains what the script asked for since the last call, and its errors.
+        pub fn poll(&mut self) -> Vec<Result<ScriptAction, String>> {
+            self.shared(|shared| std::mem::take(&mut shared.actions))
+                .unwrap_or_default()
+        }
+
+        fn shared<T>(&self, f: impl FnOnce(&mut Shared) -> T) -> Option<T> {
+            let script = self.script.as_ref()?;
+            let mut shared = script.shared.lock().ok()?;
+            Some(f(&mut shared))
+        }
+
+        /// Calls `hook` if the script defines it with `arity` parameters.
+        fn call_hook(
+            &mut self,
+            view: ScriptView,
+            hook: &str,
+            arity: usize,
+            args: impl rhai::FuncArgs,
+        ) {
+            let Some(script) = &self.script else {
+                return;
+            };
+            if !script
+                .ast
+                .iter_functions()
+                .any(|function| function.name == hook && function.params.len() == arity)
+            {
+                return;
+            }
+            self.call(view, |script| {
+                let options = rhai::CallFnOptions::new().eval_ast(false);
+                script.engine.call_fn_with_options::<Dynamic>(
+                    options,
+                    &mut rhai::Scope::new(),
+                    &script.ast,
+                    hook,
+                    args,
+                )
+            });
+        }
+
+        /// Runs `f` with the script seeing `view`, keeping its error for `poll`.
+        fn call(
+            &mut self,
+            view: ScriptView,
+            f: impl FnOnce(&Script) -> Result<Dynamic, Box<EvalAltResult>>,
+        ) {
+            let Some(script) = &self.script else {
+                return;
+            };
+            if let Ok(mut shared) = script.shared.lock() {
+                shared.view = view;
+            }
+            if let Err(e) = f(script)
+                && let Ok(mut shared) = script.shared.lock()
+            {
+                shared
+                    .actions
+                    .push(Err(format!("Script {}: {}", script.name, e)));
+            }
+        }
+    }
+
+    /// An engine with the viewer API registered, recording into `shared`.
+    fn engine(shared: &Arc<Mutex<Shared>>) -> Engine {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let act = |shared: &Arc<Mutex<Shared>>| {
+            let shared = shared.clone();
+            move |action: ScriptAction| {
+                if let Ok(mut shared) = shared.lock() {
+                    shared.actions.push(Ok(action));
+                }
+            }
+        };
+        let push = act(shared);
+        engine.on_print(move |text| push(ScriptAction::Message(text.to_string())));
+        let push = act(shared);
+        engine.register_fn("message", move |text: &str| {
+            push(ScriptAction::Message(text.to_string()))
+        });
+        let push = act(shared);
+        engine.register_fn("goto_page", move |page: i64| {
+            push(ScriptAction::GotoPage(page.max(1) as usize - 1))
+        });
+        let push = act(shared);
+        engine.register_fn("open", move |file: &str| {
+            push(ScriptAction::Open(file.to_string()))
+        });
+        let push = act(shared);
+        engine.register_fn("command", move |line: &str| {
+            push(ScriptAction::Command(line.to_string()))
+        });
+
+        let view = |shared: &Arc<Mutex<Shared>>| {
+            let shared = shared.clone();
+            move || {
+                shared
+                    .lock()
+                    .map(|shared| shared.view.clone())
+                    .unwrap_or_default()
+            }
+        };
+        let read = view(shared);
+        engine.register_fn("file", move || read().file);
+        let read = view(shared);
+        engine.register_fn("page", move || read().page as i64 + 1);
+        let read = view(shared);
+        engine.register_fn("pages", move || read().pages as i64);
+
+        let commands = shared.clone();
+        engine.register_fn("register_command", move |name: &str, function: FnPtr| {
+            if let Ok(mut shared) = commands.lock() {
+                shared.commands.insert(name.to_string(), function);
+            }
+        });
+        let keys = shared.clone();
+        engine.register_fn(
+            "bind_key",
+            move |key: &str, function: FnPtr| -> Result<(), Box<EvalAltResult>> {
+                let chord = KeyChord::parse(key)
+                    .map_err(|e| EvalAltResult::ErrorRuntime(e.into(), Position::NONE))?;
+                if let Ok(mut shared) = keys.lock() {
+                    shared.keys.insert(chord, function);
+                }
+                Ok(())
+            },
+        );
+        engine
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+mod disabled {
+    use super::{ScriptAction, ScriptView};
+    use iced::keyboard::{Key, Modifiers};
+    use std::path::Path;
+
+    /// Stands in for the script host when lukia is built without the `scripting` feature.
+    #[derive(Default)]
+    pub struct ScriptHost;
+
+    impl ScriptHost {
+        pub fn start(path: Option<&Path>) -> (Self, Option<String>) {
+            let error = path.map(|path| {
+                format!(
+                    "Script {} not run: lukia was built without the scripting feature",
+                    path.display()
+                )
+            });
+            (Self, error)
+        }
+
+        pub fn document_opened(&mut self, _view: ScriptView) {}
+
+        pub fn page_changed(&mut self, _view: ScriptView) {}
+
+        pub fn run_command(&mut self, _view: ScriptView, _name: &str, _args: &str) -> bool {
+            false
+        }
+
+        pub fn run_key(&mut self, _view: ScriptView, _key: &Key, _modifiers: Modifiers) -> bool {
+            false
+        }
+
+        pub fn poll(&mut self) -> Vec<Result<ScriptAction, String>> {
+            Vec::new()
+        }
+    }
+}
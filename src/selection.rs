@@ -0,0 +1,104 @@
+use iced::Rectangle;
+
+use crate::pdf::PageWord;
+
+/// Visual mode: the words from `anchor` to `cursor` on the current page, inclusive.
+pub struct Selection {
+    pub anchor: usize,
+    pub cursor: usize,
+}
+
+impl Selection {
+    pub fn new(cursor: usize) -> Self {
+        Self {
+            anchor: cursor,
+            cursor,
+        }
+    }
+
+    /// First and last selected word, in reading order.
+    pub fn range(&self) -> (usize, usize) {
+        (self.anchor.min(self.cursor), self.anchor.max(self.cursor))
+    }
+
+    pub fn swap_ends(&mut self) {
+        std::mem::swap(&mut self.anchor, &mut self.cursor);
+    }
+
+    pub fn next_word(&mut self, words: &[PageWord]) {
+        if self.cursor + 1 < words.len() {
+            self.cursor += 1;
+        }
+    }
+
+    pub fn previous_word(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    /// Moves to the word on the next line closest to the cursor horizontally.
+    pub fn next_line(&mut self, words: &[PageWord]) {
+        let current = words[self.cursor].bounds;
+        if let Some(start) =
+            (self.cursor + 1..words.len()).find(|&i| !same_line(&current, &words[i].bounds))
+        {
+            let line = (start..words.len())
+                .take_while(|&i| same_line(&words[start].bounds, &words[i].bounds));
+            self.cursor = closest(words, line, &current).unwrap_or(start);
+        }
+    }
+
+    /// Moves to the word on the previous line closest to the cursor horizontally.
+    pub fn previous_line(&mut self, words: &[PageWord]) {
+        let current = words[self.cursor].bounds;
+        if let Some(end) = (0..self.cursor)
+            .rev()
+            .find(|&i| !same_line(&current, &words[i].bounds))
+        {
+            let line = (0..=end)
+                .rev()
+                .take_while(|&i| same_line(&words[end].bounds, &words[i].bounds));
+            self.cursor = closest(words, line, &current).unwrap_or(end);
+        }
+    }
+
+    pub fn text(&self, words: &[PageWord]) -> String {
+        let (first, last) = self.range();
+        words[first..=last]
+            .iter()
+            .map(|word| word.text.as_str())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// One rectangle per line of the selection, relative to the page.
+    pub fn line_rects(&self, words: &[PageWord]) -> Vec<Rectangle> {
+        let (first, last) = self.range();
+        let mut rects: Vec<Rectangle> = Vec::new();
+        for word in &words[first..=last] {
+            match rects.last_mut() {
+                Some(line) if same_line(line, &word.bounds) => *line = line.union(&word.bounds),
+                _ => rects.push(word.bounds),
+            }
+        }
+        rects
+    }
+}
+
+/// Whether the vertical centre of `b` falls within `a`.
+fn same_line(a: &Rectangle, b: &Rectangle) -> bool {
+    let centre = b.y + b.height / 2.0;
+    centre >= a.y && centre <= a.y + a.height
+}
+
+fn closest(
+    words: &[PageWord],
+    candidates: impl Iterator<Item = usize>,
+    target: &Rectangle,
+) -> Option<usize> {
+    let centre = target.center_x();
+    candidates.min_by(|&a, &b| {
+        let a = (words[a].bounds.center_x() - centre).abs();
+        let b = (words[b].bounds.center_x() - centre).abs();
+        a.total_cmp(&b)
+    })
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::state::WindowGeometry;
+
+/// Written by `:mksession` when no file is given.
+pub const DEFAULT_FILE: &str = "session.toml";
+
+/// The open documents of a window, written by `:mksession` and restored with `-S`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Session {
+    /// Index into `buffers` of the document shown.
+    pub current: usize,
+    /// Open documents in buffer order.
+    pub buffers: Vec<SessionBuffer>,
+    /// The other viewport of a split view.
+    pub split: Option<SessionSplit>,
+    pub window: Option<WindowGeometry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionBuffer {
+    pub file: PathBuf,
+    /// 1-based; documents not yet shown reopen where they were last closed.
+    pub page: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SessionSplit {
+    pub file: PathBuf,
+    /// 1-based.
+    pub page: usize,
+    /// Whether this viewport is the left one.
+    pub left: bool,
+}
+
+impl Session {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read session {}: {}", path.display(), e))?;
+        let session: Self = toml::from_str(&contents)
+            .map_err(|e| format!("Error in session {}:\n{}", path.display(), e))?;
+        if session.current >= session.buffers.len() {
+            return Err(format!(
+                "Session {} has no buffer {}",
+                path.display(),
+                session.current
+            )
+            .into());
+        }
+        Ok(session)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
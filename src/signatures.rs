@@ -0,0 +1,332 @@
+use crate::active;
+use pdfium_render::prelude::*;
+
+/// OID 2.5.4.3 (common name), as it appears DER-encoded in a certificate.
+const COMMON_NAME_OID: &[u8] = &[0x06, 0x03, 0x55, 0x04, 0x03];
+
+/// A digital signature of the document. The signature itself is not verified; only
+/// whether bytes were added after the signed revision is checked.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    pub signer: Option<String>,
+    pub time: Option<String>,
+    pub reason: Option<String>,
+    /// `None` when the signed byte range could not be found in the file.
+    pub modified_after: Option<bool>,
+}
+
+impl Signature {
+    pub fn summary(&self) -> String {
+        let mut parts = vec![self
+            .signer
+            .as_deref()
+            .unwrap_or("Unknown signer")
+            .to_string()];
+        parts.extend(self.time.clone());
+        parts.push(
+            match self.modified_after {
+                Some(false) => "unchanged since signing",
+                Some(true) => "modified after signing",
+                None => "signed range not found",
+            }
+            .to_string(),
+        );
+        parts.extend(self.reason.clone());
+        parts.join("  ·  ")
+    }
+}
+
+/// Reads the signatures of `document`, matching each to its `/ByteRange` in `file`,
+/// the document's raw bytes, to tell whether later revisions were appended.
+pub fn read_signatures(document: &PdfDocument, file: &[u8]) -> Vec<Signature> {
+    let ranges = byte_ranges(file);
+
+    document
+        .signatures()
+        .iter()
+        .map(|signature| {
+            let contents = signature.bytes();
+            let modified_after = ranges
+                .iter()
+                .find(|range| signed_contents(file, range).is_some_and(|c| c == trim(&contents)))
+                .map(|range| range[2] + range[3] < file.len());
+            Signature {
+                signer: signer_name(&contents),
+                time: signature.signing_date().map(|date| format_date(&date)),
+                reason: signature.reason().filter(|reason| !reason.is_empty()),
+                modified_after,
+            }
+        })
+        .collect()
+}
+
+/// Partial names (`/T`) of the signature fields that have no value, i.e. are not
+/// signed. pdfium does not expose signature field values, so the field dictionaries
+/// are read from the raw file and its object streams.
+pub fn unsigned_fields(file: &[u8]) -> Vec<String> {
+    let streams = active::object_streams(file);
+    let mut names = Vec::new();
+    for bytes in std::iter::once(file).chain(streams.iter().map(Vec::as_slice)) {
+        for end in active::names(bytes, b"/Sig") {
+            let start = end - b"/Sig".len();
+            if !bytes[..start].trim_ascii_end().ends_with(b"/FT") {
+                continue;
+            }
+            let Some(dictionary) = dictionary_start(bytes, start) else {
+                continue;
+            };
+            let keys = dictionary_keys(bytes, dictionary);
+            if keys.iter().any(|&(key, _)| key == b"/V") {
+                continue;
+            }
+            if let Some(&(_, value)) = keys.iter().find(|&&(key, _)| key == b"/T")
+                && let Some(name) = text_string(&bytes[value..])
+            {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// The offset just after the `<<` opening the dictionary that contains `inside`.
+fn dictionary_start(bytes: &[u8], inside: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut end = inside;
+    while end >= 2 {
+        match &bytes[end - 2..end] {
+            b"<<" if depth == 0 => return Some(end),
+            b"<<" => {
+                depth -= 1;
+                end -= 2;
+            }
+            b">>" => {
+                depth += 1;
+                end -= 2;
+            }
+            _ => end -= 1,
+        }
+    }
+    None
+}
+
+/// The names in the dictionary whose contents start at `start`, each with the offset
+/// just after it, leaving out those in nested dictionaries. Keys and name values are
+/// not told apart, which does not matter for looking keys up.
+fn dictionary_keys(bytes: &[u8], start: usize) -> Vec<(&[u8], usize)> {
+    let mut keys = Vec::new();
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match &bytes[i..] {
+            [b'<', b'<', ..] => {
+                depth += 1;
+                i += 2;
+            }
+            [b'>', b'>', ..] if depth == 0 => break,
+            [b'>', b'>', ..] => {
+                depth -= 1;
+                i += 2;
+            }
+            // Strings may contain anything, including `>>` and `/`.
+            [b'(', ..] => i += literal_string(&bytes[i..]).map_or(1, |(_, length)| length),
+            [b'<', ..] => i += find(&bytes[i..], b">").map_or(1, |end| end + 1),
+            [b'/', ..] => {
+                let end = bytes[i + 1..]
+                    .iter()
+                    .position(|&b| b.is_ascii_whitespace() || b"()<>[]{}/%".contains(&b))
+                    .map_or(bytes.len(), |length| i + 1 + length);
+                if depth == 0 {
+                    keys.push((&bytes[i..end], end));
+                }
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+    keys
+}
+
+/// The PDF text string at the start of `bytes`, literal or hex.
+fn text_string(bytes: &[u8]) -> Option<String> {
+    let bytes = bytes.trim_ascii_start();
+    let raw = match bytes.first()? {
+        b'(' => literal_string(bytes)?.0,
+        b'<' => {
+            let end = find(bytes, b">")?;
+            let hex: Vec<u8> = bytes[1..end]
+                .iter()
+                .copied()
+                .filter(u8::is_ascii_hexdigit)
+                .collect();
+            hex.chunks(2)
+                .map(|pair| {
+                    let pair = [pair[0], *pair.get(1).unwrap_or(&b'0')];
+                    u8::from_str_radix(std::str::from_utf8(&pair).ok()?, 16).ok()
+                })
+                .collect::<Option<_>>()?
+        }
+        _ => return None,
+    };
+    Some(match raw.strip_prefix(&[0xfe, 0xff]) {
+        Some(utf16) => String::from_utf16_lossy(
+            &utf16
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        ),
+        // PDFDocEncoding matches Latin-1 for the characters field names use.
+        None => raw.iter().map(|&b| b as char).collect(),
+    })
+}
+
+/// The bytes of the literal string `(...)` at the start of `bytes` with escapes
+/// resolved, and its length in the file.
+fn literal_string(bytes: &[u8]) -> Option<(Vec<u8>, usize)> {
+    let mut value = Vec::new();
+    let mut depth = 0;
+    let mut i = 1;
+    loop {
+        let b = *bytes.get(i)?;
+        i += 1;
+        match b {
+            b'(' => depth += 1,
+            b')' if depth == 0 => return Some((value, i)),
+            b')' => depth -= 1,
+            b'\\' => {
+                let escaped = *bytes.get(i)?;
+                i += 1;
+                match escaped {
+                    b'n' => value.push(b'\n'),
+                    b'r' => value.push(b'\r'),
+                    b't' => value.push(b'\t'),
+                    b'b' => value.push(0x08),
+                    b'f' => value.push(0x0c),
+                    b'0'..=b'7' => {
+                        let mut code = u32::from(escaped - b'0');
+                        // Up to three digits.
+                        for _ in 0..2 {
+                            match bytes.get(i) {
+                                Some(&digit @ b'0'..=b'7') => {
+                                    code = code * 8 + u32::from(digit - b'0');
+                                    i += 1;
+                                }
+                                _ => break,
+                            }
+                        }
+                        value.push(code as u8);
+                    }
+                    // A line continuation.
+                    b'\r' | b'\n' => {}
+                    other => value.push(other),
+                }
+                continue;
+            }
+            _ => {}
+        }
+        value.push(b);
+    }
+}
+
+/// Every `/ByteRange [a b c d]` array in the file.
+fn byte_ranges(file: &[u8]) -> Vec<[usize; 4]> {
+    const KEY: &[u8] = b"/ByteRange";
+    let mut ranges = Vec::new();
+    let mut position = 0;
+    while let Some(found) = find(&file[position..], KEY) {
+        position += found + KEY.len();
+        let rest = &file[position..file.len().min(position + 100)];
+        let Some(end) = rest.iter().position(|&b| b == b']') else {
+            continue;
+        };
+        let values: Vec<usize> = String::from_utf8_lossy(&rest[..end])
+            .trim_start()
+            .trim_start_matches('[')
+            .split_whitespace()
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        if let [a, b, c, d] = values[..]
+            && a <= b
+            && b <= c
+            && c.saturating_add(d) <= file.len()
+        {
+            ranges.push([a, b, c, d]);
+        }
+    }
+    ranges
+}
+
+/// The signature between the two signed ranges, stored as `<hex>`.
+fn signed_contents(file: &[u8], range: &[usize; 4]) -> Option<Vec<u8>> {
+    let hex = file.get(range[1]..range[2])?;
+    let hex: Vec<u8> = hex.iter().copied().filter(u8::is_ascii_hexdigit).collect();
+    let bytes: Option<Vec<u8>> = hex
+        .chunks_exact(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect();
+    bytes.map(|bytes| trim(&bytes).to_vec())
+}
+
+/// The signature with the zero padding reserved for it removed.
+fn trim(contents: &[u8]) -> &[u8] {
+    let end = contents.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+    &contents[..end]
+}
+
+/// The subject common name of the first certificate in a PKCS#7 signature: the first
+/// common name after the certificate's validity period, which sits between its issuer
+/// and subject.
+fn signer_name(der: &[u8]) -> Option<String> {
+    let validity = (0..der.len().saturating_sub(17)).find(|&i| {
+        der[i] == 0x17 && der[i + 1] == 0x0d && der[i + 15] == 0x17 && der[i + 16] == 0x0d
+    })?;
+    let start = validity + find(&der[validity..], COMMON_NAME_OID)? + COMMON_NAME_OID.len();
+    let (&tag, rest) = der[start..].split_first()?;
+    let (&length, rest) = rest.split_first()?;
+    let value = rest.get(..length as usize)?;
+    match tag {
+        // BMPString
+        0x1e => String::from_utf16(
+            &value
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>(),
+        )
+        .ok(),
+        // UTF8String, PrintableString, IA5String, TeletexString
+        0x0c | 0x13 | 0x16 | 0x14 => Some(String::from_utf8_lossy(value).into_owned()),
+        _ => None,
+    }
+}
+
+/// `D:YYYYMMDDHHmmSS+HH'mm'` as `YYYY-MM-DD HH:mm:SS +HH:mm`, or unchanged if it does
+/// not look like a PDF date.
+fn format_date(date: &str) -> String {
+    let digits = date.trim_start_matches("D:");
+    if digits.len() < 14 || !digits[..14].bytes().all(|b| b.is_ascii_digit()) {
+        return date.to_string();
+    }
+    let zone = digits[14..].replace('\'', "");
+    let zone = match zone.as_str() {
+        "" => String::new(),
+        "Z" => " UTC".to_string(),
+        zone if zone.len() == 5 => format!(" {}:{}", &zone[..3], &zone[3..]),
+        zone => format!(" {}", zone),
+    };
+    format!(
+        "{}-{}-{} {}:{}:{}{}",
+        &digits[..4],
+        &digits[4..6],
+        &digits[6..8],
+        &digits[8..10],
+        &digits[10..12],
+        &digits[12..14],
+        zone
+    )
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::paths;
+
+const STATE_FILE: &str = "state.json";
+const MAX_DOCUMENTS: usize = 1000;
+/// Bytes hashed from each end of a file to identify it.
+const HASH_SAMPLE: u64 = 1 << 20;
+
+/// Per-document viewer state, keyed by a hash of the file contents so it survives
+/// renames and moves.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct StateStore {
+    pub documents: BTreeMap<String, DocumentState>,
+    /// Window geometry at the last exit.
+    pub window: Option<WindowGeometry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub width: f32,
+    pub height: f32,
+    pub x: Option<f32>,
+    pub y: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentState {
+    /// Where the document was last opened from.
+    pub path: PathBuf,
+    pub last_page: usize,
+    pub zoom: f32,
+    /// Clockwise page rotation in degrees.
+    pub rotation: u16,
+    pub marks: BTreeMap<char, usize>,
+    /// The invert/recolor/tint choice last made for this document, if any.
+    pub color_mode: Option<String>,
+    /// Seconds since the Unix epoch; the oldest entries are dropped first.
+    pub last_opened: u64,
+}
+
+impl Default for DocumentState {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            last_page: 0,
+            zoom: 1.0,
+            rotation: 0,
+            marks: BTreeMap::new(),
+            color_mode: None,
+            last_opened: 0,
+        }
+    }
+}
+
+impl StateStore {
+    pub fn file_path() -> Result<PathBuf, Box<dyn Error>> {
+        let dir = paths::data_dir().ok_or("Could not determine the data directory")?;
+        Ok(dir.join(STATE_FILE))
+    }
+
+    pub fn load() -> Result<Self, Box<dyn Error>> {
+        let path = Self::file_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let data = fs::read(&path)?;
+        serde_json::from_slice(&data)
+            .map_err(|e| format!("Corrupt state file {}: {}", path.display(), e).into())
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let path = Self::file_path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// The saved state for `key`, or defaults for a document seen for the first time.
+    pub fn get(&self, key: &str) -> DocumentState {
+        self.documents.get(key).cloned().unwrap_or_default()
+    }
+
+    /// Stores `state` under `key` and writes the store.
+    pub fn update(key: &str, mut state: DocumentState) -> Result<(), Box<dyn Error>> {
+        state.last_opened = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        Self::modify(|store| {
+            store.documents.insert(key.to_string(), state);
+            while store.documents.len() > MAX_DOCUMENTS {
+                let oldest = store
+                    .documents
+                    .iter()
+                    .min_by_key(|(_, state)| state.last_opened)
+                    .map(|(key, _)| key.clone());
+                match oldest {
+                    Some(key) => store.documents.remove(&key),
+                    None => break,
+                };
+            }
+        })
+    }
+
+    /// Documents by when they were last left, most recent first.
+    pub fn recent(&self) -> Vec<&DocumentState> {
+        let mut documents: Vec<&DocumentState> = self
+            .documents
+            .values()
+            .filter(|state| !state.path.as_os_str().is_empty())
+            .collect();
+        documents.sort_by_key(|state| std::cmp::Reverse(state.last_opened));
+        // A file edited in place gets a new key; keep its latest state only.
+        let mut seen = std::collections::HashSet::new();
+        documents.retain(|state| seen.insert(&state.path));
+        documents
+    }
+
+    pub fn set_window(geometry: WindowGeometry) -> Result<(), Box<dyn Error>> {
+        Self::modify(|store| store.window = Some(geometry))
+    }
+
+    /// Re-reads the store before changing it so concurrent viewers do not drop each
+    /// other's entries.
+    fn modify(change: impl FnOnce(&mut Self)) -> Result<(), Box<dyn Error>> {
+        let mut store = Self::load().unwrap_or_default();
+        change(&mut store);
+        store.save()
+    }
+}
+
+/// Identifies a file by its size and the FNV-1a hash of its first and last `HASH_SAMPLE`
+/// bytes, which is cheap even for very large PDFs.
+pub fn document_key(path: &Path) -> Result<String, Box<dyn Error>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    let mut feed = |bytes: &[u8]| {
+        for &byte in bytes {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+
+    let mut buffer = Vec::new();
+    (&mut file).take(HASH_SAMPLE).read_to_end(&mut buffer)?;
+    feed(&buffer);
+    if size > HASH_SAMPLE {
+        file.seek(SeekFrom::Start(
+            size.saturating_sub(HASH_SAMPLE).max(HASH_SAMPLE),
+        ))?;
+        buffer.clear();
+        file.read_to_end(&mut buffer)?;
+        feed(&buffer);
+    }
+
+    Ok(format!("{:016x}-{}", hash, size))
+}
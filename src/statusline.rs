@@ -0,0 +1,38 @@
+/// The `{field}` placeholders a `statusline` format can use.
+pub const FIELDS: &[&str] = &["position", "page", "pages", "label", "percent", "chapter"];
+
+/// Checks that every `{...}` in `format` names one of `FIELDS`.
+pub fn validate(format: &str) -> Result<(), String> {
+    let mut unknown = None;
+    expand(format, |field| {
+        if !FIELDS.contains(&field) {
+            unknown.get_or_insert_with(|| field.to_string());
+        }
+        String::new()
+    });
+    match unknown {
+        Some(field) => Err(format!(
+            "unknown statusline field '{{{}}}' (expected one of: {})",
+            field,
+            FIELDS.join(", ")
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Replaces each `{field}` in `format` with `value(field)`; braces that do not close
+/// are kept as they are.
+pub fn expand(format: &str, mut value: impl FnMut(&str) -> String) -> String {
+    let mut expanded = String::new();
+    let mut rest = format;
+    while let Some(open) = rest.find('{') {
+        let Some(close) = rest[open..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..open]);
+        expanded.push_str(&value(&rest[open + 1..open + close]));
+        rest = &rest[open + close + 1..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
@@ -0,0 +1,263 @@
+//! SyncTeX forward search: from a line of a TeX source to the boxes it produced, read
+//! from the `.synctex.gz` (or `.synctex`) file TeX writes next to the PDF with
+//! `-synctex=1`. Positions in that file are in scaled points from the page's top-left
+//! corner; `find` returns them in PDF points like the D-Bus highlights.
+
+use flate2::read::MultiGzDecoder;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::dbus::Rect;
+
+/// Scaled points per PDF point (big point).
+const SP_PER_BP: f64 = 65781.76;
+/// How many lines past the requested one to look at when it produced nothing, e.g. a
+/// blank line or a comment.
+const LINE_LOOKAHEAD: usize = 100;
+
+/// A source location, given as `line:column:file.tex`. The column is accepted for
+/// compatibility but not used, as TeX does not record it.
+#[derive(Debug, Clone)]
+pub struct Forward {
+    /// 1-based.
+    pub line: usize,
+    pub input: PathBuf,
+}
+
+impl Forward {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        const USAGE: &str = "Expected line:column:file.tex";
+        let mut parts = spec.splitn(3, ':');
+        let (Some(line), Some(column), Some(input)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return Err(USAGE.to_string());
+        };
+        let line = line.trim().parse().map_err(|_| USAGE)?;
+        column.trim().parse::<i64>().map_err(|_| USAGE)?;
+        if input.is_empty() {
+            return Err(USAGE.to_string());
+        }
+        Ok(Self {
+            line,
+            input: PathBuf::from(input),
+        })
+    }
+}
+
+/// A node TeX recorded for a source line.
+struct Record {
+    /// 0-based.
+    page: usize,
+    tag: u32,
+    line: usize,
+    rect: Rect,
+}
+
+/// The page index `forward` ended up on in `pdf` and the rectangles of the lines it
+/// produced there.
+pub fn find(pdf: &Path, forward: &Forward) -> Result<(usize, Vec<Rect>), String> {
+    let (path, contents) = read(pdf)?;
+    let base = path.parent().unwrap_or(Path::new("."));
+    let (inputs, records) = parse(&contents);
+
+    let wanted = canonical(Path::new("."), &forward.input);
+    let mut tags: Vec<u32> = inputs
+        .iter()
+        .filter(|(_, input)| canonical(base, input) == wanted)
+        .map(|&(tag, _)| tag)
+        .collect();
+    if tags.is_empty() {
+        // The paths may not resolve the same way, e.g. when TeX ran elsewhere.
+        tags = inputs
+            .iter()
+            .filter(|(_, input)| input.file_name() == forward.input.file_name())
+            .map(|&(tag, _)| tag)
+            .collect();
+    }
+    if tags.is_empty() {
+        return Err(format!(
+            "{} is not part of {}",
+            forward.input.display(),
+            pdf.display()
+        ));
+    }
+
+    for line in forward.line..forward.line + LINE_LOOKAHEAD {
+        let found: Vec<&Record> = records
+            .iter()
+            .filter(|record| record.line == line && tags.contains(&record.tag))
+            .collect();
+        let Some(page) = found.iter().map(|record| record.page).min() else {
+            continue;
+        };
+        let mut rects: Vec<Rect> = Vec::new();
+        for record in found.iter().filter(|record| record.page == page) {
+            if !rects.contains(&record.rect) {
+                rects.push(record.rect);
+            }
+        }
+        return Ok((page, rects));
+    }
+    Err(format!(
+        "Nothing on or after line {} of {}",
+        forward.line,
+        forward.input.display()
+    ))
+}
+
+/// The SyncTeX file of `pdf` and its text.
+fn read(pdf: &Path) -> Result<(PathBuf, String), String> {
+    let compressed = pdf.with_extension("synctex.gz");
+    let path = if compressed.is_file() {
+        compressed
+    } else {
+        pdf.with_extension("synctex")
+    };
+    let mut bytes = Vec::new();
+    let result = File::open(&path).and_then(|file| {
+        if path.extension().is_some_and(|extension| extension == "gz") {
+            MultiGzDecoder::new(file).read_to_end(&mut bytes)
+        } else {
+            (&file).read_to_end(&mut bytes)
+        }
+    });
+    result.map_err(|e| format!("No SyncTeX data for {}: {}", pdf.display(), e))?;
+    Ok((path, String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// `input` resolved against `base`, the directory TeX ran in.
+fn canonical(base: &Path, input: &Path) -> PathBuf {
+    let path = base.join(input);
+    fs::canonicalize(&path).unwrap_or(path)
+}
+
+/// The input files by tag, and every node recorded with a position. Nodes other than
+/// horizontal boxes stand for the box around them, so a match highlights whole lines.
+fn parse(contents: &str) -> (Vec<(u32, PathBuf)>, Vec<Record>) {
+    let mut inputs = Vec::new();
+    let mut records = Vec::new();
+    let (mut unit, mut magnification, mut x_offset, mut y_offset) = (1.0, 1000.0, 0.0, 0.0);
+    let mut page = None;
+    // The rectangle of each open box; `None` for vertical ones.
+    let mut boxes: Vec<Option<Rect>> = Vec::new();
+
+    for line in contents.lines() {
+        if let Some(input) = line.strip_prefix("Input:") {
+            if let Some((tag, path)) = input.split_once(':')
+                && let Ok(tag) = tag.parse()
+            {
+                inputs.push((tag, PathBuf::from(path)));
+            }
+            continue;
+        }
+        if line.starts_with("Postamble:") {
+            break;
+        }
+        let setting = |name: &str| line.strip_prefix(name).and_then(|v| v.parse::<f64>().ok());
+        if let Some(value) = setting("Unit:") {
+            unit = value;
+        } else if let Some(value) = setting("Magnification:") {
+            magnification = value;
+        } else if let Some(value) = setting("X Offset:") {
+            x_offset = value;
+        } else if let Some(value) = setting("Y Offset:") {
+            y_offset = value;
+        }
+
+        let Some(kind) = line.chars().next() else {
+            continue;
+        };
+        let fields = &line[kind.len_utf8()..];
+        match kind {
+            '{' => {
+                page = fields
+                    .parse::<usize>()
+                    .ok()
+                    .map(|page| page.saturating_sub(1));
+                boxes.clear();
+                continue;
+            }
+            '}' => {
+                page = None;
+                continue;
+            }
+            ')' | ']' => {
+                boxes.pop();
+                continue;
+            }
+            _ => {}
+        }
+        let Some(page) = page else {
+            continue;
+        };
+        let Some(node) = Node::parse(fields) else {
+            continue;
+        };
+        let scale = unit * magnification / 1000.0 / SP_PER_BP;
+        let x = (node.x + x_offset) * scale;
+        let y = (node.y + y_offset) * scale;
+        let own = node.size.map(|(width, height, depth)| {
+            let (width, height, depth) = (width * scale, height * scale, depth * scale);
+            (x.min(x + width), y - height, x.max(x + width), y + depth)
+        });
+        let rect = match kind {
+            '(' => {
+                boxes.push(own);
+                own
+            }
+            '[' => {
+                boxes.push(None);
+                None
+            }
+            'h' => own,
+            'v' => None,
+            _ => boxes.last().copied().flatten(),
+        };
+        if let Some(rect) = rect {
+            records.push(Record {
+                page,
+                tag: node.tag,
+                line: node.line,
+                rect,
+            });
+        }
+    }
+    (inputs, records)
+}
+
+/// The fields of a node line after its kind: `tag,line[,column]:x,y[:W,H,D]`.
+struct Node {
+    tag: u32,
+    line: usize,
+    x: f64,
+    y: f64,
+    size: Option<(f64, f64, f64)>,
+}
+
+impl Node {
+    fn parse(fields: &str) -> Option<Self> {
+        let mut parts = fields.split(':');
+        let mut source = parts.next()?.split(',');
+        let tag = source.next()?.parse().ok()?;
+        let line = source.next()?.parse().ok()?;
+        let numbers = |part: Option<&str>| -> Option<Vec<f64>> {
+            part?.split(',').map(|value| value.parse().ok()).collect()
+        };
+        let position = numbers(parts.next())?;
+        let [x, y] = position[..] else {
+            return None;
+        };
+        let size = match numbers(parts.next()).as_deref() {
+            Some(&[width, height, depth]) => Some((width, height, depth)),
+            _ => None,
+        };
+        Some(Self {
+            tag,
+            line,
+            x,
+            y,
+            size,
+        })
+    }
+}
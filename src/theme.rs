@@ -0,0 +1,231 @@
+use iced::Color;
+use serde::Deserialize;
+use std::fs;
+
+use crate::paths;
+
+/// Names accepted for a page tint besides `"#rrggbb"`.
+const TINT_PRESETS: &[(&str, [u8; 3])] =
+    &[("sepia", [240, 215, 170]), ("paper-cream", [250, 243, 225])];
+
+/// Colors for selections, search results and link hints that stay distinct without
+/// red-green vision, from the Okabe-Ito palette. They apply on top of the theme.
+const HIGHLIGHT_PALETTES: &[(&str, &str)] = &[
+    ("default", ""),
+    (
+        "deuteranopia",
+        r##"
+        selection = "#0072b2"
+        search = "#e69f00"
+        hint_background = "#f0e442"
+        hint_foreground = "#000000"
+        "##,
+    ),
+    (
+        "protanopia",
+        r##"
+        selection = "#56b4e9"
+        search = "#f0e442"
+        hint_background = "#0072b2"
+        hint_foreground = "#ffffff"
+        "##,
+    ),
+];
+
+const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("dark", ""),
+    (
+        "light",
+        r##"
+        background = "#d8d8d8"
+        foreground = "#202020"
+        statusbar_background = "#f0f0f0"
+        statusbar_foreground = "#202020"
+        mode_normal = "#3a7bd5"
+        mode_command = "#c07000"
+        mode_overlay = "#2e8b57"
+        highlight = "#b8cff0"
+        muted = "#707070"
+        "##,
+    ),
+    (
+        "solarized",
+        r##"
+        background = "#002b36"
+        foreground = "#93a1a1"
+        statusbar_background = "#073642"
+        statusbar_foreground = "#eee8d5"
+        mode_normal = "#268bd2"
+        mode_command = "#b58900"
+        mode_overlay = "#859900"
+        highlight = "#2f5866"
+        muted = "#586e75"
+        "##,
+    ),
+];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub background: HexColor,
+    pub foreground: HexColor,
+    pub statusbar_background: HexColor,
+    pub statusbar_foreground: HexColor,
+    pub mode_normal: HexColor,
+    pub mode_command: HexColor,
+    pub mode_overlay: HexColor,
+    /// Selected rows and other emphasised regions.
+    pub highlight: HexColor,
+    pub muted: HexColor,
+    /// The visual-mode selection and focused form field; `highlight` if unset.
+    pub selection: Option<HexColor>,
+    /// Lines found by SyncTeX and rectangles highlighted over D-Bus; `highlight` if
+    /// unset.
+    pub search: Option<HexColor>,
+    /// Link-hint labels; `mode_command` and `statusbar_foreground` if unset.
+    pub hint_background: Option<HexColor>,
+    pub hint_foreground: Option<HexColor>,
+}
+
+/// A color written as `"#rrggbb"` in the config file.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub Color);
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: HexColor(Color::BLACK),
+            foreground: HexColor(Color::WHITE),
+            statusbar_background: HexColor(Color::from_rgb8(30, 30, 30)),
+            statusbar_foreground: HexColor(Color::WHITE),
+            mode_normal: HexColor(Color::from_rgb8(60, 110, 180)),
+            mode_command: HexColor(Color::from_rgb8(190, 120, 30)),
+            mode_overlay: HexColor(Color::from_rgb8(70, 140, 90)),
+            highlight: HexColor(Color::from_rgb8(70, 70, 110)),
+            muted: HexColor(Color::from_rgb8(150, 150, 150)),
+            selection: None,
+            search: None,
+            hint_background: None,
+            hint_foreground: None,
+        }
+    }
+}
+
+impl TryFrom<String> for HexColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid color '{}', expected \"#rrggbb\"", value);
+        let hex = value.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(HexColor(Color::from_rgb8(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+        )))
+    }
+}
+
+/// What white becomes when pages are tinted: one of `TINT_PRESETS` or `"#rrggbb"`.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TintColor(pub Color);
+
+impl TintColor {
+    /// The preset name, or else `#rrggbb`.
+    pub fn name(&self) -> String {
+        let [r, g, b, _] = self.0.into_rgba8();
+        match TINT_PRESETS.iter().find(|(_, rgb)| *rgb == [r, g, b]) {
+            Some((name, _)) => name.to_string(),
+            None => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+
+    pub fn presets() -> Vec<Self> {
+        TINT_PRESETS
+            .iter()
+            .map(|(_, [r, g, b])| TintColor(Color::from_rgb8(*r, *g, *b)))
+            .collect()
+    }
+}
+
+impl TryFrom<String> for TintColor {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some((_, [r, g, b])) = TINT_PRESETS.iter().find(|(name, _)| *name == value) {
+            return Ok(TintColor(Color::from_rgb8(*r, *g, *b)));
+        }
+        HexColor::try_from(value.clone())
+            .map(|color| TintColor(color.0))
+            .map_err(|_| {
+                let presets: Vec<&str> = TINT_PRESETS.iter().map(|(name, _)| *name).collect();
+                format!(
+                    "invalid tint '{}', expected \"#rrggbb\" or one of: {}",
+                    value,
+                    presets.join(", ")
+                )
+            })
+    }
+}
+
+impl Theme {
+    /// Resolves a built-in theme or `themes/<name>.toml` in the config directory, then
+    /// applies the highlight `palette` and the `[colors]` overrides on top.
+    pub fn resolve(name: &str, palette: &str, overrides: &toml::Table) -> Result<Self, String> {
+        let Some((_, palette)) = HIGHLIGHT_PALETTES.iter().find(|(n, _)| *n == palette) else {
+            let names: Vec<&str> = HIGHLIGHT_PALETTES.iter().map(|(n, _)| *n).collect();
+            return Err(format!(
+                "unknown highlight_palette '{}' (expected one of: {})",
+                palette,
+                names.join(", ")
+            ));
+        };
+        let source = match BUILTIN_THEMES.iter().find(|(n, _)| *n == name) {
+            Some((_, source)) => source.to_string(),
+            None => {
+                let path = paths::config_dir()
+                    .map(|dir| dir.join("themes").join(format!("{}.toml", name)))
+                    .ok_or_else(|| format!("unknown theme '{}'", name))?;
+                fs::read_to_string(&path).map_err(|e| {
+                    let builtin: Vec<&str> = BUILTIN_THEMES.iter().map(|(n, _)| *n).collect();
+                    format!(
+                        "unknown theme '{}' (built-in: {}; {}: {})",
+                        name,
+                        builtin.join(", "),
+                        path.display(),
+                        e
+                    )
+                })?
+            }
+        };
+
+        let mut table: toml::Table =
+            toml::from_str(&source).map_err(|e| format!("theme '{}': {}", name, e))?;
+        table.extend(toml::from_str::<toml::Table>(palette).map_err(|e| e.to_string())?);
+        table.extend(overrides.clone());
+        table
+            .try_into()
+            .map_err(|e| format!("theme '{}': {}", name, e))
+    }
+
+    pub fn selection(&self) -> Color {
+        self.selection.unwrap_or(self.highlight).0
+    }
+
+    pub fn search(&self) -> Color {
+        self.search.unwrap_or(self.highlight).0
+    }
+
+    pub fn hint_background(&self) -> Color {
+        self.hint_background.unwrap_or(self.mode_command).0
+    }
+
+    pub fn hint_foreground(&self) -> Color {
+        self.hint_foreground.unwrap_or(self.statusbar_foreground).0
+    }
+}
@@ -0,0 +1,730 @@
+use iced::widget::{
+    column, container, image, mouse_area, pin, responsive, row, space, text, Column, Stack,
+};
+use iced::{mouse, Color, ContentFit, Element, Length, Point, Rectangle, Size, Vector};
+
+use crate::hints::LinkHints;
+use crate::ink::InkInput;
+use crate::messages::Level;
+use crate::outline::TocPanel;
+use crate::overlay::{Overlay, OverlayTarget};
+use crate::theme::Theme;
+use crate::Message;
+
+const OVERLAY_VISIBLE_ROWS: usize = 20;
+const TOC_WIDTH: f32 = 300.0;
+const TOC_ROW_HEIGHT: f32 = 22.0;
+const SCROLLBAR_WIDTH: f32 = 4.0;
+const SCROLLBAR_MIN_LENGTH: f32 = 12.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Normal,
+    Command,
+    Overlay,
+    Toc,
+    Hint,
+    Visual,
+    Draw,
+    Form,
+}
+
+impl Mode {
+    fn label(self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Command => "COMMAND",
+            Mode::Overlay => "LIST",
+            Mode::Toc => "TOC",
+            Mode::Hint => "HINT",
+            Mode::Visual => "VISUAL",
+            Mode::Draw => "DRAW",
+            Mode::Form => "FORM",
+        }
+    }
+
+    fn color(self, theme: &Theme) -> Color {
+        match self {
+            Mode::Normal => theme.mode_normal.0,
+            Mode::Command | Mode::Hint | Mode::Visual | Mode::Draw | Mode::Form => {
+                theme.mode_command.0
+            }
+            Mode::Overlay | Mode::Toc => theme.mode_overlay.0,
+        }
+    }
+}
+
+/// How a page is laid out in its area: fitted whole inside the padding or to its
+/// width, scaled by `zoom`, and moved `pan` pixels left and `scroll` pixels up where
+/// that makes it larger than the area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageFit {
+    pub aspect_ratio: f32,
+    pub padding: f32,
+    pub fit_width: bool,
+    pub zoom: f32,
+    pub pan: f32,
+    pub scroll: f32,
+}
+
+pub fn page_area<'a>(
+    current_image: Option<&image::Handle>,
+    fit: PageFit,
+    theme: &Theme,
+) -> Element<'a, Message> {
+    let background = theme.background.0;
+
+    if let Some(handle) = current_image
+        && (fit.fit_width || fit.zoom != 1.0)
+        && matches!(handle, image::Handle::Rgba { .. })
+    {
+        let handle = handle.clone();
+        container(responsive(move |size: Size| {
+            page_slice(&handle, page_frame(size, fit), size, fit.padding, 1.0)
+        }))
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(move |_theme| container::Style {
+            background: Some(background.into()),
+            ..container::Style::default()
+        })
+        .into()
+    } else if let Some(handle) = current_image {
+        container(
+            image(handle.clone())
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .content_fit(ContentFit::Contain),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding(fit.padding)
+        .style(move |_theme| container::Style {
+            background: Some(background.into()),
+            ..container::Style::default()
+        })
+        .into()
+    } else {
+        container(text("Loading...").size(20).color(theme.foreground.0))
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x(Length::Fill)
+            .center_y(Length::Fill)
+            .style(move |_theme| container::Style {
+                background: Some(background.into()),
+                ..container::Style::default()
+            })
+            .into()
+    }
+}
+
+/// The page turned away from, drawn over the new one while it fades out with
+/// `opacity` or slides out sideways by `offset` times the width of the area.
+pub fn page_transition<'a>(
+    previous: &image::Handle,
+    fit: PageFit,
+    opacity: f32,
+    offset: f32,
+) -> Element<'a, Message> {
+    let previous = previous.clone();
+    responsive(move |size: Size| {
+        let page = page_frame(size, fit);
+        let page = Rectangle {
+            x: page.x + offset * size.width,
+            ..page
+        };
+        page_slice(&previous, page, size, fit.padding, opacity)
+    })
+    .into()
+}
+
+/// The part of a page image framed by `page` that is visible in an area of `size`,
+/// cropped from the bitmap rather than scaled whole.
+fn page_slice<'a>(
+    handle: &image::Handle,
+    page: Rectangle,
+    size: Size,
+    padding: f32,
+    opacity: f32,
+) -> Element<'a, Message> {
+    let image::Handle::Rgba { width, height, .. } = handle else {
+        return space().into();
+    };
+    let Some(visible) = page.intersection(&inner_area(size, padding)) else {
+        return space().into();
+    };
+    let scale = *width as f32 / page.width;
+    let left = ((visible.x - page.x) * scale) as u32;
+    let top = ((visible.y - page.y) * scale) as u32;
+    let crop = Rectangle {
+        x: left,
+        y: top,
+        width: ((visible.width * scale).round() as u32).min(width - left),
+        height: ((visible.height * scale).round() as u32).min(height - top),
+    };
+    pin(image(handle.clone())
+        .crop(crop)
+        .width(visible.width)
+        .height(visible.height)
+        .content_fit(ContentFit::Fill)
+        .opacity(opacity))
+    .x(visible.x)
+    .y(visible.y)
+    .into()
+}
+
+/// `section` is shown right-aligned, e.g. the outline entry containing the current page.
+/// `large` enlarges the text, for e-ink displays.
+pub fn status_bar<'a>(
+    mode: Mode,
+    status_text: String,
+    progress: Option<String>,
+    section: Option<String>,
+    document_badge: Option<String>,
+    large: bool,
+    theme: &Theme,
+) -> Element<'a, Message> {
+    let (text_size, badge_size) = if large { (20, 17) } else { (14, 12) };
+    let background = theme.statusbar_background.0;
+    let muted = theme.muted.0;
+    let mode_background = mode.color(theme);
+
+    let badge = container(
+        text(mode.label())
+            .size(badge_size)
+            .color(theme.statusbar_foreground.0),
+    )
+    .padding([1, 6])
+    .style(move |_theme| container::Style {
+        background: Some(mode_background.into()),
+        ..container::Style::default()
+    });
+
+    container(
+        row![
+            badge,
+            text(status_text)
+                .size(text_size)
+                .color(theme.statusbar_foreground.0)
+                .width(Length::Fill),
+            progress.map(|progress| text(progress).size(badge_size).color(muted)),
+            text(section.unwrap_or_default())
+                .size(text_size)
+                .color(theme.muted.0)
+        ]
+        .push(document_badge.map(|badge| {
+            container(text(badge).size(badge_size).color(muted))
+                .padding([1, 6])
+                .style(move |_theme| container::Style {
+                    border: iced::border::rounded(3).color(muted).width(1),
+                    ..container::Style::default()
+                })
+        }))
+        .spacing(8),
+    )
+    .width(Length::Fill)
+    .padding(5)
+    .style(move |_theme| container::Style {
+        background: Some(background.into()),
+        ..container::Style::default()
+    })
+    .into()
+}
+
+/// One viewport of a split view, outlined when it has the focus.
+pub fn pane<'a>(
+    content: Element<'a, Message>,
+    focused: bool,
+    theme: &Theme,
+) -> Element<'a, Message> {
+    let border = if focused {
+        theme.mode_overlay.0
+    } else {
+        theme.background.0
+    };
+
+    container(content)
+        .width(Length::FillPortion(1))
+        .height(Length::Fill)
+        .style(move |_theme| container::Style {
+            border: iced::border::width(1).color(border),
+            ..container::Style::default()
+        })
+        .into()
+}
+
+/// The open documents, the shown one highlighted; clicking one shows it.
+pub fn tabline<'a>(labels: Vec<String>, current: usize, theme: &Theme) -> Element<'a, Message> {
+    let background = theme.statusbar_background.0;
+    let accent = theme.mode_overlay.0;
+    let foreground = theme.statusbar_foreground.0;
+
+    let tabs = labels.into_iter().enumerate().map(|(index, label)| {
+        let shown = index == current;
+        let tab = container(text(label).size(14).color(foreground))
+            .padding([3, 10])
+            .style(move |_theme| container::Style {
+                background: shown.then(|| accent.into()),
+                ..container::Style::default()
+            });
+        mouse_area(tab)
+            .on_press(Message::ShowBuffer(index))
+            .interaction(mouse::Interaction::Pointer)
+            .into()
+    });
+
+    container(row(tabs).spacing(2))
+        .width(Length::Fill)
+        .style(move |_theme| container::Style {
+            background: Some(background.into()),
+            ..container::Style::default()
+        })
+        .into()
+}
+
+/// The whole window when the document given at startup could not be opened.
+pub fn startup_error<'a>(message: String, theme: &Theme) -> Element<'a, Message> {
+    let background = theme.background.0;
+    column![
+        banner(message, theme),
+        container(
+            text("Close the window to quit.")
+                .size(14)
+                .color(theme.muted.0)
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .padding(10)
+        .style(move |_theme| container::Style {
+            background: Some(background.into()),
+            ..container::Style::default()
+        })
+    ]
+    .into()
+}
+
+/// A warning strip above the page, dismissed by the next key press.
+pub fn banner<'a>(message: String, theme: &Theme) -> Element<'a, Message> {
+    let background = theme.statusbar_background.0;
+    let accent = theme.mode_overlay.0;
+
+    container(text(message).size(14).color(theme.statusbar_foreground.0))
+        .width(Length::Fill)
+        .padding([4, 10])
+        .style(move |_theme| container::Style {
+            background: Some(background.into()),
+            border: iced::border::width(1).color(accent),
+            ..container::Style::default()
+        })
+        .into()
+}
+
+/// The large page number shown while flipping quickly through pages.
+pub fn osd<'a>(label: String, theme: &Theme) -> Element<'a, Message> {
+    let background = Color {
+        a: 0.8,
+        ..theme.statusbar_background.0
+    };
+
+    let badge = container(text(label).size(64).color(theme.statusbar_foreground.0))
+        .padding([12, 28])
+        .style(move |_theme| container::Style {
+            background: Some(background.into()),
+            border: iced::border::rounded(8),
+            ..container::Style::default()
+        });
+
+    container(badge)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .into()
+}
+
+/// Short messages stacked in the bottom right corner of the page area, newest at the
+/// bottom; warnings and errors are outlined.
+pub fn toasts<'a>(messages: Vec<(Level, String)>, theme: &Theme) -> Element<'a, Message> {
+    let background = Color {
+        a: 0.8,
+        ..theme.statusbar_background.0
+    };
+    let foreground = theme.statusbar_foreground.0;
+    let outline = theme.mode_command.0;
+
+    let labels = messages.into_iter().map(|(level, message)| {
+        let border = match level {
+            Level::Info => iced::border::rounded(4),
+            Level::Warning | Level::Error => iced::border::rounded(4).color(outline).width(1),
+        };
+        container(text(message).size(14).color(foreground))
+            .padding([4, 10])
+            .style(move |_theme| container::Style {
+                background: Some(background.into()),
+                border,
+                ..container::Style::default()
+            })
+            .into()
+    });
+
+    container(
+        Column::with_children(labels)
+            .spacing(6)
+            .align_x(iced::Alignment::End),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .padding(12)
+    .align_right(Length::Fill)
+    .align_bottom(Length::Fill)
+    .into()
+}
+
+/// A thin bar along the right edge spanning `extent` of the height from `top`, both
+/// fractions of the whole document.
+pub fn scrollbar<'a>(top: f32, extent: f32, theme: &Theme) -> Element<'a, Message> {
+    let color = Color {
+        a: 0.6,
+        ..theme.muted.0
+    };
+
+    responsive(move |size: Size| {
+        let height = (extent * size.height).max(SCROLLBAR_MIN_LENGTH);
+        let y = (top * size.height).min(size.height - height).max(0.0);
+        let thumb = container(space())
+            .width(SCROLLBAR_WIDTH)
+            .height(height)
+            .style(move |_theme| container::Style {
+                background: Some(color.into()),
+                border: iced::border::rounded(SCROLLBAR_WIDTH / 2.0),
+                ..container::Style::default()
+            });
+        pin(thumb).x(size.width - SCROLLBAR_WIDTH - 2.0).y(y).into()
+    })
+    .into()
+}
+
+/// Link-hint labels placed over the page as laid out by `page_area`.
+pub fn link_hints<'a>(hints: &LinkHints, fit: PageFit, theme: &Theme) -> Element<'a, Message> {
+    let labels: Vec<(String, iced::Rectangle)> = hints
+        .visible()
+        .map(|(label, link)| (label.to_uppercase(), link.bounds))
+        .collect();
+    let background = theme.hint_background();
+    let foreground = theme.hint_foreground();
+
+    responsive(move |size: Size| {
+        let page = page_frame(size, fit);
+        let pins = labels.iter().map(|(label, bounds)| {
+            let badge = container(text(label.clone()).size(12).color(foreground))
+                .padding([0, 3])
+                .style(move |_theme| container::Style {
+                    background: Some(background.into()),
+                    border: iced::border::rounded(2),
+                    ..container::Style::default()
+                });
+            pin(badge)
+                .x(page.x + bounds.x * page.width)
+                .y(page.y + bounds.y * page.height)
+                .into()
+        });
+        Stack::with_children(pins).into()
+    })
+    .into()
+}
+
+/// Shades `rects`, relative to the page, in `color`: the visual-mode selection or
+/// search results.
+pub fn selection<'a>(rects: Vec<Rectangle>, fit: PageFit, color: Color) -> Element<'a, Message> {
+    let shade = Color { a: 0.4, ..color };
+
+    responsive(move |size: Size| {
+        let page = page_frame(size, fit);
+        let pins = rects.iter().map(|bounds| {
+            let area = container(space())
+                .width(bounds.width * page.width)
+                .height(bounds.height * page.height)
+                .style(move |_theme| container::Style {
+                    background: Some(shade.into()),
+                    ..container::Style::default()
+                });
+            pin(area)
+                .x(page.x + bounds.x * page.width)
+                .y(page.y + bounds.y * page.height)
+                .into()
+        });
+        Stack::with_children(pins).into()
+    })
+    .into()
+}
+
+/// Drawing mode: captures the mouse over the page and shows strokes not yet rendered
+/// into the page. `pen_width` is in points, `page_width` the page width in points.
+pub fn ink_layer<'a>(
+    strokes: Vec<Vec<Point>>,
+    fit: PageFit,
+    page_width: f32,
+    color: Color,
+    pen_width: f32,
+) -> Element<'a, Message> {
+    responsive(move |size: Size| {
+        let page = page_frame(size, fit);
+        let dot = (pen_width * page.width / page_width).max(1.0);
+        let to_screen = |point: &Point| {
+            Point::new(
+                page.x + point.x * page.width,
+                page.y + point.y * page.height,
+            )
+        };
+
+        // Without a canvas, strokes are drawn as dots spaced half their width apart.
+        let mut dots = Vec::new();
+        for stroke in &strokes {
+            let mut previous: Option<Point> = None;
+            for point in stroke.iter().map(to_screen) {
+                let from = previous.unwrap_or(point);
+                let steps = (from.distance(point) / (dot / 2.0)).ceil().max(1.0) as usize;
+                for step in 1..=steps {
+                    let t = step as f32 / steps as f32;
+                    dots.push(Point::new(
+                        from.x + (point.x - from.x) * t,
+                        from.y + (point.y - from.y) * t,
+                    ));
+                }
+                previous = Some(point);
+            }
+        }
+        let pins = dots.into_iter().map(|center| {
+            let area = container(space())
+                .width(dot)
+                .height(dot)
+                .style(move |_theme| container::Style {
+                    background: Some(color.into()),
+                    border: iced::border::rounded(dot / 2.0),
+                    ..container::Style::default()
+                });
+            pin(area)
+                .x(center.x - dot / 2.0)
+                .y(center.y - dot / 2.0)
+                .into()
+        });
+
+        mouse_area(
+            Stack::with_children(pins)
+                .width(Length::Fill)
+                .height(Length::Fill),
+        )
+        .on_press(Message::Ink(InkInput::Press))
+        .on_release(Message::Ink(InkInput::Release))
+        .on_move(move |position| {
+            Message::Ink(InkInput::Move(Point::new(
+                (position.x - page.x) / page.width,
+                (position.y - page.y) / page.height,
+            )))
+        })
+        .interaction(mouse::Interaction::Crosshair)
+        .into()
+    })
+    .into()
+}
+
+/// Where the page image lands in an area of `size`: centered where it fits, as with
+/// ContentFit::Contain inside the padding, and shifted by the pan and scroll where not.
+pub fn page_frame(size: Size, fit: PageFit) -> Rectangle {
+    let available = inner_area(size, fit.padding);
+    let page = page_size(available, fit);
+    let range = scroll_range(size, fit);
+    Rectangle {
+        x: available.x + ((available.width - page.width) / 2.0).max(0.0)
+            - fit.pan.clamp(0.0, range.x),
+        y: available.y + ((available.height - page.height) / 2.0).max(0.0)
+            - fit.scroll.clamp(0.0, range.y),
+        width: page.width,
+        height: page.height,
+    }
+}
+
+/// Where the view of page `page_index` of `total_pages`, laid out by `fit` in an area
+/// of `size`, starts in the document and how much of it is seen, as fractions of it.
+pub fn document_position(
+    page_index: usize,
+    total_pages: u16,
+    size: Size,
+    fit: PageFit,
+) -> (f32, f32) {
+    let total = f32::from(total_pages.max(1));
+    let available = inner_area(size, fit.padding);
+    let page = page_size(available, fit);
+    let scroll = fit.scroll.clamp(0.0, scroll_range(size, fit).y);
+    let top = (page_index as f32 + scroll / page.height) / total;
+    let extent = (available.height / page.height).min(1.0) / total;
+    (top, extent)
+}
+
+/// How many whole pixels a page laid out by `fit` in an area of `size` can pan right
+/// and scroll down.
+pub fn scroll_range(size: Size, fit: PageFit) -> Vector {
+    let available = inner_area(size, fit.padding);
+    let page = page_size(available, fit);
+    Vector::new(
+        (page.width - available.width).max(0.0).floor(),
+        (page.height - available.height).max(0.0).floor(),
+    )
+}
+
+fn page_size(available: Rectangle, fit: PageFit) -> Size {
+    let width = if fit.fit_width {
+        available.width
+    } else {
+        available.width.min(available.height * fit.aspect_ratio)
+    };
+    let width = width * fit.zoom;
+    Size::new(width, width / fit.aspect_ratio)
+}
+
+fn inner_area(size: Size, padding: f32) -> Rectangle {
+    Rectangle {
+        x: padding,
+        y: padding,
+        width: (size.width - 2.0 * padding).max(1.0),
+        height: (size.height - 2.0 * padding).max(1.0),
+    }
+}
+
+pub fn overlay_panel<'a>(overlay: &'a Overlay, theme: &Theme) -> Element<'a, Message> {
+    let first_row = overlay
+        .selected
+        .saturating_sub(OVERLAY_VISIBLE_ROWS - 1)
+        .min(overlay.matches.len().saturating_sub(OVERLAY_VISIBLE_ROWS));
+    let highlight = theme.highlight.0;
+    let foreground = theme.statusbar_foreground.0;
+    let background = theme.statusbar_background.0;
+
+    let rows = overlay
+        .shown()
+        .skip(first_row)
+        .take(OVERLAY_VISIBLE_ROWS)
+        .map(|(i, entry)| {
+            let selected = i == overlay.selected;
+            let color = match entry.target {
+                OverlayTarget::Heading => theme.muted.0,
+                _ => foreground,
+            };
+            container(text(entry.label.as_str()).size(14).color(color))
+                .width(Length::Fill)
+                .padding([2, 8])
+                .style(move |_theme| container::Style {
+                    background: selected.then(|| highlight.into()),
+                    ..container::Style::default()
+                })
+                .into()
+        });
+
+    let body: Element<'_, Message> = if overlay.entries.is_empty() {
+        text("(empty)").size(14).color(theme.muted.0).into()
+    } else if overlay.matches.is_empty() {
+        text("(no matches)").size(14).color(theme.muted.0).into()
+    } else {
+        Column::with_children(rows).into()
+    };
+
+    let panel = container(
+        column![
+            text(overlay.title.as_str()).size(16).color(foreground),
+            overlay
+                .filter
+                .as_ref()
+                .map(|filter| text(format!("/{}", filter)).size(14).color(foreground)),
+            body
+        ]
+        .spacing(8),
+    )
+    .width(Length::Fixed(420.0))
+    .padding(12)
+    .style(move |_theme| container::Style {
+        background: Some(background.into()),
+        ..container::Style::default()
+    });
+
+    container(panel)
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .center_x(Length::Fill)
+        .center_y(Length::Fill)
+        .into()
+}
+
+/// The outline sidebar; `height` is the space available for it in pixels.
+pub fn toc_panel<'a>(toc: &TocPanel, height: f32, theme: &Theme) -> Element<'a, Message> {
+    let visible_rows = ((height / TOC_ROW_HEIGHT) as usize)
+        .saturating_sub(2)
+        .max(1);
+    let highlight = theme.highlight.0;
+    let foreground = theme.statusbar_foreground.0;
+    let muted = theme.muted.0;
+    let background = theme.statusbar_background.0;
+
+    let visible = toc.visible();
+    let selected = visible.iter().position(|&i| i == toc.selected).unwrap_or(0);
+    let first_row = selected
+        .saturating_sub(visible_rows - 1)
+        .min(visible.len().saturating_sub(visible_rows));
+
+    let rows = visible
+        .into_iter()
+        .skip(first_row)
+        .take(visible_rows)
+        .map(|i| {
+            let item = &toc.items[i];
+            let marker = if !toc.has_children(i) {
+                "  "
+            } else if toc.is_collapsed(i) {
+                "▸ "
+            } else {
+                "▾ "
+            };
+            let page = item
+                .page_index
+                .map(|p| (p + 1).to_string())
+                .unwrap_or_default();
+            let is_selected = i == toc.selected;
+            container(
+                row![
+                    text(format!(
+                        "{}{}{}",
+                        "  ".repeat(item.depth),
+                        marker,
+                        item.title
+                    ))
+                    .size(13)
+                    .color(foreground)
+                    .width(Length::Fill),
+                    text(page).size(13).color(muted)
+                ]
+                .spacing(6),
+            )
+            .width(Length::Fill)
+            .padding([2, 6])
+            .style(move |_theme| container::Style {
+                background: is_selected.then(|| highlight.into()),
+                ..container::Style::default()
+            })
+            .into()
+        });
+
+    container(
+        column![
+            text("Contents").size(15).color(foreground),
+            Column::with_children(rows)
+        ]
+        .spacing(6),
+    )
+    .width(Length::Fixed(TOC_WIDTH))
+    .height(Length::Fill)
+    .padding(8)
+    .style(move |_theme| container::Style {
+        background: Some(background.into()),
+        ..container::Style::default()
+    })
+    .into()
+}
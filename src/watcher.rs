@@ -0,0 +1,88 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime};
+
+/// How long a document must be left alone after changing before it is reloaded, so
+/// that a file still being written is not opened half-way.
+const SETTLE: Duration = Duration::from_millis(300);
+
+/// Watches a document for changes on disk, e.g. by latexmk or `typst watch`.
+pub struct FileWatcher {
+    path: PathBuf,
+    _watcher: RecommendedWatcher,
+    events: mpsc::Receiver<()>,
+    /// When the last change was seen, while waiting for it to settle.
+    changed_at: Option<Instant>,
+    /// Modification time and size when last loaded or written by lukia.
+    stamp: Option<(SystemTime, u64)>,
+}
+
+impl FileWatcher {
+    /// Watches the directory of `path` rather than the file, as many programs replace a
+    /// file instead of writing to it.
+    pub fn new(path: &Path) -> Result<Self, String> {
+        let path = fs::canonicalize(path).map_err(|e| e.to_string())?;
+        let (Some(directory), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(format!("Cannot watch {}", path.display()));
+        };
+        let name = name.to_os_string();
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event
+                    && matches!(
+                        event.kind,
+                        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+                    )
+                    && event
+                        .paths
+                        .iter()
+                        .any(|path| path.file_name() == Some(&name))
+                {
+                    let _ = tx.send(());
+                }
+            })
+            .map_err(|e| format!("Cannot watch {}: {}", path.display(), e))?;
+        watcher
+            .watch(directory, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Cannot watch {}: {}", path.display(), e))?;
+        Ok(Self {
+            stamp: stamp(&path),
+            path,
+            _watcher: watcher,
+            events: rx,
+            changed_at: None,
+        })
+    }
+
+    /// True once the document has changed and then been left alone for a moment.
+    pub fn poll(&mut self) -> bool {
+        if self.events.try_iter().count() > 0 {
+            self.changed_at = Some(Instant::now());
+        }
+        if self.changed_at.is_none_or(|at| at.elapsed() < SETTLE) {
+            return false;
+        }
+        self.changed_at = None;
+        // Gone (until it is written again) or changed only by lukia itself.
+        let stamp = stamp(&self.path);
+        if stamp.is_none_or(|(_, size)| size == 0) || stamp == self.stamp {
+            return false;
+        }
+        self.stamp = stamp;
+        true
+    }
+
+    /// Takes the document as it is now as seen, e.g. after writing annotations to it.
+    pub fn refresh(&mut self) {
+        self.stamp = stamp(&self.path);
+        self.changed_at = None;
+    }
+}
+
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}